@@ -3,10 +3,10 @@ use clap::Parser;
 use mina_indexer::{
     block::{parser::BlockParser, BlockHash},
     display_duration,
+    network::NetworkProfile,
     state::{ledger::genesis, IndexerState},
     store::IndexerStore,
-    CANONICAL_UPDATE_THRESHOLD, MAINNET_CANONICAL_THRESHOLD, MAINNET_TRANSITION_FRONTIER_K,
-    PRUNE_INTERVAL_DEFAULT,
+    CANONICAL_UPDATE_THRESHOLD, MAINNET_CANONICAL_THRESHOLD, PRUNE_INTERVAL_DEFAULT,
 };
 use std::{fs, path::PathBuf, sync::Arc, thread};
 use tokio::{
@@ -73,9 +73,14 @@ async fn main() -> anyhow::Result<()> {
         .with(stdout_layer.with_filter(log_level))
         .init();
 
-    let mut bp =
-        BlockParser::new_filtered(&blocks_dir, max_block_length, MAINNET_CANONICAL_THRESHOLD)
-            .unwrap();
+    let network = NetworkProfile::mainnet();
+    let mut bp = BlockParser::new_filtered(
+        &blocks_dir,
+        &network,
+        max_block_length,
+        MAINNET_CANONICAL_THRESHOLD,
+    )
+    .unwrap();
     let store_dir = &PathBuf::from(DB_PATH);
     if store_dir.exists() {
         fs::remove_dir_all(store_dir)?;
@@ -94,7 +99,8 @@ async fn main() -> anyhow::Result<()> {
         BlockHash(GENESIS_HASH.to_string()),
         genesis_root.ledger,
         indexer_store,
-        MAINNET_TRANSITION_FRONTIER_K,
+        network,
+        MAINNET_CANONICAL_THRESHOLD,
         PRUNE_INTERVAL_DEFAULT,
         CANONICAL_UPDATE_THRESHOLD,
     )
@@ -234,11 +240,7 @@ async fn main() -> anyhow::Result<()> {
     println!("\n~~~ DB stats ~~~");
     println!(
         "Estimate number of keys:    {:?}",
-        state
-            .indexer_store
-            .as_ref()
-            .unwrap()
-            .estimate_live_data_size()
+        state.indexer_store.as_ref().unwrap().estimate_num_keys()
     );
     println!(
         "Estimate live data size:    {:?}",
@@ -250,6 +252,18 @@ async fn main() -> anyhow::Result<()> {
                 .estimate_live_data_size()
         )
     );
+    for cf in ["ledgers", "post-balances"] {
+        println!(
+            "Estimate {cf} size: {:?}",
+            ByteSize::b(
+                state
+                    .indexer_store
+                    .as_ref()
+                    .unwrap()
+                    .estimate_live_data_size_cf(cf)
+            )
+        );
+    }
     println!(
         "Current size all memtables: {:?}",
         ByteSize::b(
@@ -2,16 +2,17 @@ use clap::{Parser, Subcommand};
 use mina_indexer::{
     block::BlockHash,
     client,
+    network::{Network, NetworkProfile},
     server::{create_dir_if_non_existent, IndexerConfiguration, MinaIndexer},
     state::ledger,
     store::IndexerStore,
-    CANONICAL_UPDATE_THRESHOLD, MAINNET_CANONICAL_THRESHOLD, MAINNET_GENESIS_HASH,
-    MAINNET_TRANSITION_FRONTIER_K, PRUNE_INTERVAL_DEFAULT,
+    CANONICAL_UPDATE_THRESHOLD, MAINNET_CANONICAL_THRESHOLD, PRUNE_INTERVAL_DEFAULT,
+    SNAPSHOT_INTERVAL_DEFAULT_SEC,
 };
 use serde::Deserializer;
 use serde_derive::Deserialize;
-use std::{path::PathBuf, sync::Arc};
-use tracing::{error, info, instrument, trace};
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use tracing::{error, info, instrument, trace, warn};
 use tracing_subscriber::{filter::LevelFilter, prelude::*};
 
 #[derive(Parser, Debug)]
@@ -59,12 +60,17 @@ pub struct ServerArgs {
     /// Use a non-genesis ledger
     #[arg(long, default_value_t = false)]
     is_genesis_ledger: bool,
-    /// Hash of the base ledger
-    #[arg(
-        long,
-        default_value = MAINNET_GENESIS_HASH
-    )]
-    root_hash: String,
+    /// Network to index
+    #[serde(default)]
+    #[arg(long, value_enum, default_value_t = Network::Mainnet)]
+    network: Network,
+    /// Protocol constants of a custom network (config file only)
+    #[serde(default)]
+    #[arg(skip)]
+    network_profile: Option<NetworkProfile>,
+    /// Hash of the base ledger [default: the network's genesis hash]
+    #[arg(long)]
+    root_hash: Option<String>,
     /// Path to startup blocks directory
     #[arg(short, long, default_value = concat!(env!("HOME"), "/.mina-indexer/startup-blocks"))]
     startup_dir: PathBuf,
@@ -97,6 +103,25 @@ pub struct ServerArgs {
     /// Path to an indexer snapshot
     #[arg(long)]
     pub snapshot_path: Option<PathBuf>,
+    /// Interval (in seconds) between automatic state snapshots, 0 disables them
+    #[serde(default = "snapshot_interval_default")]
+    #[arg(long, default_value_t = SNAPSHOT_INTERVAL_DEFAULT_SEC)]
+    snapshot_interval: u64,
+    /// Discard the existing database and rebuild it from the startup blocks
+    #[serde(default)]
+    #[arg(long, default_value_t = false)]
+    pub reindex: bool,
+    /// Check canonical ledgers' Merkle roots against their blocks' ledger hashes
+    #[serde(default)]
+    #[arg(long, default_value_t = false)]
+    verify_ledger_hashes: bool,
+    /// Check the signatures of ingested blocks' user commands
+    #[serde(default)]
+    #[arg(long, default_value_t = false)]
+    verify_signatures: bool,
+    /// Path to move malformed block files to [default: leave them in place]
+    #[arg(long)]
+    quarantine_dir: Option<PathBuf>,
 }
 
 #[tokio::main]
@@ -111,12 +136,18 @@ pub async fn main() -> anyhow::Result<()> {
                     serde_yaml::from_reader(&config_file[..])?
                 }
             };
+            if args.reindex && args.snapshot_path.is_some() {
+                anyhow::bail!("--reindex and --snapshot-path cannot be used together");
+            }
+
             let option_snapshot_path = args.snapshot_path.clone();
+            let reindex = args.reindex;
             let database_dir = args.database_dir.clone();
             let log_dir = args.log_dir.clone();
             let log_level = args.log_level;
             let log_level_stdout = args.log_level_stdout;
             let config = handle_command_line_arguments(args).await?;
+            let network = config.network.clone();
 
             let mut log_number = 0;
             let mut log_file = format!("{}/mina-indexer-{}.log", log_dir.display(), log_number);
@@ -145,12 +176,19 @@ pub async fn main() -> anyhow::Result<()> {
                 let indexer_store = IndexerStore::from_backup(&snapshot_path, &database_dir)?;
                 Arc::new(indexer_store)
             } else {
+                if reindex && tokio::fs::metadata(&database_dir).await.is_ok() {
+                    info!("Removing {} to reindex", database_dir.display());
+                    tokio::fs::remove_dir_all(&database_dir).await?;
+                }
                 Arc::new(IndexerStore::new(&database_dir)?)
             };
 
-            MinaIndexer::new(config, db.clone()).await?;
-            mina_indexer::gql::start_gql(db).await.unwrap();
-            Ok(())
+            let indexer = MinaIndexer::new(config, db.clone()).await?;
+            mina_indexer::gql::start_gql(db, network).await.unwrap();
+
+            // the GraphQL server stops on SIGINT/SIGTERM too,
+            // wait for the indexer to finish shutting down
+            indexer.await_loop().await
         }
     }
 }
@@ -163,12 +201,34 @@ pub async fn handle_command_line_arguments(
 
     let ledger = args.initial_ledger;
     let is_genesis_ledger = args.is_genesis_ledger;
-    let root_hash = BlockHash(args.root_hash.to_string());
+    let network = match (args.network.profile(), args.network_profile) {
+        (Some(network), None) | (None, Some(network)) => network,
+        (Some(_), Some(_)) => anyhow::bail!(
+            "A network profile can only be supplied for the custom network, not {}",
+            args.network
+        ),
+        (None, None) => {
+            anyhow::bail!("The custom network requires a network_profile in the config file")
+        }
+    };
+    let root_hash = match (args.root_hash, network.genesis_hash.clone()) {
+        (Some(root_hash), _) => BlockHash(root_hash),
+        (None, Some(genesis_hash)) => genesis_hash,
+        (None, None) => anyhow::bail!(
+            "The {} genesis hash isn't known, supply it with --root-hash",
+            network.network
+        ),
+    };
     let startup_dir = args.startup_dir;
     let watch_dir = args.watch_dir;
     let prune_interval = args.prune_interval;
     let canonical_threshold = args.canonical_threshold;
     let canonical_update_threshold = args.canonical_update_threshold;
+    let snapshot_interval =
+        (args.snapshot_interval > 0).then(|| Duration::from_secs(args.snapshot_interval));
+    let verify_ledger_hashes = args.verify_ledger_hashes;
+    let verify_signatures = args.verify_signatures;
+    let quarantine_dir = args.quarantine_dir;
 
     assert!(
         ledger.is_file(),
@@ -177,7 +237,7 @@ pub async fn handle_command_line_arguments(
     );
     assert!(
         // bad things happen if this condition fails
-        canonical_update_threshold < MAINNET_TRANSITION_FRONTIER_K,
+        canonical_update_threshold < network.k,
         "canonical update threshold must be strictly less than the transition frontier length!"
     );
 
@@ -197,9 +257,27 @@ pub async fn handle_command_line_arguments(
         Ok(ledger) => {
             info!("Ledger parsed successfully!");
 
+            // the ledger's genesis timestamp takes precedence over the profile's
+            let mut network = network;
+            match ledger.genesis.timestamp_millis() {
+                Ok(genesis_timestamp) if genesis_timestamp != network.genesis_timestamp => {
+                    warn!(
+                        "Using the ledger's genesis timestamp {genesis_timestamp} instead of the {} one {}",
+                        network.network, network.genesis_timestamp
+                    );
+                    network.genesis_timestamp = genesis_timestamp;
+                }
+                Ok(_) => (),
+                Err(err) => warn!(
+                    "Unable to parse the ledger's genesis timestamp {}: {err}",
+                    ledger.genesis.genesis_state_timestamp
+                ),
+            }
+
             Ok(IndexerConfiguration {
                 ledger,
                 is_genesis_ledger,
+                network,
                 root_hash,
                 startup_dir,
                 watch_dir,
@@ -207,11 +285,19 @@ pub async fn handle_command_line_arguments(
                 canonical_threshold,
                 canonical_update_threshold,
                 from_snapshot: args.snapshot_path.is_some(),
+                snapshot_interval,
+                verify_ledger_hashes,
+                verify_signatures,
+                quarantine_dir,
             })
         }
     }
 }
 
+fn snapshot_interval_default() -> u64 {
+    SNAPSHOT_INTERVAL_DEFAULT_SEC
+}
+
 pub fn level_filter_deserializer<'de, D>(deserializer: D) -> Result<LevelFilter, D::Error>
 where
     D: Deserializer<'de>,
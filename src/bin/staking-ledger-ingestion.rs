@@ -1,17 +1,17 @@
+use anyhow::bail;
 use clap::Parser;
 
 use glob::glob;
 
 use mina_indexer::{
     staking_ledger::{
-        staking_ledger_store::StakingLedgerStore, DelegationTotals, StakingLedger,
-        StakingLedgerAccount,
+        staking_ledger_store::StakingLedgerStore, StakingLedger, StakingLedgerAccount,
     },
     store::IndexerStore,
 };
-use rust_decimal::{prelude::ToPrimitive, Decimal};
-use rust_decimal_macros::dec;
-use std::{ffi::OsStr, fs::File, io::Read, path::PathBuf, time::Instant, u32::MAX};
+use std::{
+    collections::HashMap, ffi::OsStr, fs::File, io::Read, path::PathBuf, time::Instant, u32::MAX,
+};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -41,7 +41,41 @@ fn extract_epoch_and_hash(file_name: &OsStr) -> Option<(u32, &str)> {
     Some((epoch, ledger_hash))
 }
 
-fn main() {
+/// Report accounts whose balance or delegate differ from the derived staking ledger
+///
+/// The derived ledger is built from staged ledgers, small differences are expected
+fn report_differences(derived_ledger: &StakingLedger, accounts: &[StakingLedgerAccount]) {
+    let derived_accounts: HashMap<&str, &StakingLedgerAccount> = derived_ledger
+        .accounts
+        .iter()
+        .map(|account| (account.pk.as_str(), account))
+        .collect();
+    let mut num_differences = 0;
+
+    for account in accounts {
+        match derived_accounts.get(account.pk.as_str()) {
+            None => num_differences += 1,
+            Some(derived) => {
+                if derived.balance_nanomina() != account.balance_nanomina()
+                    || derived.delegate != account.delegate
+                {
+                    num_differences += 1;
+                }
+            }
+        }
+    }
+
+    if num_differences > 0 || derived_accounts.len() != accounts.len() {
+        println!(
+            "{} of {} accounts differ from the derived staking ledger ({} accounts)",
+            num_differences,
+            accounts.len(),
+            derived_accounts.len()
+        );
+    }
+}
+
+fn main() -> anyhow::Result<()> {
     let args = Args::parse();
     let staking_ledgers_dir = args.staking_ledgers_dir;
     let database_dir = args.database_dir;
@@ -75,60 +109,34 @@ fn main() {
         let _ = file.read_to_end(&mut bytes);
         drop(file);
 
-        let mut accounts = match serde_json::from_slice::<Vec<StakingLedgerAccount>>(&bytes) {
+        let accounts = match serde_json::from_slice::<Vec<StakingLedgerAccount>>(&bytes) {
             Err(why) => panic!("Unable to parse JSON {}: {}", display, why),
             Ok(file) => file,
         };
 
-        for account in accounts.iter_mut() {
-            account.ledger_hash = Some(ledger_hash.to_string());
-            account.epoch_number = Some(epoch as i32);
-        }
-
-        let mut accs = accounts.clone();
-        println!("{} accounts in staking ledger {}", accs.len(), epoch);
+        // derived ledgers are replaced, official ones must not be replaced by another
         let now = Instant::now();
-        let mut processed = 0;
-        for account in &mut accounts {
-            let mut count_delegates = 0;
-            let mut total_delegations: i64 = 0;
-            let mut j = 0;
-            while j < accs.len() {
-                // Counting delgations for account.pk
-                if account.pk == accs[j].delegate {
-                    count_delegates += 1;
-                    let amount = accs[j]
-                        .balance
-                        .parse::<Decimal>()
-                        .map(|a| a * dec!(1_000_000_000))
-                        .unwrap_or_else(|_| Decimal::ZERO)
-                        .to_i64()
-                        .unwrap();
-
-                    total_delegations += amount;
-                    accs.swap_remove(j);
-                } else {
-                    j += 1;
+        if let Some(existing_ledger) = db.get_epoch(epoch)? {
+            if existing_ledger.derived {
+                if existing_ledger.ledger_hash != ledger_hash {
+                    println!(
+                        "Derived staking ledger of epoch {} hashes to {}",
+                        epoch, existing_ledger.ledger_hash
+                    );
                 }
-            }
-            account.delegation_totals = Some(DelegationTotals {
-                count_delegates,
-                total_delegations,
-            });
-            processed += 1;
-            if processed % 5000 == 0 {
-                let delta: u128 = Instant::now().duration_since(now).as_millis();
-                println!(
-                    "Processed {} ledger accounts in {} milliseconds",
-                    processed, delta
+                report_differences(&existing_ledger, &accounts);
+            } else if existing_ledger.ledger_hash != ledger_hash {
+                bail!(
+                    "Staking ledger {} of epoch {} doesn't match the already ingested {}",
+                    ledger_hash,
+                    epoch,
+                    existing_ledger.ledger_hash
                 );
             }
         }
-        let ledger = StakingLedger {
-            epoch_number: epoch,
-            ledger_hash: ledger_hash.to_string(),
-            accounts: accounts.clone(),
-        };
+
+        println!("{} accounts in staking ledger {}", accounts.len(), epoch);
+        let ledger = StakingLedger::new(epoch, ledger_hash.to_string(), accounts);
 
         match db.add_epoch(epoch, &ledger) {
             Ok(_) => println!("Successfully persisted staking ledger: {}", epoch),
@@ -151,4 +159,5 @@ fn main() {
         "{} ledgers/sec",
         (count as f64 / (delta as f64 / 1000.0_f64))
     );
+    Ok(())
 }
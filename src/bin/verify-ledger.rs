@@ -0,0 +1,87 @@
+use clap::Parser;
+use mina_indexer::{
+    block::parser::BlockParser,
+    network::Network,
+    state::ledger::{diff::LedgerDiff, genesis, reconciliation::BlockReconciliation, Ledger},
+    MAINNET_CANONICAL_THRESHOLD,
+};
+use std::{
+    fs::File,
+    io::{stdout, BufWriter, Write},
+    path::PathBuf,
+    process,
+};
+use tokio::time::Instant;
+
+// Applies the canonical blocks of a block directory starting at genesis,
+// checking each block's ledger diff against its post balances.
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Blocks directory path, starting at genesis
+    #[arg(short, long)]
+    blocks_dir: PathBuf,
+    /// Path to the genesis ledger
+    #[arg(
+        short,
+        long,
+        default_value = "./tests/data/genesis_ledgers/mainnet.json"
+    )]
+    genesis_ledger: PathBuf,
+    /// Network the blocks belong to
+    #[arg(long, value_enum, default_value_t = Network::Mainnet)]
+    network: Network,
+    /// Number of blocks a canonical block must be buried under
+    #[arg(long, default_value_t = MAINNET_CANONICAL_THRESHOLD)]
+    canonical_threshold: u32,
+    /// Path to write the discrepancies as JSON lines (default: stdout)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let network = match args.network.profile() {
+        Some(network) => network,
+        None => anyhow::bail!("Ledger verification requires a built-in network"),
+    };
+
+    let genesis_root = genesis::parse_file(&args.genesis_ledger).await?;
+    let mut ledger: Ledger = genesis_root.ledger.try_into()?;
+    let mut block_parser = BlockParser::new(&args.blocks_dir, &network, args.canonical_threshold)?;
+    let mut output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(stdout()),
+    };
+
+    let total_time = Instant::now();
+    let mut num_inconsistent = 0;
+
+    // only the canonical chain yields a single sequence of ledgers
+    for _ in 0..block_parser.num_canonical {
+        let block = match block_parser.next().await? {
+            Some(block) => block,
+            None => break,
+        };
+
+        let reconciliation = BlockReconciliation::new(&ledger, &block, &network);
+        if !reconciliation.is_consistent() {
+            num_inconsistent += 1;
+            writeln!(output, "{}", serde_json::to_string(&reconciliation)?)?;
+        }
+        ledger.apply_diff(&LedgerDiff::from_precomputed_block(&block, &network))?;
+    }
+    output.flush()?;
+
+    eprintln!(
+        "Verified {} canonical blocks in {:?}, {num_inconsistent} inconsistent",
+        block_parser.num_canonical,
+        total_time.elapsed()
+    );
+    if num_inconsistent > 0 {
+        process::exit(1);
+    }
+    Ok(())
+}
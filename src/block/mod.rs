@@ -1,11 +1,22 @@
-use anyhow::anyhow;
-use std::{ffi::OsStr, path::Path};
+use std::{
+    ffi::OsStr,
+    io::Read,
+    path::{Path, PathBuf},
+};
 
-use mina_serialization_types::{common::Base58EncodableVersionedType, v1::HashV1, version_bytes};
+use flate2::read::GzDecoder;
+use mina_hasher::{create_legacy, Hasher};
+use mina_rs_base::protocol_state::ProtocolState as HashableProtocolState;
+use mina_serialization_types::{
+    common::Base58EncodableVersionedType, protocol_state::ProtocolState, v1::HashV1, version_bytes,
+};
+use o1_utils::FieldHelpers;
 use serde::{Deserialize, Serialize};
-use tokio::io::AsyncReadExt;
+use thiserror::Error;
+use tracing::warn;
 
 use self::precomputed::{BlockLogContents, PrecomputedBlock};
+use crate::network::Network;
 
 pub mod parser;
 pub mod precomputed;
@@ -32,6 +43,106 @@ pub struct BlockWithoutHeight {
 #[derive(Hash, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct BlockHash(pub String);
 
+/// Compression of a block file or archive, given by its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Uncompressed,
+    Gzip,
+    Zstd,
+}
+
+const BLOCK_FILE_EXTENSIONS: [(&str, Compression); 3] = [
+    (".json", Compression::Uncompressed),
+    (".json.gz", Compression::Gzip),
+    (".json.zst", Compression::Zstd),
+];
+
+const BLOCK_ARCHIVE_EXTENSIONS: [(&str, Compression); 4] = [
+    (".tar", Compression::Uncompressed),
+    (".tar.gz", Compression::Gzip),
+    (".tgz", Compression::Gzip),
+    (".tar.zst", Compression::Zstd),
+];
+
+/// Reasons a precomputed block file can't be parsed
+#[derive(Debug, Error)]
+pub enum BlockParseError {
+    #[error("unable to read the block file: {0}")]
+    Io(#[source] std::io::Error),
+    /// The file's gzip or zstd stream is corrupt
+    #[error("unable to decompress the block file: {0}")]
+    Decompression(#[source] std::io::Error),
+    #[error("invalid precomputed block JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The file isn't named like a block, or is named with another block's state hash
+    #[error("bad block file name: {0}")]
+    BadFilename(String),
+    /// The file is named with a length other than the block's
+    #[error("block named with length {filename_length} has length {blockchain_length}")]
+    LengthMismatch {
+        filename_length: u32,
+        blockchain_length: u32,
+    },
+    /// Another block file of the same name is already being parsed
+    #[error("block file name {0} is taken by another block file")]
+    DuplicateFileName(String),
+    /// The block doesn't descend from the configured network's genesis block
+    #[error("block {state_hash} has genesis state hash {block_genesis_hash}, it's not a {network} block (genesis state hash {genesis_hash})")]
+    WrongNetwork {
+        state_hash: String,
+        block_genesis_hash: String,
+        network: Network,
+        genesis_hash: String,
+    },
+}
+
+impl BlockParseError {
+    /// Whether the file itself is at fault, io errors and truncated JSON
+    /// may just mean the file is still being written
+    pub fn is_malformed_file(&self) -> bool {
+        match self {
+            BlockParseError::Io(_) => false,
+            BlockParseError::Decompression(error) => {
+                error.kind() != std::io::ErrorKind::UnexpectedEof
+            }
+            BlockParseError::Json(error) => !error.is_eof(),
+            BlockParseError::BadFilename(_)
+            | BlockParseError::LengthMismatch { .. }
+            | BlockParseError::DuplicateFileName(_)
+            | BlockParseError::WrongNetwork { .. } => true,
+        }
+    }
+}
+
+impl Compression {
+    fn from_extension(path: &Path, extensions: &[(&str, Compression)]) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?;
+        extensions
+            .iter()
+            .find(|(extension, _)| file_name.ends_with(extension))
+            .map(|(_, compression)| *compression)
+    }
+
+    /// Wraps `reader` so it reads decompressed bytes
+    pub fn decoder<'a>(self, reader: impl Read + 'a) -> std::io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Compression::Uncompressed => Box::new(reader),
+            Compression::Gzip => Box::new(GzDecoder::new(reader)),
+            Compression::Zstd => Box::new(zstd::Decoder::new(reader)?),
+        })
+    }
+
+    fn decompress(self, bytes: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        if self == Compression::Uncompressed {
+            return Ok(bytes);
+        }
+
+        let mut decompressed = Vec::new();
+        self.decoder(&bytes[..])?.read_to_end(&mut decompressed)?;
+        Ok(decompressed)
+    }
+}
+
 impl BlockHash {
     pub fn from_bytes(bytes: [u8; 32]) -> Self {
         let block_hash = unsafe { String::from_utf8_unchecked(Vec::from(bytes)) };
@@ -44,9 +155,29 @@ impl BlockHash {
         Self(versioned.to_base58_string().unwrap())
     }
 
+    /// Poseidon hash of the protocol state, i.e. the state hash of its block
+    pub fn from_protocol_state(protocol_state: &ProtocolState) -> Self {
+        let protocol_state: HashableProtocolState = protocol_state.clone().into();
+        let bytes: [u8; 32] = create_legacy::<HashableProtocolState>(())
+            .hash(&protocol_state)
+            .to_bytes()
+            .try_into()
+            .expect("field elements are 32 bytes");
+        Self::from_hashv1(bytes.into())
+    }
+
     pub fn previous_state_hash(block: &PrecomputedBlock) -> Self {
         Self::from_hashv1(block.protocol_state.previous_state_hash.clone())
     }
+
+    pub fn genesis_state_hash(block: &PrecomputedBlock) -> Self {
+        Self::from_hashv1(block.protocol_state.body.t.t.genesis_state_hash.clone())
+    }
+
+    /// First 12 characters of the hash, for logging
+    fn short(&self) -> &str {
+        self.0.get(0..12).unwrap_or(&self.0)
+    }
 }
 
 impl Block {
@@ -142,8 +273,8 @@ impl std::fmt::Debug for Block {
             self.height,
             self.blockchain_length,
             self.global_slot_since_genesis,
-            &self.state_hash.0[0..12],
-            &self.parent_hash.0[0..12]
+            self.state_hash.short(),
+            self.parent_hash.short()
         )
     }
 }
@@ -155,8 +286,8 @@ impl std::fmt::Debug for BlockWithoutHeight {
             "Block {{ len: {}, slot: {}, state: {}, parent: {} }}",
             self.blockchain_length,
             self.global_slot_since_genesis,
-            &self.state_hash.0[0..12],
-            &self.parent_hash.0[0..12]
+            self.state_hash.short(),
+            self.parent_hash.short()
         )
     }
 }
@@ -167,31 +298,129 @@ impl std::fmt::Debug for BlockHash {
     }
 }
 
-pub async fn parse_file(filename: &Path) -> anyhow::Result<PrecomputedBlock> {
-    if is_valid_block_file(filename) {
-        let blockchain_length =
-            get_blockchain_length(filename.file_name().expect("filename already checked"));
-        let state_hash = get_state_hash(filename.file_name().expect("filename already checked"))
-            .expect("state hash already checked");
+/// Parses a precomputed block file, named with or without its state hash,
+/// and optionally gzip or zstd compressed
+///
+/// Blocks whose protocol state doesn't hash to the state hash in their file name,
+/// or whose length isn't the one in their file name, are rejected.
+pub async fn parse_file(filename: &Path) -> Result<PrecomputedBlock, BlockParseError> {
+    let (file_name, compression) = match (
+        filename.file_name(),
+        Compression::from_extension(filename, &BLOCK_FILE_EXTENSIONS),
+    ) {
+        (Some(file_name), Some(compression)) => (file_name, compression),
+        _ => {
+            return Err(BlockParseError::BadFilename(format!(
+                "{} is not a JSON, JSON gzip or JSON zstd file",
+                filename.display()
+            )))
+        }
+    };
+    let blockchain_length = get_blockchain_length(file_name);
+    let state_hash = get_state_hash(file_name);
+    let contents = tokio::fs::read(filename)
+        .await
+        .map_err(BlockParseError::Io)?;
+    let contents = compression
+        .decompress(contents)
+        .map_err(BlockParseError::Decompression)?;
 
-        let mut log_file = tokio::fs::File::open(&filename).await?;
-        let mut log_file_contents = Vec::new();
+    PrecomputedBlock::from_log_contents(BlockLogContents {
+        state_hash,
+        blockchain_length,
+        contents,
+    })
+}
 
-        log_file.read_to_end(&mut log_file_contents).await?;
-        drop(log_file);
-        let precomputed_block = PrecomputedBlock::from_log_contents(BlockLogContents {
-            state_hash,
-            blockchain_length,
-            contents: log_file_contents,
+/// Reader over the block file's JSON, decompressing it if needed
+pub fn open_block_file(path: &Path) -> std::io::Result<Box<dyn Read>> {
+    let compression =
+        Compression::from_extension(path, &BLOCK_FILE_EXTENSIONS).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} is not a block file", path.display()),
+            )
         })?;
+    compression.decoder(std::fs::File::open(path)?)
+}
 
-        Ok(precomputed_block)
-    } else {
-        Err(anyhow!(
-            "Could not find valid block! {} is not a valid Precomputed Block",
-            filename.display()
-        ))
+/// Unpacks the block files of a tar archive, optionally gzip or zstd
+/// compressed, into `dir` and returns their paths
+///
+/// Entries are unpacked by file name, the archive's directory layout is dropped.
+/// Only the block files `keep` accepts are unpacked, the others are skipped over.
+/// A block file named like one already unpacked into `dir` is unpacked into a
+/// `duplicate-{n}` subdirectory rather than overwriting it.
+pub fn unpack_block_archive(
+    archive_path: &Path,
+    dir: &Path,
+    keep: impl Fn(&Path) -> bool,
+) -> std::io::Result<Vec<PathBuf>> {
+    let compression = Compression::from_extension(archive_path, &BLOCK_ARCHIVE_EXTENSIONS)
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} is not a block archive", archive_path.display()),
+            )
+        })?;
+    let mut archive = tar::Archive::new(compression.decoder(std::fs::File::open(archive_path)?)?);
+    std::fs::create_dir_all(dir)?;
+
+    let mut paths = vec![];
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+        match entry_path.file_name() {
+            Some(file_name)
+                if entry.header().entry_type().is_file()
+                    && is_block_file(&entry_path)
+                    && keep(Path::new(file_name)) =>
+            {
+                let mut path = dir.join(file_name);
+                let mut n = 0;
+                while path.exists() {
+                    n += 1;
+                    path = dir.join(format!("duplicate-{n}")).join(file_name);
+                }
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                entry.unpack(&path)?;
+                paths.push(path);
+            }
+            _ => continue,
+        }
     }
+    Ok(paths)
+}
+
+/// Moves a block file that failed to parse into `quarantine_dir`, logging why
+pub fn quarantine_block_file(
+    path: &Path,
+    quarantine_dir: &Path,
+    reason: &BlockParseError,
+) -> std::io::Result<PathBuf> {
+    let file_name = path.file_name().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} is not a file", path.display()),
+        )
+    })?;
+    std::fs::create_dir_all(quarantine_dir)?;
+
+    // renaming fails across filesystems
+    let quarantined = quarantine_dir.join(file_name);
+    if std::fs::rename(path, &quarantined).is_err() {
+        std::fs::copy(path, &quarantined)?;
+        std::fs::remove_file(path)?;
+    }
+
+    warn!(
+        "Quarantined block file {} to {}: {reason}",
+        path.display(),
+        quarantined.display()
+    );
+    Ok(quarantined)
 }
 
 /// Extracts a state hash from an OS file name
@@ -211,8 +440,11 @@ pub fn get_state_hash(file_name: &OsStr) -> Option<String> {
 
 /// Extracts a blockchain length from an OS file name
 fn get_blockchain_length(file_name: &OsStr) -> Option<u32> {
-    file_name
-        .to_str()?
+    let file_name = file_name.to_str()?;
+    BLOCK_FILE_EXTENSIONS
+        .iter()
+        .find_map(|(extension, _)| file_name.strip_suffix(extension))
+        .unwrap_or(file_name)
         .split('-')
         .fold(None, |acc, x| match x.parse::<u32>() {
             Err(_) => acc,
@@ -220,14 +452,22 @@ fn get_blockchain_length(file_name: &OsStr) -> Option<u32> {
         })
 }
 
+/// Whether the path is a JSON file, optionally gzip or zstd compressed, which
+/// may name a block without its state hash
+pub fn is_block_file(path: &Path) -> bool {
+    Compression::from_extension(path, &BLOCK_FILE_EXTENSIONS).is_some()
+}
+
+/// Whether the path is a tar archive, optionally gzip or zstd compressed
+pub fn is_block_archive(path: &Path) -> bool {
+    Compression::from_extension(path, &BLOCK_ARCHIVE_EXTENSIONS).is_some()
+}
+
+/// Whether the path is a block file named with a state hash, as block discovery requires
 pub fn is_valid_block_file(path: &Path) -> bool {
     let file_name = path.file_name();
     if let Some(file_name) = file_name {
-        get_state_hash(file_name).is_some()
-            && file_name
-                .to_str()
-                .map(|file_name| file_name.ends_with(".json"))
-                .unwrap_or(false)
+        get_state_hash(file_name).is_some() && is_block_file(path)
     } else {
         false
     }
@@ -1,21 +1,24 @@
 use crate::{
     block::{
-        get_blockchain_length, get_state_hash, is_valid_block_file,
-        precomputed::{BlockLogContents, PrecomputedBlock},
+        get_blockchain_length, get_state_hash, is_block_archive, is_block_file,
+        is_valid_block_file, open_block_file, parse_file, precomputed::PrecomputedBlock,
+        quarantine_block_file, unpack_block_archive, BlockHash, BlockParseError,
     },
-    display_duration, BLOCK_REPORTING_FREQ_NUM,
+    display_duration,
+    network::NetworkProfile,
+    BLOCK_REPORTING_FREQ_NUM,
 };
 use anyhow::anyhow;
 use glob::glob;
 use std::{
-    fs::File,
-    io::{prelude::*, SeekFrom},
+    collections::HashSet,
+    ffi::OsString,
+    io::prelude::*,
     path::{Path, PathBuf},
     time::Instant,
     vec::IntoIter,
 };
-use tokio::io::AsyncReadExt;
-use tracing::{debug, info};
+use tracing::{debug, error, info, warn};
 
 pub enum SearchRecursion {
     None,
@@ -25,23 +28,56 @@ pub enum SearchRecursion {
 /// Splits block paths into two collections: canonical and successive
 ///
 /// Traverses canoncial paths first, then successive
+///
+/// Block files may be gzip or zstd compressed. Blocks in tar archives are
+/// unpacked into a temporary directory, removed along with the parser. An
+/// archived block file named like another block file is quarantined.
 pub struct BlockParser {
     pub num_canonical: u32,
     pub total_num_blocks: u32,
     pub blocks_dir: PathBuf,
     pub recursion: SearchRecursion,
+    /// Blocks of other networks are rejected, `None` skips the check
+    pub network: Option<NetworkProfile>,
+    /// Malformed block files are moved here, `None` leaves them in place
+    pub quarantine_dir: Option<PathBuf>,
     canonical_paths: IntoIter<PathBuf>,
     successive_paths: IntoIter<PathBuf>,
+    /// Holds the blocks unpacked from archives, if any
+    archive_dir: Option<ArchiveDir>,
+}
+
+/// Temporary directory of unpacked archive blocks, removed on drop
+struct ArchiveDir {
+    dir: PathBuf,
+    /// Unpacked block files named like another block file, quarantined by
+    /// the parser before it parses any block
+    duplicates: Vec<PathBuf>,
 }
 
 impl BlockParser {
-    pub fn new(blocks_dir: &Path, canonical_threshold: u32) -> anyhow::Result<Self> {
-        Self::new_internal(blocks_dir, SearchRecursion::None, None, canonical_threshold)
+    pub fn new(
+        blocks_dir: &Path,
+        network: &NetworkProfile,
+        canonical_threshold: u32,
+    ) -> anyhow::Result<Self> {
+        Self::new_internal(
+            blocks_dir,
+            network,
+            SearchRecursion::None,
+            None,
+            canonical_threshold,
+        )
     }
 
-    pub fn new_recursive(blocks_dir: &Path, canonical_threshold: u32) -> anyhow::Result<Self> {
+    pub fn new_recursive(
+        blocks_dir: &Path,
+        network: &NetworkProfile,
+        canonical_threshold: u32,
+    ) -> anyhow::Result<Self> {
         Self::new_internal(
             blocks_dir,
+            network,
             SearchRecursion::Recursive,
             None,
             canonical_threshold,
@@ -50,33 +86,76 @@ impl BlockParser {
 
     pub fn new_filtered(
         blocks_dir: &Path,
+        network: &NetworkProfile,
         blocklength: u32,
         canonical_threshold: u32,
     ) -> anyhow::Result<Self> {
         Self::new_internal(
             blocks_dir,
+            network,
             SearchRecursion::None,
             Some(blocklength),
             canonical_threshold,
         )
     }
 
+    /// Length-sorted `BlockParser` over the blocks in `blocks_dir` longer than
+    /// `blockchain_length`, all treated as successive. Used to catch up a state
+    /// restored from the db without re-parsing the blocks it already knows about.
+    pub fn new_successive(
+        blocks_dir: &Path,
+        network: &NetworkProfile,
+        blockchain_length: u32,
+    ) -> anyhow::Result<Self> {
+        if blocks_dir.exists() {
+            let blocks_dir = blocks_dir.to_owned();
+            // archived blocks the db already holds aren't unpacked again
+            let is_successive = |path: &Path| {
+                length_from_path(path).map_or(false, |length| length > blockchain_length)
+            };
+            let (paths, archive_dir) =
+                find_block_paths(&blocks_dir, &SearchRecursion::None, is_successive)?;
+            let mut paths: Vec<PathBuf> = paths
+                .into_iter()
+                .filter(|path| is_successive(path))
+                .collect();
+            paths.sort_by_key(|x| length_from_path_or_max(x));
+
+            Ok(Self {
+                num_canonical: 0,
+                total_num_blocks: paths.len() as u32,
+                blocks_dir,
+                recursion: SearchRecursion::None,
+                network: Some(network.clone()),
+                quarantine_dir: None,
+                canonical_paths: vec![].into_iter(),
+                successive_paths: paths.into_iter(),
+                archive_dir,
+            })
+        } else {
+            Err(anyhow!(
+                "[BlockParser::new_successive] log path {blocks_dir:?} does not exist!"
+            ))
+        }
+    }
+
     /// Simplified `BlockParser` for testing without canonical chain discovery.
     pub fn new_testing(blocks_dir: &Path) -> anyhow::Result<Self> {
         if blocks_dir.exists() {
             let blocks_dir = blocks_dir.to_owned();
-            let paths: Vec<PathBuf> = glob(&format!("{}/*.json", blocks_dir.display()))
-                .expect("Failed to read glob pattern")
-                .filter_map(|x| x.ok())
-                .collect();
+            let (paths, archive_dir) =
+                find_block_paths(&blocks_dir, &SearchRecursion::None, |_| true)?;
 
             Ok(Self {
                 num_canonical: 0,
                 total_num_blocks: paths.len() as u32,
                 blocks_dir,
                 recursion: SearchRecursion::None,
+                network: None,
+                quarantine_dir: None,
                 canonical_paths: vec![].into_iter(),
                 successive_paths: paths.into_iter(),
+                archive_dir,
             })
         } else {
             Err(anyhow!(
@@ -91,22 +170,28 @@ impl BlockParser {
     /// - blocks that are higher than the canonical tip
     fn new_internal(
         blocks_dir: &Path,
+        network: &NetworkProfile,
         recursion: SearchRecursion,
         length_filter: Option<u32>,
         canonical_threshold: u32,
     ) -> anyhow::Result<Self> {
         debug!("Building parser");
         if blocks_dir.exists() {
-            let pattern = match &recursion {
-                SearchRecursion::None => format!("{}/*.json", blocks_dir.display()),
-                SearchRecursion::Recursive => format!("{}/**/*.json", blocks_dir.display()),
-            };
             let blocks_dir = blocks_dir.to_owned();
-            let mut paths: Vec<PathBuf> = glob(&pattern)
-                .expect("Failed to read glob pattern")
-                .filter_map(|x| x.ok())
-                .filter(|path| length_from_path(path).is_some())
-                .collect();
+            let (paths, archive_dir) = find_block_paths(&blocks_dir, &recursion, |_| true)?;
+            let (mut paths, mut unnamed_paths): (Vec<PathBuf>, Vec<PathBuf>) = paths
+                .into_iter()
+                .partition(|path| length_from_path(path).is_some() && is_valid_block_file(path));
+
+            // canonical chain discovery goes by file name, blocks named without
+            // their length and state hash are parsed after it
+            for path in unnamed_paths.iter() {
+                warn!(
+                    "Block file {} isn't named with its length and state hash, it's parsed after the canonical chain",
+                    path.display()
+                );
+            }
+            unnamed_paths.sort_by_key(|x| length_from_path_or_max(x));
 
             // separate all blocks into the canonical chain
             // and the blocks that follow the canonical tip
@@ -186,13 +271,17 @@ impl BlockParser {
                     ) < canonical_threshold
                 {
                     info!("No canoncial blocks can be confidently found. Adding all blocks to the witness tree.");
+                    paths.append(&mut unnamed_paths);
                     return Ok(Self {
                         num_canonical: 0,
                         total_num_blocks: paths.len() as u32,
                         blocks_dir,
                         recursion,
+                        network: Some(network.clone()),
+                        quarantine_dir: None,
                         canonical_paths: vec![].into_iter(),
                         successive_paths: paths.into_iter(),
+                        archive_dir,
                     });
                 }
 
@@ -265,16 +354,21 @@ impl BlockParser {
                     // handle case where we fail to find parent
                     if !parent_found {
                         info!(
-                            "Unable to locate parent block: mainnet-{}-{parent_hash}.json",
+                            "Unable to locate parent block: {}-{}-{parent_hash}.json",
+                            network.network,
                             length_from_path_or_max(curr_path) - 1,
                         );
+                        paths.append(&mut unnamed_paths);
                         return Ok(Self {
                             num_canonical: 0,
                             total_num_blocks: paths.len() as u32,
                             blocks_dir,
                             recursion,
+                            network: Some(network.clone()),
+                            quarantine_dir: None,
                             canonical_paths: vec![].into_iter(),
                             successive_paths: paths.into_iter(),
+                            archive_dir,
                         });
                     }
                 }
@@ -297,14 +391,18 @@ impl BlockParser {
                 // sort lowest to highest
                 canonical_paths.reverse();
             }
+            successive_paths.append(&mut unnamed_paths);
 
             Ok(Self {
                 num_canonical: canonical_paths.len() as u32,
                 total_num_blocks: (canonical_paths.len() + successive_paths.len()) as u32,
                 blocks_dir,
                 recursion,
+                network: Some(network.clone()),
+                quarantine_dir: None,
                 canonical_paths: canonical_paths.into_iter(),
                 successive_paths: successive_paths.into_iter(),
+                archive_dir,
             })
         } else {
             Err(anyhow!(
@@ -314,16 +412,89 @@ impl BlockParser {
     }
 
     /// Traverses `self`'s internal paths. First canonical, then successive.
+    ///
+    /// Blocks which fail to parse or belong to another network are skipped,
+    /// and their files quarantined if they're at fault. A skipped canonical
+    /// block leaves the blocks after it without a known parent.
     pub async fn next(&mut self) -> anyhow::Result<Option<PrecomputedBlock>> {
-        if let Some(next_path) = self.canonical_paths.next() {
-            return self.parse_file(&next_path).await.map(Some);
+        let duplicates = self
+            .archive_dir
+            .as_mut()
+            .map(|archive_dir| std::mem::take(&mut archive_dir.duplicates))
+            .unwrap_or_default();
+        for path in duplicates {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            self.reject(
+                &path,
+                &BlockParseError::DuplicateFileName(file_name.into_owned()),
+            );
         }
 
-        if let Some(next_path) = self.successive_paths.next() {
-            return self.parse_file(&next_path).await.map(Some);
+        loop {
+            let (next_path, is_canonical) = match self.canonical_paths.next() {
+                Some(next_path) => (next_path, true),
+                None => match self.successive_paths.next() {
+                    Some(next_path) => (next_path, false),
+                    None => return Ok(None),
+                },
+            };
+
+            match self
+                .parse_file(&next_path)
+                .await
+                .and_then(|precomputed_block| {
+                    self.check_network(&precomputed_block)?;
+                    Ok(precomputed_block)
+                }) {
+                Ok(precomputed_block) => return Ok(Some(precomputed_block)),
+                Err(parse_error) => {
+                    if is_canonical {
+                        error!(
+                            "Skipping canonical block file {}, the canonical chain is broken past it",
+                            next_path.display()
+                        );
+                    }
+                    self.reject(&next_path, &parse_error);
+                }
+            }
+        }
+    }
+
+    /// Quarantines the block file if it's malformed, otherwise logs and leaves it
+    fn reject(&self, path: &Path, parse_error: &BlockParseError) {
+        match &self.quarantine_dir {
+            Some(quarantine_dir) if parse_error.is_malformed_file() => {
+                if let Err(e) = quarantine_block_file(path, quarantine_dir, parse_error) {
+                    error!(
+                        "Unable to quarantine block file {} ({parse_error}): {e}",
+                        path.display()
+                    );
+                }
+            }
+            _ => warn!("Skipping block file {}: {parse_error}", path.display()),
         }
+    }
 
-        Ok(None)
+    /// Rejects the block if it doesn't descend from the network's genesis block
+    fn check_network(&self, precomputed_block: &PrecomputedBlock) -> Result<(), BlockParseError> {
+        if let Some(NetworkProfile {
+            network,
+            genesis_hash: Some(genesis_hash),
+            ..
+        }) = &self.network
+        {
+            let block_genesis_hash = BlockHash::genesis_state_hash(precomputed_block);
+            if &block_genesis_hash != genesis_hash && precomputed_block.state_hash != genesis_hash.0
+            {
+                return Err(BlockParseError::WrongNetwork {
+                    state_hash: precomputed_block.state_hash.clone(),
+                    block_genesis_hash: block_genesis_hash.0,
+                    network: *network,
+                    genesis_hash: genesis_hash.0.clone(),
+                });
+            }
+        }
+        Ok(())
     }
 
     /// Gets the precomputed block with supplied `state_hash`, it must exist ahead
@@ -355,62 +526,96 @@ impl BlockParser {
         Ok(next_block)
     }
 
-    /// Parses the precomputed block's JSON file, throws if a read error occurs.
-    pub async fn parse_file(&mut self, filename: &Path) -> anyhow::Result<PrecomputedBlock> {
-        if is_valid_block_file(filename) {
-            let blockchain_length =
-                get_blockchain_length(filename.file_name().expect("filename already checked"));
-            let state_hash =
-                get_state_hash(filename.file_name().expect("filename already checked"))
-                    .expect("state hash already checked");
-
-            let mut log_file = tokio::fs::File::open(&filename).await?;
-            let mut log_file_contents = Vec::new();
-
-            log_file.read_to_end(&mut log_file_contents).await?;
-            drop(log_file);
-            let precomputed_block = PrecomputedBlock::from_log_contents(BlockLogContents {
-                state_hash,
-                blockchain_length,
-                contents: log_file_contents,
-            })?;
-
-            Ok(precomputed_block)
-        } else {
-            Err(anyhow!(
-                "
-[BlockParser::parse_file]
-    Could not find valid block!
-    {} is not a valid precomputed block",
-                filename.display()
-            ))
-        }
+    /// Parses the precomputed block's JSON file, throws if a read error occurs
+    /// or if the block's state hash or length doesn't match its file name.
+    pub async fn parse_file(
+        &mut self,
+        filename: &Path,
+    ) -> Result<PrecomputedBlock, BlockParseError> {
+        parse_file(filename).await
     }
 }
 
+/// Bytes of the block's JSON searched for its parent hash
+const PARENT_HASH_SEARCH_LENGTH: u64 = 4096;
+
 /// Gets the parent hash from the contents of the block's JSON file.
 /// This function depends on the current JSON layout for precomputed blocks
 /// and should be modified to use a custom `prev_state_hash` field deserializer.
 fn extract_parent_hash_from_path(path: &Path) -> anyhow::Result<String> {
-    let mut parent_hash_offset = 75;
-    let mut parent_hash = read_parent_hash(path, parent_hash_offset)?;
-
-    while !parent_hash.starts_with("3N") {
-        parent_hash_offset += 1;
-        parent_hash = read_parent_hash(path, parent_hash_offset)?;
-    }
-    Ok(parent_hash)
+    let parent_hash_length = 52;
+    let mut prefix = vec![];
+    open_block_file(path)?
+        .take(PARENT_HASH_SEARCH_LENGTH)
+        .read_to_end(&mut prefix)?;
+
+    (75..prefix.len().saturating_sub(parent_hash_length - 1))
+        .map(|offset| &prefix[offset..offset + parent_hash_length])
+        .find(|parent_hash| parent_hash.starts_with(b"3N"))
+        .ok_or_else(|| anyhow!("No parent hash found in {}", path.display()))
+        .and_then(|parent_hash| {
+            String::from_utf8(parent_hash.to_vec()).map_err(anyhow::Error::from)
+        })
 }
 
-fn read_parent_hash(path: &Path, parent_hash_offset: u64) -> anyhow::Result<String> {
-    let parent_hash_length = 52;
-    let mut f = File::open(path)?;
-    let mut buf = vec![0; parent_hash_length];
+/// Block files in glob order followed by the block files of archives. Archives
+/// are unpacked into a fresh temporary directory, returned if there were any.
+/// Only the archived block files `keep` accepts are unpacked.
+///
+/// An archived block file sharing its file name with another archived or plain
+/// block file is set aside in the archive directory's duplicates, since only
+/// one of them can be parsed.
+fn find_block_paths(
+    blocks_dir: &Path,
+    recursion: &SearchRecursion,
+    keep: impl Fn(&Path) -> bool,
+) -> anyhow::Result<(Vec<PathBuf>, Option<ArchiveDir>)> {
+    let pattern = match recursion {
+        SearchRecursion::None => format!("{}/*", blocks_dir.display()),
+        SearchRecursion::Recursive => format!("{}/**/*", blocks_dir.display()),
+    };
+
+    let mut paths = vec![];
+    let mut archive_paths = vec![];
+    let mut archive_dir = None;
+    for path in glob(&pattern)
+        .expect("Failed to read glob pattern")
+        .filter_map(|x| x.ok())
+    {
+        if is_block_file(&path) {
+            paths.push(path);
+        } else if is_block_archive(&path) {
+            let ArchiveDir { dir, .. } = archive_dir.get_or_insert_with(|| ArchiveDir {
+                dir: std::env::temp_dir()
+                    .join(format!("mina-indexer-blocks-{}", uuid::Uuid::new_v4())),
+                duplicates: vec![],
+            });
+            info!("Unpacking block archive {}", path.display());
+            archive_paths.append(
+                &mut unpack_block_archive(&path, dir, &keep).map_err(|e| {
+                    anyhow!("Could not unpack block archive {}: {e}", path.display())
+                })?,
+            );
+        }
+    }
 
-    f.seek(SeekFrom::Start(parent_hash_offset))?;
-    f.read_exact(&mut buf)?;
-    drop(f);
-    String::from_utf8(buf).map_err(anyhow::Error::from)
+    if let Some(ArchiveDir { duplicates, .. }) = archive_dir.as_mut() {
+        let mut file_names: HashSet<OsString> = paths
+            .iter()
+            .filter_map(|path| path.file_name().map(ToOwned::to_owned))
+            .collect();
+        for path in archive_paths {
+            let is_duplicate = path
+                .file_name()
+                .map_or(false, |file_name| !file_names.insert(file_name.to_owned()));
+            if is_duplicate {
+                duplicates.push(path);
+            } else {
+                paths.push(path);
+            }
+        }
+    }
+    Ok((paths, archive_dir))
 }
 
 /// Checks if the block at `curr_path` is the _parent_ of the block at `path`.
@@ -515,6 +720,12 @@ fn max_num_canonical_blocks(
         + 1
 }
 
+impl Drop for ArchiveDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.dir).ok();
+    }
+}
+
 // path helpers
 fn length_from_path(path: &Path) -> Option<u32> {
     get_blockchain_length(path.file_name()?)
@@ -587,6 +798,13 @@ mod tests {
             });
     }
 
+    #[test]
+    fn blockchain_lengths_without_state_hash() {
+        for file_name in ["mainnet-3.json", "mainnet-3.json.gz", "mainnet-3.json.zst"] {
+            assert_eq!(get_blockchain_length(&OsString::from(file_name)), Some(3));
+        }
+    }
+
     #[test]
     fn invalid_filenames_have_invalid_state_hash_or_non_json_extension() {
         Vec::from(FILENAMES_INVALID)
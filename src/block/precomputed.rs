@@ -1,23 +1,27 @@
-use crate::state::{
-    ledger::{
+use crate::{
+    block::{BlockHash, BlockParseError},
+    state::ledger::{
         command::{PaymentPayload, SignedCommand, UserCommandWithStatus},
         public_key::PublicKey,
+        LedgerHash,
     },
-    Canonicity,
 };
 use mina_serialization_types::{
     json::DeltaTransitionChainProofJson,
     protocol_state::{ProtocolState, ProtocolStateJson},
     protocol_state_proof::ProtocolStateProofBase64Json,
     staged_ledger_diff::{
-        self, SignedCommandPayloadBody, StagedLedgerDiff, StagedLedgerDiffJson, StakeDelegation,
+        self, CoinBase, InternalCommandBalanceData, SignedCommandPayloadBody, StagedLedgerDiff,
+        StagedLedgerDiffJson, StakeDelegation, TransactionSnarkWork,
     },
     v1::{DeltaTransitionChainProof, ProtocolStateProofV1, UserCommandWithStatusV1},
 };
 use serde::{Deserialize, Serialize};
 
 pub struct BlockLogContents {
-    pub(crate) state_hash: String,
+    /// State hash in the block's file name, if any
+    pub(crate) state_hash: Option<String>,
+    /// Length in the block's file name, if any
     pub(crate) blockchain_length: Option<u32>,
     pub(crate) contents: Vec<u8>,
 }
@@ -33,7 +37,6 @@ pub struct BlockLog {
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct PrecomputedBlock {
-    pub canonicity: Option<Canonicity>,
     pub state_hash: String,
     pub scheduled_time: String,
     pub protocol_state: ProtocolState,
@@ -44,8 +47,9 @@ pub struct PrecomputedBlock {
 }
 
 impl PrecomputedBlock {
-    pub fn from_log_contents(log_contents: BlockLogContents) -> serde_json::Result<Self> {
-        let state_hash = log_contents.state_hash;
+    /// Parses the block, rejecting it if its protocol state doesn't hash to
+    /// the state hash it was named with, or its length isn't the named one
+    pub fn from_log_contents(log_contents: BlockLogContents) -> Result<Self, BlockParseError> {
         let str = String::from_utf8_lossy(&log_contents.contents);
         let BlockLog {
             scheduled_time,
@@ -53,18 +57,32 @@ impl PrecomputedBlock {
             protocol_state_proof,
             staged_ledger_diff,
             delta_transition_chain_proof,
-        } = serde_json::from_str::<BlockLog>(&str).unwrap();
-        let blockchain_length = if let Some(blockchain_length) = log_contents.blockchain_length {
-            blockchain_length
-        } else {
-            protocol_state.body.consensus_state.blockchain_length.0
-        };
+        } = serde_json::from_str::<BlockLog>(&str)?;
+        let blockchain_length = protocol_state.body.consensus_state.blockchain_length.0;
+        if let Some(filename_length) = log_contents.blockchain_length {
+            if filename_length != blockchain_length {
+                return Err(BlockParseError::LengthMismatch {
+                    filename_length,
+                    blockchain_length,
+                });
+            }
+        }
+
+        let protocol_state: ProtocolState = protocol_state.into();
+        let state_hash = BlockHash::from_protocol_state(&protocol_state).0;
+        if let Some(named_state_hash) = log_contents.state_hash {
+            if named_state_hash != state_hash {
+                return Err(BlockParseError::BadFilename(format!(
+                    "block named with state hash {named_state_hash} has state hash {state_hash}"
+                )));
+            }
+        }
+
         Ok(Self {
-            canonicity: None,
             state_hash,
             scheduled_time,
             blockchain_length,
-            protocol_state: protocol_state.into(),
+            protocol_state,
             protocol_state_proof: protocol_state_proof.into(),
             staged_ledger_diff: staged_ledger_diff.into(),
             delta_transition_chain_proof: delta_transition_chain_proof.into(),
@@ -72,14 +90,69 @@ impl PrecomputedBlock {
     }
 
     pub fn commands(&self) -> Vec<UserCommandWithStatusV1> {
-        self.staged_ledger_diff
-            .diff
-            .clone()
-            .inner()
-            .0
-            .inner()
-            .inner()
-            .commands
+        let diff = self.staged_ledger_diff.diff.clone().inner();
+        let mut commands = diff.0.inner().inner().commands;
+        if let Some(pre_diff_two) = diff.1 {
+            commands.append(&mut pre_diff_two.inner().inner().commands);
+        }
+        commands
+    }
+
+    pub fn completed_works(&self) -> Vec<TransactionSnarkWork> {
+        let diff = self.staged_ledger_diff.diff.clone().inner();
+        let mut completed_works = diff.0.inner().inner().completed_works;
+        if let Some(pre_diff_two) = diff.1 {
+            completed_works.append(&mut pre_diff_two.inner().inner().completed_works);
+        }
+        completed_works
+            .into_iter()
+            .map(|work| work.inner())
+            .collect()
+    }
+
+    pub fn coinbase(&self) -> CoinBase {
+        let diff = self.staged_ledger_diff.diff.clone().inner();
+        let mut coinbases = vec![diff.0.inner().inner().coinbase.inner()];
+        if let Some(pre_diff_two) = diff.1 {
+            coinbases.push(pre_diff_two.inner().inner().coinbase.inner());
+        }
+
+        let mut applied = false;
+        let mut fee_transfers = vec![];
+        for coinbase in coinbases {
+            match coinbase {
+                CoinBase::Zero => (),
+                CoinBase::One(fee_transfer) => {
+                    applied = true;
+                    fee_transfers.extend(fee_transfer);
+                }
+                CoinBase::Two(fee_transfer0, fee_transfer1) => {
+                    applied = true;
+                    fee_transfers.extend(fee_transfer0);
+                    fee_transfers.extend(fee_transfer1);
+                }
+            }
+        }
+
+        let mut fee_transfers = fee_transfers.into_iter();
+        match (applied, fee_transfers.next(), fee_transfers.next()) {
+            (false, _, _) => CoinBase::Zero,
+            (true, fee_transfer0, None) => CoinBase::One(fee_transfer0),
+            (true, fee_transfer0, fee_transfer1) => CoinBase::Two(fee_transfer0, fee_transfer1),
+        }
+    }
+
+    pub fn internal_command_balances(&self) -> Vec<InternalCommandBalanceData> {
+        let diff = self.staged_ledger_diff.diff.clone().inner();
+        let mut internal_command_balances = diff.0.inner().inner().internal_command_balances;
+        if let Some(pre_diff_two) = diff.1 {
+            internal_command_balances
+                .append(&mut pre_diff_two.inner().inner().internal_command_balances);
+        }
+        internal_command_balances
+            .into_iter()
+            .map(|balance_data| balance_data.inner())
+            .collect()
     }
 
     pub fn block_public_keys(&self) -> Vec<PublicKey> {
@@ -127,6 +200,32 @@ impl PrecomputedBlock {
         public_keys
     }
 
+    pub fn block_creator(&self) -> PublicKey {
+        self.protocol_state
+            .body
+            .t
+            .t
+            .consensus_state
+            .t
+            .t
+            .block_creator
+            .clone()
+            .into()
+    }
+
+    pub fn coinbase_receiver(&self) -> PublicKey {
+        self.protocol_state
+            .body
+            .t
+            .t
+            .consensus_state
+            .t
+            .t
+            .coinbase_receiver
+            .clone()
+            .into()
+    }
+
     pub fn global_slot_since_genesis(&self) -> u32 {
         self.protocol_state
             .body
@@ -140,6 +239,63 @@ impl PrecomputedBlock {
             .t
     }
 
+    /// Hash of the ledger the next epoch is staked on. The first block of an
+    /// epoch fixes it to its parent's snarked ledger hash.
+    pub fn next_epoch_ledger_hash(&self) -> LedgerHash {
+        LedgerHash::from_hashv1(
+            self.protocol_state
+                .body
+                .t
+                .t
+                .consensus_state
+                .t
+                .t
+                .next_epoch_data
+                .t
+                .t
+                .ledger
+                .t
+                .t
+                .hash
+                .clone(),
+        )
+    }
+
+    /// Hash of the staged ledger resulting from the block
+    pub fn staged_ledger_hash(&self) -> LedgerHash {
+        LedgerHash::from_hashv1(
+            self.protocol_state
+                .body
+                .t
+                .t
+                .blockchain_state
+                .t
+                .t
+                .staged_ledger_hash
+                .t
+                .t
+                .non_snark
+                .t
+                .ledger_hash
+                .clone(),
+        )
+    }
+
+    /// Hash of the most recent snarked ledger as of the block
+    pub fn snarked_ledger_hash(&self) -> LedgerHash {
+        LedgerHash::from_hashv1(
+            self.protocol_state
+                .body
+                .t
+                .t
+                .blockchain_state
+                .t
+                .t
+                .snarked_ledger_hash
+                .clone(),
+        )
+    }
+
     pub fn timestamp(&self) -> u64 {
         self.protocol_state
             .body
@@ -1,9 +1,17 @@
-use crate::state::ledger::{command::UserCommandWithStatus, public_key::PublicKey};
+use crate::{
+    network::Network,
+    state::ledger::{account::Amount, command::UserCommandWithStatus, public_key::PublicKey},
+};
 use blake2::digest::VariableOutput;
+use mina_hasher::{Hashable, ROInput};
 use mina_serialization_types::staged_ledger_diff::{SignedCommandPayload, UserCommand};
+use mina_signer::{BaseField, CompressedPubKey, NetworkId, PubKey, ScalarField, Signature, Signer};
+use o1_utils::FieldHelpers;
 use std::io::Write;
 use versioned::Versioned;
 
+const DEFAULT_TOKEN_ID: u64 = 1;
+
 pub struct SignedCommand(pub mina_serialization_types::staged_ledger_diff::SignedCommandV1);
 
 impl SignedCommand {
@@ -21,6 +29,10 @@ impl SignedCommand {
         self.0.t.t.payload.t.t.common.t.t.t.nonce.t.t
     }
 
+    pub fn fee(&self) -> Amount {
+        self.0.t.t.payload.t.t.common.t.t.t.fee.t.t.into()
+    }
+
     pub fn fee_payer(&self) -> PublicKey {
         self.0
             .t
@@ -37,6 +49,10 @@ impl SignedCommand {
             .into()
     }
 
+    pub fn signer(&self) -> PublicKey {
+        self.0.clone().inner().inner().signer.0.inner().into()
+    }
+
     pub fn receiver_pk(&self) -> PublicKey {
         match self.0.t.t.payload.t.t.body.t.t.clone() {
             mina_serialization_types::staged_ledger_diff::SignedCommandPayloadBody::PaymentPayload(payment_payload)
@@ -70,6 +86,37 @@ impl SignedCommand {
         }
     }
 
+    /// Checks the command's signature against its signer's key
+    ///
+    /// Signatures commit to the network, mainnet commands don't verify on other networks.
+    pub fn verify(&self, network: Network) -> bool {
+        let network_id = match network {
+            Network::Mainnet => NetworkId::MAINNET,
+            _ => NetworkId::TESTNET,
+        };
+        match (self.signature(), self.signer_key()) {
+            (Some(signature), Some(signer_key)) => mina_signer::create_legacy(network_id).verify(
+                &signature,
+                &signer_key,
+                &SignedCommandInput::from(self),
+            ),
+            _ => false,
+        }
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        let (rx, s) = &self.0.t.t.signature.t.t.0;
+        Some(Signature::new(
+            BaseField::from_bytes(rx).ok()?,
+            ScalarField::from_bytes(s).ok()?,
+        ))
+    }
+
+    /// `None` if the signer's key isn't a point on the curve
+    fn signer_key(&self) -> Option<PubKey> {
+        PubKey::from_address(&self.signer().to_address()).ok()
+    }
+
     pub fn hash_signed_command(&self) -> anyhow::Result<String> {
         let mut binprot_bytes = Vec::new();
         bin_prot::to_writer(&mut binprot_bytes, &self.0).map_err(anyhow::Error::from)?;
@@ -102,10 +149,83 @@ impl From<Versioned<Versioned<mina_serialization_types::staged_ledger_diff::Sign
     }
 }
 
+/// Legacy signing input of a payment or delegation
+#[derive(Clone)]
+struct SignedCommandInput {
+    fee: u64,
+    fee_payer_pk: CompressedPubKey,
+    nonce: u32,
+    valid_until: u32,
+    memo: Vec<u8>,
+    /// Payments are tagged 0, delegations 1
+    tag: [bool; 3],
+    source_pk: CompressedPubKey,
+    receiver_pk: CompressedPubKey,
+    amount: u64,
+}
+
+impl From<&SignedCommand> for SignedCommandInput {
+    fn from(signed_command: &SignedCommand) -> Self {
+        let common = &signed_command.payload().common.t.t.t;
+        let (tag, amount) = match &signed_command.payload().body.t.t {
+            mina_serialization_types::staged_ledger_diff::SignedCommandPayloadBody::PaymentPayload(payment_payload)
+                => ([false, false, false], payment_payload.t.t.amount.t.t),
+            mina_serialization_types::staged_ledger_diff::SignedCommandPayloadBody::StakeDelegation(_delegation_payload)
+                => ([false, false, true], 0),
+        };
+        Self {
+            fee: common.fee.t.t,
+            fee_payer_pk: CompressedPubKey::from(&signed_command.fee_payer()),
+            nonce: common.nonce.t.t as u32,
+            valid_until: common.valid_until.t.t as u32,
+            memo: common.memo.t.0.clone(),
+            tag,
+            source_pk: CompressedPubKey::from(&signed_command.source_pk()),
+            receiver_pk: CompressedPubKey::from(&signed_command.receiver_pk()),
+            amount,
+        }
+    }
+}
+
+impl Hashable for SignedCommandInput {
+    type D = NetworkId;
+
+    fn to_roinput(&self) -> ROInput {
+        let roi = ROInput::new()
+            .append_field(self.fee_payer_pk.x)
+            .append_field(self.source_pk.x)
+            .append_field(self.receiver_pk.x)
+            .append_u64(self.fee)
+            .append_u64(DEFAULT_TOKEN_ID)
+            .append_bool(self.fee_payer_pk.is_odd)
+            .append_u32(self.nonce)
+            .append_u32(self.valid_until)
+            .append_bytes(&self.memo);
+        self.tag
+            .iter()
+            .fold(roi, |roi, bit| roi.append_bool(*bit))
+            .append_bool(self.source_pk.is_odd)
+            .append_bool(self.receiver_pk.is_odd)
+            .append_u64(DEFAULT_TOKEN_ID)
+            .append_u64(self.amount)
+            // token locked
+            .append_bool(false)
+    }
+
+    fn domain_string(network_id: NetworkId) -> Option<String> {
+        match network_id {
+            NetworkId::MAINNET => "MinaSignatureMainnet",
+            NetworkId::TESTNET => "CodaSignature",
+        }
+        .to_string()
+        .into()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::SignedCommand;
-    use crate::block::parse_file;
+    use crate::{block::parse_file, network::Network};
     use mina_serialization_types::staged_ledger_diff::UserCommand;
     use std::path::PathBuf;
 
@@ -134,4 +254,21 @@ mod tests {
 
         assert_eq!(hashes, expect);
     }
+
+    #[tokio::test]
+    async fn signature_verification() {
+        let block_file = PathBuf::from("./tests/data/sequential_blocks/mainnet-105489-3NK4huLvUDiL4XuCUcyrWCKynmvhqfKsx5h2MfBXVVUq2Qwzi5uT.json");
+        let precomputed_block = parse_file(&block_file).await.unwrap();
+
+        for commandv1 in precomputed_block.commands() {
+            let UserCommand::SignedCommand(signed_commandv1) = commandv1.t.data.t.t;
+            let mut signed_command = SignedCommand(signed_commandv1);
+            assert!(signed_command.verify(Network::Mainnet));
+            assert!(!signed_command.verify(Network::Devnet));
+
+            // any change to the payload invalidates the signature
+            signed_command.0.t.t.payload.t.t.common.t.t.t.fee.t.t += 1;
+            assert!(!signed_command.verify(Network::Mainnet));
+        }
+    }
 }
@@ -8,4 +8,15 @@ pub trait BlockStore {
     fn get_block(&self, state_hash: &BlockHash) -> anyhow::Result<Option<PrecomputedBlock>>;
     fn set_canonicity(&self, state_hash: &BlockHash, canonicity: Canonicity) -> anyhow::Result<()>;
     fn get_canonicity(&self, state_hash: &BlockHash) -> anyhow::Result<Option<Canonicity>>;
+    /// Flags the block as including user commands with invalid signatures, given their hashes
+    fn set_invalid_signatures(
+        &self,
+        state_hash: &BlockHash,
+        tx_hashes: &[String],
+    ) -> anyhow::Result<()>;
+    fn get_invalid_signatures(&self, state_hash: &BlockHash)
+        -> anyhow::Result<Option<Vec<String>>>;
+    /// Kept as a count so it's cheap to report
+    fn get_num_blocks_with_invalid_signatures(&self) -> anyhow::Result<u32>;
+    fn get_blocks_with_invalid_signatures(&self) -> anyhow::Result<Vec<BlockHash>>;
 }
@@ -1,9 +1,15 @@
 use crate::{
-    block::{precomputed::PrecomputedBlock, Block},
+    block::{precomputed::PrecomputedBlock, signed_command::SignedCommand, Block, BlockHash},
+    consensus_time::ConsensusTime,
     state::{
-        ledger::account::Account,
+        ledger::{
+            account::{nanomina_to_mina, Account},
+            command::UserCommandWithStatus,
+        },
         summary::{SummaryShort, SummaryVerbose},
+        Canonicity,
     },
+    store::TransactionKey,
     SOCKET_NAME,
 };
 use clap::Parser;
@@ -12,6 +18,7 @@ use futures::{
     AsyncReadExt,
 };
 use interprocess::local_socket::tokio::LocalSocketStream;
+use mina_serialization_types::{json::UserCommandWithStatusJson, v1::UserCommandWithStatusV1};
 use serde_derive::{Deserialize, Serialize};
 use std::{path::PathBuf, process, time::Duration};
 use tokio::{
@@ -25,12 +32,18 @@ use tracing::instrument;
 pub enum ClientCli {
     /// Display the account info for the given public key
     Account(AccountArgs),
+    /// Display the most recent transactions sent or received by the given public key
+    AccountTransactions(AccountTransactionsArgs),
     /// Display the best chain
     BestChain(ChainArgs),
     /// Dump the best ledger to a file
     BestLedger(LedgerArgs),
+    /// Dump the canonical ledger at the given height to a file
+    Ledger(LedgerAtHeightArgs),
     /// Show summary of indexer state
     Summary(SummaryArgs),
+    /// Display the transaction with the given hash, its block(s) and their canonicity
+    Transaction(TransactionArgs),
     /// Save the current IndexerState to an indxr file
     SaveState { out_dir: PathBuf },
 }
@@ -43,6 +56,17 @@ pub struct AccountArgs {
     public_key: String,
 }
 
+#[derive(clap::Args, Debug, Serialize, Deserialize)]
+#[command(author, version, about, long_about = None)]
+pub struct AccountTransactionsArgs {
+    /// Retrieve this public key's transactions
+    #[arg(short, long)]
+    public_key: String,
+    /// Number of transactions to include
+    #[arg(short, long, default_value_t = 10)]
+    num: usize,
+}
+
 #[derive(clap::Args, Debug, Serialize, Deserialize)]
 #[command(author, version, about, long_about = None)]
 pub struct ChainArgs {
@@ -65,6 +89,17 @@ pub struct LedgerArgs {
     path: PathBuf,
 }
 
+#[derive(clap::Args, Debug, Serialize, Deserialize)]
+#[command(author, version, about, long_about = None)]
+pub struct LedgerAtHeightArgs {
+    /// Blockchain length of the canonical block
+    #[arg(long)]
+    height: u32,
+    /// Path to write the ledger
+    #[arg(short, long)]
+    path: PathBuf,
+}
+
 #[derive(clap::Args, Debug, Serialize, Deserialize)]
 #[command(author, version, about, long_about = None)]
 pub struct SummaryArgs {
@@ -73,6 +108,33 @@ pub struct SummaryArgs {
     verbose: bool,
 }
 
+#[derive(clap::Args, Debug, Serialize, Deserialize)]
+#[command(author, version, about, long_about = None)]
+pub struct TransactionArgs {
+    /// Hash of the transaction
+    #[arg(long)]
+    hash: String,
+}
+
+/// JSON output of an account, annotated with its balances at the best tip's global slot
+#[derive(Serialize)]
+struct AccountBalances<'a> {
+    #[serde(flatten)]
+    account: &'a Account,
+    global_slot: u32,
+    locked_balance: u64,
+    liquid_balance: u64,
+}
+
+/// JSON output of a best chain block, annotated with its consensus time
+#[derive(Serialize)]
+struct BestChainBlock<'a> {
+    #[serde(flatten)]
+    block: &'a PrecomputedBlock,
+    #[serde(flatten)]
+    consensus_time: &'a ConsensusTime,
+}
+
 #[instrument]
 pub async fn run(command: &ClientCli, output_json: bool) -> Result<(), anyhow::Error> {
     let conn = match LocalSocketStream::connect(SOCKET_NAME).await {
@@ -99,26 +161,88 @@ pub async fn run(command: &ClientCli, output_json: bool) -> Result<(), anyhow::E
             writer.write_all(command.as_bytes()).await?;
             reader.read_to_end(&mut buffer).await?;
 
-            let account: Account = bcs::from_bytes(&buffer)?;
+            let (account, global_slot): (Account, u32) = bcs::from_bytes(&buffer)?;
+            let locked_balance = account.locked_balance(global_slot);
+            let liquid_balance = account.liquid_balance(global_slot);
             if output_json {
+                let account = AccountBalances {
+                    account: &account,
+                    global_slot,
+                    locked_balance: locked_balance.0,
+                    liquid_balance: liquid_balance.0,
+                };
                 stdout()
                     .write_all(serde_json::to_string(&account)?.as_bytes())
                     .await?;
             } else {
                 stdout()
-                    .write_all(format!("{account:?}").as_bytes())
+                    .write_all(
+                        format!(
+                            "{account:?}locked: {}, liquid: {} (slot {global_slot})\n",
+                            nanomina_to_mina(locked_balance.0),
+                            nanomina_to_mina(liquid_balance.0)
+                        )
+                        .as_bytes(),
+                    )
                     .await?;
             }
         }
+        ClientCli::AccountTransactions(account_transactions_args) => {
+            let command = format!(
+                "account_transactions {} {}\0",
+                account_transactions_args.public_key, account_transactions_args.num
+            );
+            writer.write_all(command.as_bytes()).await?;
+            reader.read_to_end(&mut buffer).await?;
+
+            let transactions: Vec<(TransactionKey, UserCommandWithStatusV1)> =
+                bcs::from_bytes(&buffer)?;
+            for (key, cmd) in transactions {
+                if output_json {
+                    let json = serde_json::json!({
+                        "hash": key.hash(),
+                        "blockchain_length": key.height(),
+                        "timestamp": key.timestamp(),
+                        "command": UserCommandWithStatusJson::from(cmd.inner()),
+                    });
+                    stdout().write_all(format!("{json}\n").as_bytes()).await?;
+                } else {
+                    let signed_command =
+                        SignedCommand::from_user_command(UserCommandWithStatus(cmd));
+                    let kind = if signed_command.is_delegation() {
+                        "delegation"
+                    } else {
+                        "payment"
+                    };
+                    stdout()
+                        .write_all(
+                            format!(
+                                "{{ len: {}, hash: {}, {kind}: {} -> {}, fee: {} }}\n",
+                                key.height(),
+                                key.hash(),
+                                signed_command.source_pk().to_address(),
+                                signed_command.receiver_pk().to_address(),
+                                signed_command.fee().0,
+                            )
+                            .as_bytes(),
+                        )
+                        .await?;
+                }
+            }
+        }
         ClientCli::BestChain(chain_args) => {
             let command = format!("best_chain {}\0", chain_args.num);
             writer.write_all(command.as_bytes()).await?;
             reader.read_to_end(&mut buffer).await?;
-            let blocks: Vec<PrecomputedBlock> = bcs::from_bytes(&buffer)?;
-            for block in blocks.iter() {
+            let blocks: Vec<(PrecomputedBlock, ConsensusTime)> = bcs::from_bytes(&buffer)?;
+            for (block, consensus_time) in blocks.iter() {
                 if output_json {
+                    let block = BestChainBlock {
+                        block,
+                        consensus_time,
+                    };
                     stdout()
-                        .write_all(serde_json::to_string(block)?.as_bytes())
+                        .write_all(serde_json::to_string(&block)?.as_bytes())
                         .await?;
                 } else {
                     let block = Block::from_precomputed(block, block.blockchain_length);
@@ -133,6 +257,17 @@ pub async fn run(command: &ClientCli, output_json: bool) -> Result<(), anyhow::E
             let msg: String = bcs::from_bytes(&buffer)?;
             println!("{msg}");
         }
+        ClientCli::Ledger(ledger_args) => {
+            let command = format!(
+                "ledger_at_height {} {}\0",
+                ledger_args.height,
+                ledger_args.path.display()
+            );
+            writer.write_all(command.as_bytes()).await?;
+            reader.read_to_end(&mut buffer).await?;
+            let msg: String = bcs::from_bytes(&buffer)?;
+            println!("{msg}");
+        }
         ClientCli::Summary(summary_args) => {
             let command = format!("summary {}\0", summary_args.verbose);
             writer.write_all(command.as_bytes()).await?;
@@ -157,6 +292,54 @@ pub async fn run(command: &ClientCli, output_json: bool) -> Result<(), anyhow::E
                 }
             }
         }
+        ClientCli::Transaction(transaction_args) => {
+            let command = format!("transaction {}\0", transaction_args.hash);
+            writer.write_all(command.as_bytes()).await?;
+            reader.read_to_end(&mut buffer).await?;
+
+            let transactions: Vec<(
+                BlockHash,
+                TransactionKey,
+                Option<Canonicity>,
+                UserCommandWithStatusV1,
+            )> = bcs::from_bytes(&buffer)?;
+            if transactions.is_empty() {
+                stdout()
+                    .write_all(
+                        format!("Transaction {} not found\n", transaction_args.hash).as_bytes(),
+                    )
+                    .await?;
+            }
+            for (state_hash, key, canonicity, cmd) in transactions {
+                if output_json {
+                    let json = serde_json::json!({
+                        "hash": key.hash(),
+                        "block_state_hash": state_hash.0,
+                        "blockchain_length": key.height(),
+                        "canonicity": canonicity,
+                        "command": UserCommandWithStatusJson::from(cmd.inner()),
+                    });
+                    stdout().write_all(format!("{json}\n").as_bytes()).await?;
+                } else {
+                    let signed_command =
+                        SignedCommand::from_user_command(UserCommandWithStatus(cmd));
+                    stdout()
+                        .write_all(
+                            format!(
+                                "{{ hash: {}, block: {}, len: {}, canonicity: {canonicity:?}, {} -> {}, fee: {} }}\n",
+                                key.hash(),
+                                state_hash.0,
+                                key.height(),
+                                signed_command.source_pk().to_address(),
+                                signed_command.receiver_pk().to_address(),
+                                signed_command.fee().0,
+                            )
+                            .as_bytes(),
+                        )
+                        .await?;
+                }
+            }
+        }
         ClientCli::SaveState { out_dir } => {
             if !out_dir.is_dir() {
                 process::exit(100);
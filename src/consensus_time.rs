@@ -0,0 +1,116 @@
+use crate::network::NetworkProfile;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// Position of a global slot in consensus time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsensusTime {
+    pub global_slot: u32,
+    pub epoch: u32,
+    pub slot_in_epoch: u32,
+    /// Start of the slot (milliseconds since the unix epoch)
+    pub slot_time: u64,
+}
+
+impl ConsensusTime {
+    pub fn from_global_slot(global_slot: u32, network: &NetworkProfile) -> Self {
+        Self {
+            global_slot,
+            epoch: global_slot / network.slots_per_epoch,
+            slot_in_epoch: global_slot % network.slots_per_epoch,
+            slot_time: network.genesis_timestamp + global_slot as u64 * network.slot_duration,
+        }
+    }
+
+    /// `None` if the global slot overflows
+    pub fn from_epoch(epoch: u32, slot_in_epoch: u32, network: &NetworkProfile) -> Option<Self> {
+        let global_slot = epoch
+            .checked_mul(network.slots_per_epoch)?
+            .checked_add(slot_in_epoch)?;
+        Some(Self::from_global_slot(global_slot, network))
+    }
+
+    /// The slot containing `timestamp` (milliseconds since the unix epoch),
+    /// `None` if it's before genesis
+    pub fn from_timestamp(timestamp: u64, network: &NetworkProfile) -> Option<Self> {
+        let millis_since_genesis = timestamp.checked_sub(network.genesis_timestamp)?;
+        let global_slot = millis_since_genesis / network.slot_duration;
+        Some(Self::from_global_slot(
+            u32::try_from(global_slot).ok()?,
+            network,
+        ))
+    }
+
+    pub fn from_datetime(datetime: DateTime<Utc>, network: &NetworkProfile) -> Option<Self> {
+        Self::from_timestamp(u64::try_from(datetime.timestamp_millis()).ok()?, network)
+    }
+
+    /// Start of the slot in UTC
+    pub fn slot_datetime(&self) -> DateTime<Utc> {
+        millis_to_datetime(self.slot_time)
+    }
+}
+
+pub fn millis_to_datetime(millis: u64) -> DateTime<Utc> {
+    let naive_dt = NaiveDateTime::from_timestamp_millis(millis as i64).unwrap();
+    DateTime::<Utc>::from_naive_utc_and_offset(naive_dt, Utc)
+}
+
+impl std::fmt::Display for ConsensusTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "epoch: {}, slot: {}, slot time: {}",
+            self.epoch,
+            self.slot_in_epoch,
+            self.slot_datetime().to_rfc3339()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConsensusTime;
+    use crate::network::NetworkProfile;
+    use chrono::DateTime;
+
+    #[test]
+    fn slots_epochs_and_datetimes() {
+        let network = NetworkProfile::mainnet();
+
+        let genesis = ConsensusTime::from_global_slot(0, &network);
+        assert_eq!(genesis.epoch, 0);
+        assert_eq!(genesis.slot_in_epoch, 0);
+        assert_eq!(
+            genesis.slot_datetime(),
+            DateTime::parse_from_rfc3339("2021-03-17T00:00:00Z").unwrap()
+        );
+
+        let consensus_time = ConsensusTime::from_global_slot(7141, &network);
+        assert_eq!(consensus_time.epoch, 1);
+        assert_eq!(consensus_time.slot_in_epoch, 1);
+        assert_eq!(
+            ConsensusTime::from_epoch(1, 1, &network),
+            Some(consensus_time)
+        );
+        assert_eq!(ConsensusTime::from_epoch(u32::MAX, 1, &network), None);
+        assert_eq!(
+            consensus_time.slot_datetime(),
+            DateTime::parse_from_rfc3339("2021-03-31T21:03:00Z").unwrap()
+        );
+
+        // any time within the slot maps back to it
+        assert_eq!(
+            ConsensusTime::from_timestamp(consensus_time.slot_time + 179_999, &network),
+            Some(consensus_time)
+        );
+        assert_eq!(
+            ConsensusTime::from_datetime(consensus_time.slot_datetime(), &network),
+            Some(consensus_time)
+        );
+        assert_eq!(
+            ConsensusTime::from_timestamp(network.genesis_timestamp - 1, &network),
+            None
+        );
+    }
+}
@@ -15,6 +15,7 @@ use juniper::http::graphiql::graphiql_source;
 use juniper::http::GraphQLRequest;
 
 use crate::gql::root::Context;
+use crate::network::NetworkProfile;
 use crate::store::IndexerStore;
 
 pub mod root;
@@ -30,18 +31,20 @@ async fn graphql_playground() -> impl Responder {
 #[route("/gql", method = "GET", method = "POST")]
 pub async fn gql(
     db: Data<Arc<IndexerStore>>,
+    network: Data<NetworkProfile>,
     schema: Data<root::Schema>,
     data: Json<GraphQLRequest>,
 ) -> impl Responder {
-    let ctx = Context::new(db.as_ref().clone());
+    let ctx = Context::new(db.as_ref().clone(), network.as_ref().clone());
     let res = data.execute(&schema, &ctx).await;
     HttpResponse::Ok().json(res)
 }
 
-pub async fn start_gql(db: Arc<IndexerStore>) -> std::io::Result<()> {
+pub async fn start_gql(db: Arc<IndexerStore>, network: NetworkProfile) -> std::io::Result<()> {
     HttpServer::new(move || {
         App::new()
             .app_data(Data::new(db.clone()))
+            .app_data(Data::new(network.clone()))
             .app_data(Data::new(root::create_schema()))
             .service(gql)
             .service(graphql_playground)
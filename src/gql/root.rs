@@ -5,10 +5,20 @@ use juniper::EmptySubscription;
 use juniper::FieldResult;
 use juniper::RootNode;
 
+use crate::gql::schema::block;
+use crate::gql::schema::internal_command;
+use crate::gql::schema::ledger;
 use crate::gql::schema::stakes;
 use crate::gql::schema::transaction;
+use crate::gql::schema::Block;
+use crate::gql::schema::BlockQueryInput;
+use crate::gql::schema::InternalCommand;
+use crate::gql::schema::InternalCommandQueryInput;
+use crate::gql::schema::LedgerAccount;
+use crate::gql::schema::LedgerQueryInput;
 use crate::gql::schema::Transaction;
 use crate::gql::schema::TransactionQueryInput;
+use crate::network::NetworkProfile;
 use crate::staking_ledger::StakingLedgerAccount;
 use crate::store::IndexerStore;
 
@@ -16,11 +26,12 @@ use super::schema::StakesQueryInput;
 
 pub struct Context {
     pub db: Arc<IndexerStore>,
+    pub network: NetworkProfile,
 }
 
 impl Context {
-    pub fn new(db: Arc<IndexerStore>) -> Self {
-        Self { db }
+    pub fn new(db: Arc<IndexerStore>, network: NetworkProfile) -> Self {
+        Self { db, network }
     }
 }
 
@@ -35,6 +46,21 @@ impl QueryRoot {
         "0.1.1"
     }
 
+    #[graphql(description = "Get the block with the given state hash")]
+    fn block(ctx: &Context, state_hash: String) -> FieldResult<Option<Block>> {
+        Ok(block::get_block(ctx, &state_hash))
+    }
+
+    #[graphql(description = "List of all blocks")]
+    fn blocks(
+        ctx: &Context,
+        query: Option<BlockQueryInput>,
+        limit: Option<i32>,
+        sort_by: Option<block::SortBy>,
+    ) -> FieldResult<Vec<Block>> {
+        Ok(block::get_blocks(ctx, query, limit, sort_by))
+    }
+
     #[graphql(description = "List of all transactions")]
     fn transactions(
         ctx: &Context,
@@ -45,6 +71,27 @@ impl QueryRoot {
         Ok(transaction::get_transactions(ctx, query, limit, sort_by))
     }
 
+    #[graphql(description = "List of all internal commands")]
+    fn internal_commands(
+        ctx: &Context,
+        query: Option<InternalCommandQueryInput>,
+        limit: Option<i32>,
+        sort_by: Option<internal_command::SortBy>,
+    ) -> FieldResult<Vec<InternalCommand>> {
+        Ok(internal_command::get_internal_commands(
+            ctx, query, limit, sort_by,
+        ))
+    }
+
+    #[graphql(description = "Get canonical ledger accounts at a past height or global slot")]
+    fn ledger(
+        ctx: &Context,
+        query: LedgerQueryInput,
+        limit: Option<i32>,
+    ) -> FieldResult<Vec<LedgerAccount>> {
+        ledger::get_accounts(ctx, query, limit)
+    }
+
     #[graphql(description = "Get staking ledger entry")]
     fn stakes(
         ctx: &Context,
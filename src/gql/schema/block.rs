@@ -0,0 +1,329 @@
+use chrono::DateTime;
+use chrono::Utc;
+use juniper::GraphQLEnum;
+use juniper::GraphQLInputObject;
+use rocksdb::Direction;
+use rocksdb::IteratorMode;
+
+use crate::block::precomputed::PrecomputedBlock;
+use crate::block::store::BlockStore;
+use crate::block::BlockHash;
+use crate::consensus_time::millis_to_datetime;
+use crate::consensus_time::ConsensusTime;
+use crate::gql::root::Context;
+use crate::network::NetworkProfile;
+use crate::state::Canonicity;
+use crate::store::block_index_key;
+
+pub struct Block {
+    pub state_hash: String,
+    pub parent_hash: String,
+    pub block_height: i32,
+    pub global_slot: i32,
+    pub epoch: i32,
+    pub slot_in_epoch: i32,
+    pub slot_time: DateTime<Utc>,
+    pub date_time: DateTime<Utc>,
+    pub creator: String,
+    pub coinbase_receiver: String,
+    pub tx_count: i32,
+    pub canonicity: BlockCanonicity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum BlockCanonicity {
+    #[graphql(name = "CANONICAL")]
+    Canonical,
+    #[graphql(name = "ORPHANED")]
+    Orphaned,
+    #[graphql(name = "PENDING")]
+    Pending,
+}
+
+impl From<Option<Canonicity>> for BlockCanonicity {
+    fn from(value: Option<Canonicity>) -> Self {
+        match value {
+            Some(Canonicity::Canonical) => Self::Canonical,
+            Some(Canonicity::Orphaned) => Self::Orphaned,
+            Some(Canonicity::Pending) | None => Self::Pending,
+        }
+    }
+}
+
+impl Block {
+    pub fn from_precomputed(
+        block: &PrecomputedBlock,
+        canonicity: Option<Canonicity>,
+        network: &NetworkProfile,
+    ) -> Self {
+        let consensus_time =
+            ConsensusTime::from_global_slot(block.global_slot_since_genesis(), network);
+
+        Self {
+            state_hash: block.state_hash.clone(),
+            parent_hash: BlockHash::previous_state_hash(block).0,
+            block_height: block.blockchain_length as i32,
+            global_slot: consensus_time.global_slot as i32,
+            epoch: consensus_time.epoch as i32,
+            slot_in_epoch: consensus_time.slot_in_epoch as i32,
+            slot_time: consensus_time.slot_datetime(),
+            date_time: millis_to_datetime(block.timestamp()),
+            creator: block.block_creator().to_address(),
+            coinbase_receiver: block.coinbase_receiver().to_address(),
+            tx_count: block.commands().len() as i32,
+            canonicity: canonicity.into(),
+        }
+    }
+}
+
+#[derive(Debug, GraphQLEnum)]
+pub enum SortBy {
+    #[graphql(name = "BLOCKHEIGHT_DESC")]
+    BlockHeightDesc,
+    #[graphql(name = "BLOCKHEIGHT_ASC")]
+    BlockHeightAsc,
+    #[graphql(name = "GLOBALSLOT_DESC")]
+    GlobalSlotDesc,
+    #[graphql(name = "GLOBALSLOT_ASC")]
+    GlobalSlotAsc,
+}
+
+#[derive(GraphQLInputObject)]
+#[graphql(description = "Block query input")]
+pub struct BlockQueryInput {
+    pub state_hash: Option<String>,
+    pub parent_hash: Option<String>,
+    pub creator: Option<String>,
+    pub coinbase_receiver: Option<String>,
+    pub canonicity: Option<BlockCanonicity>,
+    // Logical  operators
+    #[graphql(name = "OR")]
+    pub or: Option<Vec<BlockQueryInput>>,
+    #[graphql(name = "AND")]
+    pub and: Option<Vec<BlockQueryInput>>,
+    // Comparison operators
+    #[graphql(name = "blockHeight_gte")]
+    pub block_height_gte: Option<i32>,
+    #[graphql(name = "blockHeight_lte")]
+    pub block_height_lte: Option<i32>,
+    #[graphql(name = "globalSlot_gte")]
+    pub global_slot_gte: Option<i32>,
+    #[graphql(name = "globalSlot_lte")]
+    pub global_slot_lte: Option<i32>,
+    #[graphql(name = "dateTime_gte")]
+    pub datetime_gte: Option<DateTime<Utc>>,
+    #[graphql(name = "dateTime_lte")]
+    pub datetime_lte: Option<DateTime<Utc>>,
+}
+
+impl BlockQueryInput {
+    fn matches(&self, block: &Block) -> bool {
+        let mut matches = true;
+
+        if let Some(ref state_hash) = self.state_hash {
+            matches = matches && block.state_hash == *state_hash;
+        }
+
+        if let Some(ref parent_hash) = self.parent_hash {
+            matches = matches && block.parent_hash == *parent_hash;
+        }
+
+        if let Some(ref creator) = self.creator {
+            matches = matches && block.creator == *creator;
+        }
+
+        if let Some(ref coinbase_receiver) = self.coinbase_receiver {
+            matches = matches && block.coinbase_receiver == *coinbase_receiver;
+        }
+
+        if let Some(canonicity) = self.canonicity {
+            matches = matches && block.canonicity == canonicity;
+        }
+
+        if let Some(ref query) = self.and {
+            matches = matches && query.iter().all(|and| and.matches(block));
+        }
+
+        if let Some(ref query) = self.or {
+            if !query.is_empty() {
+                matches = matches && query.iter().any(|or| or.matches(block));
+            }
+        }
+
+        if let Some(block_height_gte) = self.block_height_gte {
+            matches = matches && block.block_height >= block_height_gte;
+        }
+
+        if let Some(block_height_lte) = self.block_height_lte {
+            matches = matches && block.block_height <= block_height_lte;
+        }
+
+        if let Some(global_slot_gte) = self.global_slot_gte {
+            matches = matches && block.global_slot >= global_slot_gte;
+        }
+
+        if let Some(global_slot_lte) = self.global_slot_lte {
+            matches = matches && block.global_slot <= global_slot_lte;
+        }
+
+        if let Some(datetime_gte) = self.datetime_gte {
+            matches = matches && block.date_time >= datetime_gte;
+        }
+
+        if let Some(datetime_lte) = self.datetime_lte {
+            matches = matches && block.date_time <= datetime_lte;
+        }
+
+        matches
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+#[graphql(description = "Block")]
+impl Block {
+    #[graphql(description = "State hash")]
+    fn state_hash(&self) -> &str {
+        &self.state_hash
+    }
+
+    #[graphql(description = "Parent hash")]
+    fn parent_hash(&self) -> &str {
+        &self.parent_hash
+    }
+
+    #[graphql(description = "Block height")]
+    fn block_height(&self) -> i32 {
+        self.block_height
+    }
+
+    #[graphql(description = "Global slot since genesis")]
+    fn global_slot(&self) -> i32 {
+        self.global_slot
+    }
+
+    #[graphql(description = "Epoch")]
+    fn epoch(&self) -> i32 {
+        self.epoch
+    }
+
+    #[graphql(description = "Slot in the epoch")]
+    fn slot_in_epoch(&self) -> i32 {
+        self.slot_in_epoch
+    }
+
+    #[graphql(description = "Start of the slot")]
+    fn slot_time(&self) -> DateTime<Utc> {
+        self.slot_time
+    }
+
+    #[graphql(description = "Datetime")]
+    fn date_time(&self) -> DateTime<Utc> {
+        self.date_time
+    }
+
+    #[graphql(description = "Block creator")]
+    fn creator(&self) -> &str {
+        &self.creator
+    }
+
+    #[graphql(description = "Coinbase receiver")]
+    fn coinbase_receiver(&self) -> &str {
+        &self.coinbase_receiver
+    }
+
+    #[graphql(description = "Number of user commands")]
+    fn tx_count(&self) -> i32 {
+        self.tx_count
+    }
+
+    #[graphql(description = "Canonicity")]
+    fn canonicity(&self) -> BlockCanonicity {
+        self.canonicity
+    }
+}
+
+pub fn get_block(ctx: &Context, state_hash: &str) -> Option<Block> {
+    let state_hash = BlockHash(state_hash.to_owned());
+    let block = ctx.db.get_block(&state_hash).unwrap()?;
+    let canonicity = ctx.db.get_canonicity(&state_hash).unwrap();
+    Some(Block::from_precomputed(&block, canonicity, &ctx.network))
+}
+
+pub fn get_blocks(
+    ctx: &Context,
+    query: Option<BlockQueryInput>,
+    limit: Option<i32>,
+    sort_by: Option<SortBy>,
+) -> Vec<Block> {
+    let limit = limit.unwrap_or(100);
+    let limit_idx = limit as usize;
+
+    // walk the height or slot index in the requested order
+    let sort_by = sort_by.unwrap_or(SortBy::BlockHeightDesc);
+    let (cf, ascending, gte, lte) = match sort_by {
+        SortBy::BlockHeightDesc | SortBy::BlockHeightAsc => (
+            "blocks-height",
+            matches!(sort_by, SortBy::BlockHeightAsc),
+            query.as_ref().and_then(|q| q.block_height_gte),
+            query.as_ref().and_then(|q| q.block_height_lte),
+        ),
+        SortBy::GlobalSlotDesc | SortBy::GlobalSlotAsc => (
+            "blocks-slot",
+            matches!(sort_by, SortBy::GlobalSlotAsc),
+            query.as_ref().and_then(|q| q.global_slot_gte),
+            query.as_ref().and_then(|q| q.global_slot_lte),
+        ),
+    };
+    let gte = gte.map(|n| n.max(0) as u32);
+    let lte = lte.map(|n| n.max(0) as u32);
+
+    let mut iter = ctx.db.iterator_cf(cf);
+    match (ascending, gte, lte) {
+        (true, Some(gte), _) => {
+            let key = block_index_key(gte, "");
+            iter.set_mode(IteratorMode::From(&key, Direction::Forward));
+        }
+        (false, _, Some(lte)) => {
+            let key = block_index_key(lte.saturating_add(1), "");
+            iter.set_mode(IteratorMode::From(&key, Direction::Reverse));
+        }
+        (false, _, None) => iter.set_mode(IteratorMode::End),
+        (true, None, _) => (),
+    }
+
+    let mut blocks: Vec<Block> = Vec::new();
+    for entry in iter {
+        let (key, value) = entry.unwrap();
+
+        // stop once we're past the requested range
+        let n = std::str::from_utf8(&key[..12])
+            .unwrap()
+            .parse::<u32>()
+            .unwrap();
+        if (ascending && lte.map_or(false, |lte| n > lte))
+            || (!ascending && gte.map_or(false, |gte| n < gte))
+        {
+            break;
+        }
+
+        let state_hash = String::from_utf8(value.to_vec()).unwrap();
+        if let Some(block) = get_block(ctx, &state_hash) {
+            // If query is provided, only add blocks that satisfy the query
+            if let Some(ref query_input) = query {
+                if query_input.matches(&block) {
+                    blocks.push(block);
+                }
+            }
+            // If no query is provided, add all blocks
+            else {
+                blocks.push(block);
+            }
+        }
+        // Early break if the blocks reach the query limit
+        if blocks.len() >= limit_idx {
+            break;
+        }
+    }
+
+    blocks
+}
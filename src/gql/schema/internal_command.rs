@@ -0,0 +1,238 @@
+use chrono::DateTime;
+use chrono::NaiveDateTime;
+use chrono::Utc;
+use data_encoding::BASE32HEX;
+use juniper::GraphQLEnum;
+use juniper::GraphQLInputObject;
+use rocksdb::Direction;
+use rocksdb::IteratorMode;
+
+use crate::block::BlockHash;
+use crate::gql::root::Context;
+use crate::state::ledger::command::InternalCommand as Command;
+use crate::state::Canonicity;
+use crate::store::TransactionKey;
+
+pub struct InternalCommand {
+    pub block_state_hash: String,
+    pub to: String,
+    pub block_height: i32,
+    pub date_time: DateTime<Utc>,
+    pub canonical: bool,
+    pub kind: String,
+    pub fee: f64,
+}
+
+impl InternalCommand {
+    pub fn from_cmd(
+        cmd: Command,
+        height: i32,
+        timestamp: u64,
+        state_hash: &str,
+        canonical: bool,
+    ) -> Self {
+        let naive_dt = NaiveDateTime::from_timestamp_millis(timestamp as i64).unwrap();
+        let datetime = DateTime::<Utc>::from_naive_utc_and_offset(naive_dt, Utc);
+
+        Self {
+            block_state_hash: state_hash.to_owned(),
+            to: cmd.receiver().to_address(),
+            block_height: height,
+            date_time: datetime,
+            canonical,
+            kind: cmd.kind().to_owned(),
+            fee: cmd.amount().0 as f64 / 1_000_000_000_f64,
+        }
+    }
+}
+
+#[derive(Debug, GraphQLEnum)]
+#[graphql(name = "InternalCommandSortBy")]
+pub enum SortBy {
+    #[graphql(name = "BLOCKHEIGHT_DESC")]
+    BlockHeightDesc,
+    #[graphql(name = "BLOCKHEIGHT_ASC")]
+    BlockHeightAsc,
+}
+
+#[derive(GraphQLInputObject)]
+#[graphql(description = "Internal command query input")]
+pub struct InternalCommandQueryInput {
+    pub block_state_hash: Option<String>,
+    pub to: Option<String>,
+    pub canonical: Option<bool>,
+    pub kind: Option<String>,
+    pub fee: Option<f64>,
+    // Logical  operators
+    #[graphql(name = "OR")]
+    pub or: Option<Vec<InternalCommandQueryInput>>,
+    #[graphql(name = "AND")]
+    pub and: Option<Vec<InternalCommandQueryInput>>,
+    // Comparison operators
+    #[graphql(name = "dateTime_gte")]
+    pub datetime_gte: Option<DateTime<Utc>>,
+    #[graphql(name = "dateTime_lte")]
+    pub datetime_lte: Option<DateTime<Utc>>,
+}
+
+impl InternalCommandQueryInput {
+    fn matches(&self, internal_command: &InternalCommand) -> bool {
+        let mut matches = true;
+
+        if let Some(ref block_state_hash) = self.block_state_hash {
+            matches = matches && internal_command.block_state_hash == *block_state_hash;
+        }
+
+        if let Some(ref fee) = self.fee {
+            matches = matches && internal_command.fee == *fee;
+        }
+
+        if let Some(ref kind) = self.kind {
+            matches = matches && internal_command.kind == *kind;
+        }
+
+        if let Some(canonical) = self.canonical {
+            matches = matches && internal_command.canonical == canonical;
+        }
+
+        if let Some(ref to) = self.to {
+            matches = matches && internal_command.to == *to;
+        }
+
+        if let Some(ref query) = self.and {
+            matches = matches && query.iter().all(|and| and.matches(internal_command));
+        }
+
+        if let Some(ref query) = self.or {
+            if !query.is_empty() {
+                matches = matches && query.iter().any(|or| or.matches(internal_command));
+            }
+        }
+
+        if let Some(datetime_gte) = self.datetime_gte {
+            matches = matches && internal_command.date_time >= datetime_gte;
+        }
+
+        if let Some(datetime_lte) = self.datetime_lte {
+            matches = matches && internal_command.date_time <= datetime_lte;
+        }
+
+        matches
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+#[graphql(description = "Internal command (fee transfer or coinbase)")]
+impl InternalCommand {
+    #[graphql(description = "Block state hash")]
+    fn block_state_hash(&self) -> &str {
+        &self.block_state_hash
+    }
+
+    #[graphql(description = "To")]
+    fn to(&self) -> &str {
+        &self.to
+    }
+
+    #[graphql(description = "Block height")]
+    fn block_height(&self) -> i32 {
+        self.block_height
+    }
+
+    #[graphql(description = "Datetime")]
+    fn date_time(&self) -> DateTime<Utc> {
+        self.date_time
+    }
+
+    #[graphql(description = "Canonical")]
+    fn canonical(&self) -> bool {
+        self.canonical
+    }
+
+    #[graphql(description = "Kind")]
+    fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    #[graphql(description = "Fee")]
+    fn fee(&self) -> f64 {
+        self.fee
+    }
+}
+
+pub fn get_internal_commands(
+    ctx: &Context,
+    query: Option<InternalCommandQueryInput>,
+    limit: Option<i32>,
+    sort_by: Option<SortBy>,
+) -> Vec<InternalCommand> {
+    let limit = limit.unwrap_or(100);
+    let limit_idx = limit as usize;
+
+    let mut internal_commands: Vec<InternalCommand> = Vec::new();
+
+    let iter = if let Some(ref query_input) = query {
+        if let Some(datetime_gte) = query_input.datetime_gte {
+            let bytes = datetime_gte.timestamp_millis().to_string().into_bytes();
+            let key = BASE32HEX.encode(&bytes);
+
+            let mut iter = ctx.db.iterator_cf("internal-commands");
+            iter.set_mode(IteratorMode::From(&key.into_bytes(), Direction::Forward));
+            iter
+        } else {
+            ctx.db.iterator_cf("internal-commands")
+        }
+    } else {
+        ctx.db.iterator_cf("internal-commands")
+    };
+
+    'outer: for entry in iter {
+        let (key, value) = entry.unwrap();
+
+        // each entry holds all of a block's internal commands
+        let key = TransactionKey::from_slice(&key).unwrap();
+        let cmds = bcs::from_bytes::<Vec<Command>>(&value).unwrap();
+        let canonical = matches!(
+            ctx.db.get_canonicity(&BlockHash(key.hash().to_owned())),
+            Ok(Some(Canonicity::Canonical))
+        );
+
+        for cmd in cmds {
+            let internal_command = InternalCommand::from_cmd(
+                cmd,
+                key.height() as i32,
+                key.timestamp(),
+                key.hash(),
+                canonical,
+            );
+
+            // If query is provided, only add internal commands that satisfy the query
+            if let Some(ref query_input) = query {
+                if query_input.matches(&internal_command) {
+                    internal_commands.push(internal_command);
+                }
+            }
+            // If no query is provided, add all internal commands
+            else {
+                internal_commands.push(internal_command);
+            }
+            // Early break if the internal commands reach the query limit
+            if internal_commands.len() >= limit_idx {
+                break 'outer;
+            }
+        }
+    }
+
+    if let Some(sort_by) = sort_by {
+        match sort_by {
+            SortBy::BlockHeightDesc => {
+                internal_commands.sort_by(|a, b| b.block_height.cmp(&a.block_height))
+            }
+            SortBy::BlockHeightAsc => {
+                internal_commands.sort_by(|a, b| a.block_height.cmp(&b.block_height))
+            }
+        }
+    }
+
+    internal_commands
+}
@@ -0,0 +1,124 @@
+use juniper::{FieldResult, GraphQLInputObject};
+
+use crate::{
+    block::store::BlockStore,
+    gql::root::Context,
+    state::ledger::{account::Account, store::LedgerStore},
+};
+
+pub struct LedgerAccount {
+    pub public_key: String,
+    pub balance: f64,
+    pub nonce: i32,
+    pub delegate: String,
+    pub locked_balance: f64,
+    pub liquid_balance: f64,
+}
+
+impl LedgerAccount {
+    /// Locked and liquid balances are computed at `global_slot`
+    pub fn from_account(account: &Account, global_slot: u32) -> Self {
+        let public_key = account.public_key.to_address();
+        Self {
+            delegate: account
+                .delegate
+                .as_ref()
+                .map(|pk| pk.to_address())
+                .unwrap_or(public_key.clone()),
+            public_key,
+            balance: account.balance.0 as f64 / 1_000_000_000_f64,
+            nonce: account.nonce.0 as i32,
+            locked_balance: account.locked_balance(global_slot).0 as f64 / 1_000_000_000_f64,
+            liquid_balance: account.liquid_balance(global_slot).0 as f64 / 1_000_000_000_f64,
+        }
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+#[graphql(description = "Ledger account")]
+impl LedgerAccount {
+    #[graphql(description = "Public Key")]
+    fn public_key(&self) -> &str {
+        &self.public_key
+    }
+    #[graphql(description = "Account balance")]
+    fn balance(&self) -> f64 {
+        self.balance
+    }
+    #[graphql(description = "Nonce")]
+    fn nonce(&self) -> i32 {
+        self.nonce
+    }
+    #[graphql(description = "Delegate Key")]
+    fn delegate(&self) -> &str {
+        &self.delegate
+    }
+    #[graphql(description = "Balance locked by the account's vesting schedule")]
+    fn locked_balance(&self) -> f64 {
+        self.locked_balance
+    }
+    #[graphql(description = "Spendable balance")]
+    fn liquid_balance(&self) -> f64 {
+        self.liquid_balance
+    }
+}
+
+#[derive(GraphQLInputObject)]
+#[graphql(description = "Ledger query input")]
+pub struct LedgerQueryInput {
+    /// Blockchain length of the canonical block
+    pub height: Option<i32>,
+    /// Global slot, resolved to the last canonical block at or before it
+    pub global_slot: Option<i32>,
+    pub public_key: Option<String>,
+}
+
+/// Errors if the height, global slot or limit is negative
+pub fn get_accounts(
+    ctx: &Context,
+    query: LedgerQueryInput,
+    limit: Option<i32>,
+) -> FieldResult<Vec<LedgerAccount>> {
+    let limit = limit.unwrap_or(100);
+    let limit_idx = usize::try_from(limit).map_err(|_| format!("Negative limit {limit}"))?;
+
+    let (ledger, global_slot) = if let Some(height) = query.height {
+        let height = u32::try_from(height).map_err(|_| format!("Negative height {height}"))?;
+        let global_slot = match ctx.db.get_canonical_hash_at_height(height) {
+            Ok(Some(state_hash)) => match ctx.db.get_block(&state_hash) {
+                Ok(Some(block)) => block.global_slot_since_genesis(),
+                _ => return Ok(vec![]),
+            },
+            _ => return Ok(vec![]),
+        };
+        (ctx.db.get_ledger_at_height(height), global_slot)
+    } else if let Some(global_slot) = query.global_slot {
+        let global_slot = u32::try_from(global_slot)
+            .map_err(|_| format!("Negative global slot {global_slot}"))?;
+        (ctx.db.get_ledger_at_slot(global_slot), global_slot)
+    } else {
+        return Ok(vec![]);
+    };
+
+    let mut accounts: Vec<&Account> = Vec::new();
+    let ledger = match ledger? {
+        Some(ledger) => ledger,
+        None => return Ok(vec![]),
+    };
+    for account in ledger.accounts.values() {
+        if let Some(ref public_key) = query.public_key {
+            if account.public_key.to_address() == *public_key {
+                accounts.push(account);
+            }
+        } else {
+            accounts.push(account);
+        }
+    }
+
+    accounts.sort();
+    Ok(accounts
+        .into_iter()
+        .take(limit_idx)
+        .map(|account| LedgerAccount::from_account(account, global_slot))
+        .collect())
+}
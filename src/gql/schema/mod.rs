@@ -1,7 +1,16 @@
+pub use crate::gql::schema::block::Block;
+pub use crate::gql::schema::block::BlockQueryInput;
+pub use crate::gql::schema::internal_command::InternalCommand;
+pub use crate::gql::schema::internal_command::InternalCommandQueryInput;
+pub use crate::gql::schema::ledger::LedgerAccount;
+pub use crate::gql::schema::ledger::LedgerQueryInput;
 pub use crate::gql::schema::stakes::Stakes;
 pub use crate::gql::schema::stakes::StakesQueryInput;
 pub use crate::gql::schema::transaction::Transaction;
 pub use crate::gql::schema::transaction::TransactionQueryInput;
 
+pub mod block;
+pub mod internal_command;
+pub mod ledger;
 pub mod stakes;
 pub mod transaction;
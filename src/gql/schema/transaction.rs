@@ -1,5 +1,6 @@
+use std::collections::HashMap;
+
 use chrono::DateTime;
-use chrono::NaiveDateTime;
 use chrono::Utc;
 use data_encoding::BASE32HEX;
 use juniper::GraphQLEnum;
@@ -8,18 +9,29 @@ use mina_serialization_types::json::UserCommandWithStatusJson;
 use mina_serialization_types::staged_ledger_diff::SignedCommandPayloadBodyJson;
 use mina_serialization_types::staged_ledger_diff::StakeDelegationJson;
 use mina_serialization_types::staged_ledger_diff::UserCommandJson;
-use mina_serialization_types::v1::UserCommandWithStatusV1;
 use rocksdb::Direction;
 use rocksdb::IteratorMode;
 
+use crate::block::store::BlockStore;
+use crate::block::BlockHash;
+use crate::consensus_time::millis_to_datetime;
+use crate::consensus_time::ConsensusTime;
 use crate::gql::root::Context;
+use crate::network::NetworkProfile;
+use crate::state::ledger::public_key::PublicKey;
+use crate::state::Canonicity;
+use crate::store::StoredTransaction;
 use crate::store::TransactionKey;
 pub struct Transaction {
     pub hash: String,
+    pub block_state_hash: String,
     pub from: String,
     pub to: String,
     pub memo: String,
     pub block_height: i32,
+    pub epoch: i32,
+    pub slot_in_epoch: i32,
+    pub slot_time: DateTime<Utc>,
     pub date_time: DateTime<Utc>,
     pub canonical: bool,
     pub kind: String,
@@ -27,15 +39,18 @@ pub struct Transaction {
     pub nonce: i32,
     pub fee: f64,
     pub amount: f64,
+    pub status: String,
+    pub failure_reason: Option<String>,
 }
 
 impl Transaction {
     pub fn from_cmd(
-        cmd: UserCommandWithStatusJson,
-        height: i32,
-        timestamp: u64,
-        hash: &str,
+        tx: StoredTransaction,
+        key: &TransactionKey,
+        canonical: bool,
+        network: &NetworkProfile,
     ) -> Self {
+        let cmd = UserCommandWithStatusJson::from(tx.command.inner());
         match cmd.data {
             UserCommandJson::SignedCommand(signed_cmd) => {
                 let payload = signed_cmd.payload;
@@ -61,22 +76,33 @@ impl Transaction {
                     }
                 };
 
-                let naive_dt = NaiveDateTime::from_timestamp_millis(timestamp as i64).unwrap();
-                let datetime = DateTime::<Utc>::from_naive_utc_and_offset(naive_dt, Utc);
+                let status = serde_json::to_value(&cmd.status).unwrap_or_default();
+                let consensus_time = ConsensusTime::from_global_slot(tx.global_slot, network);
 
                 Self {
-                    hash: hash.to_owned(),
+                    hash: key.hash().to_owned(),
+                    block_state_hash: tx.state_hash.0,
                     from: sanitize_json(sender),
                     to: sanitize_json(receiver),
                     memo: sanitize_json(payload.common.memo),
-                    block_height: height,
-                    date_time: datetime,
-                    canonical: true,
+                    block_height: key.height() as i32,
+                    epoch: consensus_time.epoch as i32,
+                    slot_in_epoch: consensus_time.slot_in_epoch as i32,
+                    slot_time: consensus_time.slot_datetime(),
+                    date_time: millis_to_datetime(key.timestamp()),
+                    canonical,
                     kind: kind.to_owned(),
                     token: token_id as i32,
                     nonce: nonce as i32,
                     fee: fee as f64 / 1_000_000_000_f64,
                     amount: amount as f64 / 1_000_000_000_f64,
+                    status: if status[0] == "Failed" {
+                        "FAILED"
+                    } else {
+                        "APPLIED"
+                    }
+                    .to_owned(),
+                    failure_reason: tx.failure_reason,
                 }
             }
         }
@@ -180,6 +206,11 @@ impl Transaction {
     fn hash(&self) -> &str {
         &self.hash
     }
+    #[graphql(description = "Block state hash")]
+    fn block_state_hash(&self) -> &str {
+        &self.block_state_hash
+    }
+
     #[graphql(description = "From")]
     fn from(&self) -> &str {
         &self.from
@@ -200,6 +231,21 @@ impl Transaction {
         self.block_height
     }
 
+    #[graphql(description = "Epoch")]
+    fn epoch(&self) -> i32 {
+        self.epoch
+    }
+
+    #[graphql(description = "Slot in the epoch")]
+    fn slot_in_epoch(&self) -> i32 {
+        self.slot_in_epoch
+    }
+
+    #[graphql(description = "Start of the slot")]
+    fn slot_time(&self) -> DateTime<Utc> {
+        self.slot_time
+    }
+
     #[graphql(description = "Datetime")]
     fn date_time(&self) -> DateTime<Utc> {
         self.date_time
@@ -234,6 +280,14 @@ impl Transaction {
     fn amount(&self) -> f64 {
         self.amount
     }
+    #[graphql(description = "Status (APPLIED or FAILED)")]
+    fn status(&self) -> &str {
+        &self.status
+    }
+    #[graphql(description = "Reason the transaction failed")]
+    fn failure_reason(&self) -> Option<&str> {
+        self.failure_reason.as_deref()
+    }
 }
 
 pub fn get_transactions(
@@ -246,50 +300,65 @@ pub fn get_transactions(
     let limit_idx = limit as usize;
 
     let mut transactions: Vec<Transaction> = Vec::new();
-
-    let iter = if let Some(ref query_input) = query {
-        if let Some(datetime_gte) = query_input.datetime_gte {
-            let bytes = datetime_gte.timestamp_millis().to_string().into_bytes();
-            let key = BASE32HEX.encode(&bytes);
-
-            let mut iter = ctx.db.iterator_cf("tx");
-            iter.set_mode(IteratorMode::From(&key.into_bytes(), Direction::Forward));
-            iter
-        } else {
-            ctx.db.iterator_cf("tx")
+    let mut canonicity = CanonicityCache::default();
+
+    // use the hash or sender/receiver indices instead of scanning all transactions
+    if let Some(tx_keys) = query
+        .as_ref()
+        .and_then(|query_input| indexed_tx_keys(ctx, query_input))
+    {
+        for key in tx_keys {
+            if let Some(tx) = ctx.db.get_tx(&key).unwrap() {
+                let canonical = canonicity.canonical(ctx, &tx.state_hash);
+                let transaction = Transaction::from_cmd(tx, &key, canonical, &ctx.network);
+
+                if query.as_ref().unwrap().matches(&transaction) {
+                    transactions.push(transaction);
+                }
+                // Early break if the transactions reach the query limit
+                if transactions.len() >= limit_idx {
+                    break;
+                }
+            }
         }
     } else {
-        ctx.db.iterator_cf("tx")
-    };
-
-    for entry in iter {
-        let (key, value) = entry.unwrap();
-
-        let key = TransactionKey::from_slice(&key).unwrap();
-        let cmd = bcs::from_bytes::<UserCommandWithStatusV1>(&value)
-            .unwrap()
-            .inner();
-
-        let transaction = Transaction::from_cmd(
-            UserCommandWithStatusJson::from(cmd),
-            key.height() as i32,
-            key.timestamp(),
-            key.hash(),
-        );
-
-        // If query is provided, only add transactions that satisfy the query
-        if let Some(ref query_input) = query {
-            if query_input.matches(&transaction) {
+        let iter = if let Some(ref query_input) = query {
+            if let Some(datetime_gte) = query_input.datetime_gte {
+                let bytes = datetime_gte.timestamp_millis().to_string().into_bytes();
+                let key = BASE32HEX.encode(&bytes);
+
+                let mut iter = ctx.db.iterator_cf("tx");
+                iter.set_mode(IteratorMode::From(&key.into_bytes(), Direction::Forward));
+                iter
+            } else {
+                ctx.db.iterator_cf("tx")
+            }
+        } else {
+            ctx.db.iterator_cf("tx")
+        };
+
+        for entry in iter {
+            let (key, value) = entry.unwrap();
+
+            let key = TransactionKey::from_slice(&key).unwrap();
+            let tx = bcs::from_bytes::<StoredTransaction>(&value).unwrap();
+            let canonical = canonicity.canonical(ctx, &tx.state_hash);
+            let transaction = Transaction::from_cmd(tx, &key, canonical, &ctx.network);
+
+            // If query is provided, only add transactions that satisfy the query
+            if let Some(ref query_input) = query {
+                if query_input.matches(&transaction) {
+                    transactions.push(transaction);
+                }
+            }
+            // If no query is provided, add all transactions
+            else {
                 transactions.push(transaction);
             }
-        }
-        // If no query is provided, add all transactions
-        else {
-            transactions.push(transaction);
-        }
-        // Early break if the transactions reach the query limit
-        if transactions.len() >= limit_idx {
-            break;
+            // Early break if the transactions reach the query limit
+            if transactions.len() >= limit_idx {
+                break;
+            }
         }
     }
 
@@ -302,3 +371,46 @@ pub fn get_transactions(
 
     transactions
 }
+
+/// Canonicity of the blocks seen while answering a query, so each block's
+/// canonicity is only looked up once
+#[derive(Default)]
+struct CanonicityCache(HashMap<BlockHash, bool>);
+
+impl CanonicityCache {
+    fn canonical(&mut self, ctx: &Context, state_hash: &BlockHash) -> bool {
+        *self.0.entry(state_hash.clone()).or_insert_with(|| {
+            matches!(
+                ctx.db.get_canonicity(state_hash),
+                Ok(Some(Canonicity::Canonical))
+            )
+        })
+    }
+}
+
+/// Transaction keys from the hash, sender or receiver index, if the query filters on any
+fn indexed_tx_keys(ctx: &Context, query: &TransactionQueryInput) -> Option<Vec<TransactionKey>> {
+    if let Some(ref hash) = query.hash {
+        return ctx.db.get_tx_blocks(hash).ok().map(|tx_blocks| {
+            tx_blocks
+                .into_iter()
+                .map(|(_, key)| key)
+                .collect::<Vec<TransactionKey>>()
+        });
+    }
+    if let Some(public_key) = query
+        .from
+        .as_ref()
+        .and_then(|pk| PublicKey::from_address(pk).ok())
+    {
+        return ctx.db.get_tx_keys_from(&public_key).ok();
+    }
+    if let Some(public_key) = query
+        .to
+        .as_ref()
+        .and_then(|pk| PublicKey::from_address(pk).ok())
+    {
+        return ctx.db.get_tx_keys_to(&public_key).ok();
+    }
+    None
+}
@@ -1,6 +1,8 @@
 pub mod block;
 pub mod client;
+pub mod consensus_time;
 pub mod gql;
+pub mod network;
 pub mod receiver;
 pub mod server;
 pub mod staking_ledger;
@@ -10,10 +12,18 @@ pub mod store;
 pub const BLOCK_REPORTING_FREQ_NUM: u32 = 5000;
 pub const BLOCK_REPORTING_FREQ_SEC: u64 = 180;
 pub const CANONICAL_UPDATE_THRESHOLD: u32 = PRUNE_INTERVAL_DEFAULT / 5;
+pub const LEDGER_CHECKPOINT_INTERVAL: u32 = 1000;
+pub const MAINNET_ACCOUNT_CREATION_FEE: u64 = 1_000_000_000;
 pub const MAINNET_CANONICAL_THRESHOLD: u32 = 10;
+pub const MAINNET_COINBASE_REWARD: u64 = 720_000_000_000;
 pub const MAINNET_GENESIS_HASH: &str = "3NKeMoncuHab5ScarV5ViyF16cJPT4taWNSaTLS64Dp67wuXigPZ";
+pub const MAINNET_GENESIS_TIMESTAMP: u64 = 1615939200000;
+pub const MAINNET_LEDGER_DEPTH: u32 = 20;
+pub const MAINNET_SLOTS_PER_EPOCH: u32 = 7140;
+pub const MAINNET_SLOT_DURATION: u64 = 180_000;
 pub const MAINNET_TRANSITION_FRONTIER_K: u32 = 290;
 pub const PRUNE_INTERVAL_DEFAULT: u32 = 10;
+pub const SNAPSHOT_INTERVAL_DEFAULT_SEC: u64 = 600;
 pub const SOCKET_NAME: &str = "@mina-indexer.sock";
 
 pub fn display_duration(duration: std::time::Duration) -> String {
@@ -0,0 +1,145 @@
+use crate::{
+    block::BlockHash, state::ledger::Amount, MAINNET_ACCOUNT_CREATION_FEE, MAINNET_COINBASE_REWARD,
+    MAINNET_GENESIS_HASH, MAINNET_GENESIS_TIMESTAMP, MAINNET_LEDGER_DEPTH, MAINNET_SLOTS_PER_EPOCH,
+    MAINNET_SLOT_DURATION, MAINNET_TRANSITION_FRONTIER_K,
+};
+use clap::ValueEnum;
+use serde_derive::{Deserialize, Serialize};
+
+/// Networks the indexer knows the constants of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Devnet,
+    Berkeley,
+    /// Constants are supplied via a network profile in the config file
+    Custom,
+}
+
+/// Network-specific protocol constants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkProfile {
+    #[serde(skip, default = "custom_network")]
+    pub network: Network,
+    /// Hash of the genesis block, if it's known ahead of time
+    pub genesis_hash: Option<BlockHash>,
+    /// Transition frontier length
+    pub k: u32,
+    pub slots_per_epoch: u32,
+    /// Slot duration (milliseconds)
+    pub slot_duration: u64,
+    /// Coinbase reward (nanomina)
+    pub coinbase_amount: u64,
+    /// Supercharged coinbase reward (nanomina)
+    pub supercharged_coinbase_amount: u64,
+    /// Genesis state timestamp (milliseconds since the unix epoch)
+    pub genesis_timestamp: u64,
+    /// Depth of the ledger's Merkle tree
+    #[serde(default = "ledger_depth_default")]
+    pub ledger_depth: u32,
+    /// Fee burned when an account is created (nanomina)
+    #[serde(default = "account_creation_fee_default")]
+    pub account_creation_fee: u64,
+}
+
+fn custom_network() -> Network {
+    Network::Custom
+}
+
+fn ledger_depth_default() -> u32 {
+    MAINNET_LEDGER_DEPTH
+}
+
+fn account_creation_fee_default() -> u64 {
+    MAINNET_ACCOUNT_CREATION_FEE
+}
+
+impl Network {
+    /// The built-in profile of the network, `None` for custom networks
+    pub fn profile(self) -> Option<NetworkProfile> {
+        match self {
+            Network::Mainnet => Some(NetworkProfile::mainnet()),
+            Network::Devnet => Some(NetworkProfile::devnet()),
+            Network::Berkeley => Some(NetworkProfile::berkeley()),
+            Network::Custom => None,
+        }
+    }
+}
+
+impl NetworkProfile {
+    pub fn mainnet() -> Self {
+        Self {
+            network: Network::Mainnet,
+            genesis_hash: Some(BlockHash(MAINNET_GENESIS_HASH.to_string())),
+            k: MAINNET_TRANSITION_FRONTIER_K,
+            slots_per_epoch: MAINNET_SLOTS_PER_EPOCH,
+            slot_duration: MAINNET_SLOT_DURATION,
+            coinbase_amount: MAINNET_COINBASE_REWARD,
+            supercharged_coinbase_amount: 2 * MAINNET_COINBASE_REWARD,
+            genesis_timestamp: MAINNET_GENESIS_TIMESTAMP,
+            ledger_depth: MAINNET_LEDGER_DEPTH,
+            account_creation_fee: MAINNET_ACCOUNT_CREATION_FEE,
+        }
+    }
+
+    /// Devnet has been reset several times, its genesis hash must be supplied as the root hash
+    pub fn devnet() -> Self {
+        Self {
+            network: Network::Devnet,
+            genesis_hash: None,
+            k: 290,
+            slots_per_epoch: 7140,
+            slot_duration: 180_000,
+            coinbase_amount: 720_000_000_000,
+            supercharged_coinbase_amount: 1_440_000_000_000,
+            // 2021-09-23 17:00:00-07:00
+            genesis_timestamp: 1632441600000,
+            ledger_depth: 20,
+            account_creation_fee: 1_000_000_000,
+        }
+    }
+
+    /// Berkeley has been reset several times, its genesis hash must be supplied as the root hash
+    pub fn berkeley() -> Self {
+        Self {
+            network: Network::Berkeley,
+            genesis_hash: None,
+            k: 290,
+            slots_per_epoch: 7140,
+            slot_duration: 180_000,
+            coinbase_amount: 720_000_000_000,
+            supercharged_coinbase_amount: 1_440_000_000_000,
+            // 2023-02-23T20:00:01Z
+            genesis_timestamp: 1677182401000,
+            ledger_depth: 20,
+            account_creation_fee: 1_000_000_000,
+        }
+    }
+
+    /// Coinbase reward, depending on whether it's supercharged
+    pub fn coinbase(&self, supercharge: bool) -> Amount {
+        match supercharge {
+            true => self.supercharged_coinbase_amount.into(),
+            false => self.coinbase_amount.into(),
+        }
+    }
+}
+
+impl Default for NetworkProfile {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
+
+impl std::fmt::Display for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Network::Mainnet => write!(f, "mainnet"),
+            Network::Devnet => write!(f, "devnet"),
+            Network::Berkeley => write!(f, "berkeley"),
+            Network::Custom => write!(f, "custom"),
+        }
+    }
+}
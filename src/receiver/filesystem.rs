@@ -7,7 +7,7 @@ use tokio::sync::{
     mpsc,
     watch::{self, Sender},
 };
-use tracing::{debug, info, instrument};
+use tracing::{debug, error, info, instrument, warn};
 use watchexec::{
     error::RuntimeError,
     event::{
@@ -20,7 +20,9 @@ use watchexec::{
     fs::{worker, WorkingData},
 };
 
-use crate::block::{parse_file, parser::BlockParser, precomputed::PrecomputedBlock};
+use crate::block::{
+    parse_file, parser::BlockParser, precomputed::PrecomputedBlock, quarantine_block_file,
+};
 
 #[derive(Debug, Clone, Hash, Serialize, Deserialize, Error)]
 pub enum FilesystemReceiverError {
@@ -33,6 +35,8 @@ pub struct FilesystemReceiver {
     worker_command_sender: Sender<WorkingData>,
     worker_event_receiver: priority::Receiver<Event, Priority>,
     worker_error_receiver: mpsc::Receiver<RuntimeError>,
+    /// Malformed block files are moved here, `None` leaves them in place
+    quarantine_dir: Option<PathBuf>,
 }
 
 impl FilesystemReceiver {
@@ -57,6 +61,7 @@ impl FilesystemReceiver {
             worker_command_sender,
             worker_event_receiver,
             worker_error_receiver,
+            quarantine_dir: None,
         })
     }
 
@@ -97,6 +102,20 @@ impl FilesystemReceiver {
 
         Ok(())
     }
+
+    /// Moves malformed block files into `quarantine_dir` instead of ignoring them
+    pub fn set_quarantine_dir(&mut self, quarantine_dir: impl AsRef<Path>) {
+        self.quarantine_dir = Some(quarantine_dir.as_ref().to_path_buf());
+    }
+
+    /// Stops watching all directories, no new blocks are received afterwards
+    #[instrument(skip(self))]
+    pub fn stop(&mut self) {
+        info!("stopping FilesystemReceiver");
+        self.parsers.clear();
+        self.worker_command_sender
+            .send_replace(WorkingData::default());
+    }
 }
 
 #[async_trait]
@@ -136,7 +155,22 @@ impl super::BlockReceiver for FilesystemReceiver {
                             if let Some((path, Some(_filetype))) = path_and_filetype {
                                 match parse_file(path.as_path()).await {
                                     Ok(block) => return Ok(Some(block)),
-                                    Err(_) => continue,
+                                    // the file may be incomplete, a later event retries it
+                                    Err(parse_error) if !parse_error.is_malformed_file() => {
+                                        debug!("Unable to parse block file {}: {parse_error}", path.display());
+                                        continue;
+                                    }
+                                    Err(parse_error) => {
+                                        match &self.quarantine_dir {
+                                            Some(quarantine_dir) => {
+                                                if let Err(e) = quarantine_block_file(path, quarantine_dir, &parse_error) {
+                                                    error!("Unable to quarantine block file {} ({parse_error}): {e}", path.display());
+                                                }
+                                            }
+                                            None => warn!("Ignoring block file {}: {parse_error}", path.display()),
+                                        }
+                                        continue;
+                                    }
                                 }
                             }
                         }
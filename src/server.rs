@@ -1,13 +1,19 @@
 use crate::{
-    block::{parser::BlockParser, store::BlockStore, Block, BlockHash, BlockWithoutHeight},
+    block::{
+        parser::BlockParser, precomputed::PrecomputedBlock, store::BlockStore, Block, BlockHash,
+        BlockWithoutHeight,
+    },
+    consensus_time::ConsensusTime,
+    network::NetworkProfile,
     receiver::{filesystem::FilesystemReceiver, BlockReceiver},
     state::{
-        ledger::{genesis::GenesisRoot, public_key::PublicKey, Ledger},
+        ledger::{genesis::GenesisRoot, public_key::PublicKey, store::LedgerStore, Ledger},
+        snapshot::{StateStore, STATE_SNAPSHOT_VERSION},
         summary::{SummaryShort, SummaryVerbose},
         IndexerState, Tip,
     },
     store::IndexerStore,
-    MAINNET_TRANSITION_FRONTIER_K, SOCKET_NAME,
+    SNAPSHOT_INTERVAL_DEFAULT_SEC, SOCKET_NAME,
 };
 use anyhow::anyhow;
 use futures::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
@@ -17,21 +23,24 @@ use log::trace;
 use serde_derive::{Deserialize, Serialize};
 use std::{
     path::{Path, PathBuf},
-    process,
     sync::Arc,
     time::Duration,
 };
 use tokio::{
     fs::{self, create_dir_all, metadata},
     io,
+    signal::unix::{signal, Signal, SignalKind},
     sync::{mpsc, watch},
     task::JoinHandle,
+    time,
 };
-use tracing::{debug, error, info, instrument};
+use tracing::{debug, error, info, instrument, warn};
 
 pub struct IndexerConfiguration {
     pub ledger: GenesisRoot,
     pub is_genesis_ledger: bool,
+    /// Protocol constants of the indexed network
+    pub network: NetworkProfile,
     pub root_hash: BlockHash,
     pub startup_dir: PathBuf,
     pub watch_dir: PathBuf,
@@ -39,6 +48,14 @@ pub struct IndexerConfiguration {
     pub canonical_threshold: u32,
     pub canonical_update_threshold: u32,
     pub from_snapshot: bool,
+    /// Interval between writes of the state snapshot to the db, never if `None`
+    pub snapshot_interval: Option<Duration>,
+    /// Check canonical ledgers' Merkle roots against their blocks' ledger hashes
+    pub verify_ledger_hashes: bool,
+    /// Check the signatures of ingested blocks' user commands
+    pub verify_signatures: bool,
+    /// Directory malformed block files are moved to, left in place if `None`
+    pub quarantine_dir: Option<PathBuf>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -51,16 +68,18 @@ struct SaveResponse(String);
 pub enum MinaIndexerRunPhase {
     JustStarted,
     ConnectingToIPCSocket,
-    SettingSIGINTHandler,
+    SettingSignalHandlers,
     InitializingState,
     StateInitializedFromParser,
     StateInitializedFromSnapshot,
+    StateInitializedFromStore,
     StartingBlockReceiver,
     StartingIPCSocketListener,
     StartingMainServerLoop,
     ReceivingBlock,
     ReceivingIPCConnection,
     SavingStateSnapshot,
+    ShuttingDown,
 }
 
 pub enum MinaIndexerQuery {
@@ -78,7 +97,7 @@ pub enum MinaIndexerQueryResponse {
 }
 
 pub struct MinaIndexer {
-    _loop_join_handle: JoinHandle<anyhow::Result<()>>,
+    loop_join_handle: JoinHandle<anyhow::Result<()>>,
     phase_receiver: watch::Receiver<MinaIndexerRunPhase>,
     query_sender: mpsc::Sender<(MinaIndexerQuery, oneshot::Sender<MinaIndexerQueryResponse>)>,
 }
@@ -92,14 +111,40 @@ impl MinaIndexer {
 
         let (query_sender, query_receiver) = mpsc::channel(1);
 
-        let _loop_join_handle = tokio::spawn(async move {
+        phase_sender.send_replace(MinaIndexerRunPhase::SettingSignalHandlers);
+        debug!("Setting SIGINT and SIGTERM handlers");
+        let mut shutdown_signals = ShutdownSignals::new()?;
+
+        let loop_join_handle = tokio::spawn(async move {
             let watch_dir = config.watch_dir.clone();
-            let (state, phase_sender) = initialize(config, store, phase_sender).await?;
-            run(watch_dir, state, phase_sender, query_receiver).await
+            let snapshot_interval = config.snapshot_interval;
+            let quarantine_dir = config.quarantine_dir.clone();
+
+            // blocks are ingested atomically, so initialization can be abandoned at any
+            // point. The partial state isn't snapshotted on purpose: the next start
+            // catches the last snapshot up with the db, or rebuilds the state from the
+            // blocks above the stored canonical tip if there's no snapshot yet
+            let (state, phase_sender) = tokio::select! {
+                init = initialize(config, store, phase_sender) => init?,
+                signal = shutdown_signals.recv() => {
+                    info!("{signal} received while initializing, exiting");
+                    return Ok(());
+                }
+            };
+            run(
+                watch_dir,
+                snapshot_interval,
+                quarantine_dir,
+                state,
+                phase_sender,
+                query_receiver,
+                shutdown_signals,
+            )
+            .await
         });
 
         Ok(Self {
-            _loop_join_handle,
+            loop_join_handle,
             phase_receiver,
             query_sender,
         })
@@ -121,7 +166,7 @@ impl MinaIndexer {
         use MinaIndexerRunPhase::*;
         !matches!(
             *self.phase_receiver.borrow(),
-            JustStarted | SettingSIGINTHandler | InitializingState
+            JustStarted | SettingSignalHandlers | InitializingState
         )
     }
 
@@ -129,6 +174,11 @@ impl MinaIndexer {
         *self.phase_receiver.borrow()
     }
 
+    /// Waits for the main loop to finish, i.e. after a graceful shutdown
+    pub async fn await_loop(self) -> anyhow::Result<()> {
+        self.loop_join_handle.await?
+    }
+
     pub async fn blocks_processed(&self) -> anyhow::Result<u32> {
         match self
             .send_query(MinaIndexerQuery::NumBlocksProcessed)
@@ -152,19 +202,12 @@ pub async fn initialize(
         .await
         .expect_err("Server is already running... Exiting.");
 
-    phase_sender.send_replace(SettingSIGINTHandler);
-    debug!("Setting Ctrl-C handler");
-    ctrlc::set_handler(move || {
-        info!("SIGINT received. Exiting.");
-        process::exit(0);
-    })
-    .expect("Error setting Ctrl-C handler");
-
     phase_sender.send_replace(InitializingState);
     info!("Starting mina-indexer server");
     let IndexerConfiguration {
         ledger,
         is_genesis_ledger,
+        network,
         root_hash,
         startup_dir,
         watch_dir: _,
@@ -172,9 +215,76 @@ pub async fn initialize(
         canonical_threshold,
         canonical_update_threshold,
         from_snapshot,
+        snapshot_interval: _,
+        verify_ledger_hashes,
+        verify_signatures,
+        quarantine_dir,
     } = config;
 
-    let state = if !from_snapshot {
+    debug!("Checking the database for half-written blocks");
+    store.recover_incomplete_blocks(&network)?;
+
+    // snapshots written by another version can't be decoded, the stored blocks
+    // are replayed instead and a current snapshot takes their place
+    let snapshot_version = store.snapshot_version()?;
+    if let Some(version) = snapshot_version.filter(|version| *version != STATE_SNAPSHOT_VERSION) {
+        warn!("Discarding state snapshot version {version}, expected {STATE_SNAPSHOT_VERSION}");
+    }
+
+    let state = if !from_snapshot && snapshot_version == Some(STATE_SNAPSHOT_VERSION) {
+        info!("Resuming indexer state from the existing database");
+        let mut state = IndexerState::from_state_snapshot(
+            store,
+            network.clone(),
+            canonical_threshold,
+            prune_interval,
+            canonical_update_threshold,
+        )?;
+        state.verify_ledger_hashes = verify_ledger_hashes;
+        state.verify_signatures = verify_signatures;
+
+        // only parse the startup blocks the db can't already account for
+        let canonical_tip_length = state.canonical_tip_block().blockchain_length;
+        info!(
+            "Ingesting blocks longer than {canonical_tip_length} from {}",
+            startup_dir.display()
+        );
+        let mut block_parser =
+            BlockParser::new_successive(&startup_dir, &network, canonical_tip_length)?;
+        block_parser.quarantine_dir = quarantine_dir;
+        state.catch_up(&mut block_parser).await?;
+        state.persist_state_snapshot()?;
+
+        phase_sender.send_replace(StateInitializedFromStore);
+        state
+    } else if !from_snapshot && store.has_blocks() {
+        info!("Rebuilding indexer state from the blocks in the existing database");
+        let mut state = IndexerState::new(
+            root_hash.clone(),
+            ledger.ledger,
+            store,
+            network.clone(),
+            canonical_threshold,
+            prune_interval,
+            canonical_update_threshold,
+        )?;
+        state.verify_ledger_hashes = verify_ledger_hashes;
+        state.verify_signatures = verify_signatures;
+        state.rebuild_from_store()?;
+
+        // then add the startup blocks the db doesn't hold yet
+        let mut block_parser = BlockParser::new_successive(
+            &startup_dir,
+            &network,
+            state.canonical_tip_block().blockchain_length,
+        )?;
+        block_parser.quarantine_dir = quarantine_dir;
+        state.catch_up(&mut block_parser).await?;
+        state.persist_state_snapshot()?;
+
+        phase_sender.send_replace(StateInitializedFromStore);
+        state
+    } else if !from_snapshot {
         info!(
             "Initializing indexer state from blocks in {}",
             startup_dir.display()
@@ -183,12 +293,16 @@ pub async fn initialize(
             root_hash.clone(),
             ledger.ledger,
             store,
-            MAINNET_TRANSITION_FRONTIER_K,
+            network.clone(),
+            canonical_threshold,
             prune_interval,
             canonical_update_threshold,
         )?;
+        state.verify_ledger_hashes = verify_ledger_hashes;
+        state.verify_signatures = verify_signatures;
 
-        let mut block_parser = BlockParser::new(&startup_dir, canonical_threshold)?;
+        let mut block_parser = BlockParser::new(&startup_dir, &network, canonical_threshold)?;
+        block_parser.quarantine_dir = quarantine_dir;
         if is_genesis_ledger {
             state
                 .initialize_with_contiguous_canonical(&mut block_parser)
@@ -198,17 +312,21 @@ pub async fn initialize(
                 .initialize_without_contiguous_canonical(&mut block_parser)
                 .await?;
         }
+        state.persist_state_snapshot()?;
 
         phase_sender.send_replace(StateInitializedFromParser);
         state
     } else {
         info!("initializing indexer state from snapshot");
-        let state = IndexerState::from_state_snapshot(
+        let mut state = IndexerState::from_state_snapshot(
             store,
-            MAINNET_TRANSITION_FRONTIER_K,
+            network,
+            canonical_threshold,
             prune_interval,
             canonical_update_threshold,
         )?;
+        state.verify_ledger_hashes = verify_ledger_hashes;
+        state.verify_signatures = verify_signatures;
 
         phase_sender.send_replace(StateInitializedFromSnapshot);
         state
@@ -220,18 +338,24 @@ pub async fn initialize(
 #[instrument(skip_all)]
 pub async fn run(
     block_watch_dir: impl AsRef<Path>,
+    snapshot_interval: Option<Duration>,
+    quarantine_dir: Option<PathBuf>,
     mut state: IndexerState,
     phase_sender: watch::Sender<MinaIndexerRunPhase>,
     mut query_receiver: mpsc::Receiver<(
         MinaIndexerQuery,
         oneshot::Sender<MinaIndexerQueryResponse>,
     )>,
+    mut shutdown_signals: ShutdownSignals,
 ) -> Result<(), anyhow::Error> {
     use MinaIndexerRunPhase::*;
 
     phase_sender.send_replace(StartingBlockReceiver);
     let mut filesystem_receiver = FilesystemReceiver::new(1024, 64).await?;
     filesystem_receiver.load_directory(block_watch_dir.as_ref())?;
+    if let Some(quarantine_dir) = quarantine_dir {
+        filesystem_receiver.set_quarantine_dir(quarantine_dir);
+    }
     info!("Block receiver set to watch {:?}", block_watch_dir.as_ref());
 
     phase_sender.send_replace(StartingIPCSocketListener);
@@ -257,8 +381,28 @@ pub async fn run(
     let (mut save_resp_tx, save_resp_rx) = spmc::channel();
     let save_tx = Arc::new(save_tx);
     let save_resp_rx = Arc::new(save_resp_rx);
+
+    // the ticker is only polled if auto snapshots are enabled
+    let tick_period =
+        snapshot_interval.unwrap_or(Duration::from_secs(SNAPSHOT_INTERVAL_DEFAULT_SEC));
+    let mut snapshot_ticker = time::interval_at(time::Instant::now() + tick_period, tick_period);
+    snapshot_ticker.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+
     loop {
         tokio::select! {
+            signal = shutdown_signals.recv() => {
+                info!("{signal} received, shutting down");
+                break;
+            }
+
+            _ = snapshot_ticker.tick(), if snapshot_interval.is_some() => {
+                phase_sender.send_replace(SavingStateSnapshot);
+                debug!("Writing the state snapshot to the db");
+                if let Err(e) = state.persist_state_snapshot() {
+                    error!("Error writing the state snapshot: {e}");
+                }
+            }
+
             Some((command, response_sender)) = query_receiver.recv() => {
                 use MinaIndexerQuery::*;
                 let response = match command {
@@ -288,7 +432,7 @@ pub async fn run(
                     info!("Added {block:?}");
                 } else {
                     info!("Block receiver shutdown, system exit");
-                    return Ok(())
+                    break;
                 }
             }
 
@@ -302,6 +446,7 @@ pub async fn run(
                         let block_store_readonly = Arc::new(state.spawn_secondary_database()?);
                         let summary = state.summary_verbose();
                         let ledger = state.best_ledger()?.unwrap();
+                        let network = state.network.clone();
 
                         let save_tx = save_tx.clone();
                         let save_resp_rx = save_resp_rx.clone();
@@ -309,7 +454,7 @@ pub async fn run(
                         // handle the connection
                         tokio::spawn(async move {
                             debug!("Handling client connection");
-                            if let Err(e) = handle_conn(stream, block_store_readonly.clone(), best_tip, ledger, summary, save_tx, save_resp_rx).await {
+                            if let Err(e) = handle_conn(stream, block_store_readonly.clone(), best_tip, ledger, summary, network, save_tx, save_resp_rx).await {
                                 error!("Error handling connection: {e}");
                             }
                             debug!("Removing readonly instance at {}", block_store_readonly.db_path.clone().display());
@@ -335,15 +480,57 @@ pub async fn run(
             }
         }
     }
+
+    phase_sender.send_replace(ShuttingDown);
+    info!("Stopping the block receiver");
+    filesystem_receiver.stop();
+
+    info!("Writing the state snapshot and flushing the db");
+    state.persist_state_snapshot()?;
+    if let Some(indexer_store) = state.indexer_store.as_ref() {
+        indexer_store.mark_clean()?;
+        indexer_store.flush()?;
+    }
+
+    // the socket is abstract, it goes away with the listener
+    drop(listener);
+
+    Ok(())
+}
+
+/// SIGINT and SIGTERM listeners, registered before initialization
+/// so the server can be stopped at any point
+pub struct ShutdownSignals {
+    sigint: Signal,
+    sigterm: Signal,
+}
+
+impl ShutdownSignals {
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            sigint: signal(SignalKind::interrupt())?,
+            sigterm: signal(SignalKind::terminate())?,
+        })
+    }
+
+    /// Waits for either signal, returning its name
+    pub async fn recv(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.sigint.recv() => "SIGINT",
+            _ = self.sigterm.recv() => "SIGTERM",
+        }
+    }
 }
 
 #[instrument(skip_all)]
+#[allow(clippy::too_many_arguments)]
 async fn handle_conn(
     conn: LocalSocketStream,
     db: Arc<IndexerStore>,
     best_tip: Block,
-    ledger: Ledger,
+    ledger: Arc<Ledger>,
     summary: SummaryVerbose,
+    network: NetworkProfile,
     save_tx: Arc<mpsc::Sender<SaveCommand>>,
     _save_resp_rx: Arc<spmc::Receiver<Option<SaveResponse>>>,
 ) -> Result<(), anyhow::Error> {
@@ -369,10 +556,37 @@ async fn handle_conn(
             let account = ledger.accounts.get(&public_key);
             if let Some(account) = account {
                 debug!("Writing account {account:?} to client");
-                let bytes = bcs::to_bytes(account)?;
+                // the ledger is the best tip's, balances unlock relative to its slot
+                let bytes = bcs::to_bytes(&(account, best_tip.global_slot_since_genesis))?;
                 writer.write_all(&bytes).await?;
             }
         }
+        "account_transactions" => {
+            let public_key =
+                PublicKey::from_address(&String::from_utf8(buffers.next().unwrap().to_vec())?)?;
+            let data_buffer = buffers.next().unwrap();
+            let num = String::from_utf8(data_buffer[..data_buffer.len() - 1].to_vec())?
+                .parse::<usize>()?;
+            info!(
+                "Received account_transactions command for {}",
+                public_key.to_address()
+            );
+
+            // most recent transactions sent or received by the account
+            let mut tx_keys = db.get_tx_keys_from(&public_key)?;
+            tx_keys.append(&mut db.get_tx_keys_to(&public_key)?);
+            tx_keys.sort();
+            tx_keys.dedup();
+
+            let mut transactions = vec![];
+            for key in tx_keys.into_iter().rev().take(num) {
+                if let Some(tx) = db.get_tx(&key)? {
+                    transactions.push((key, tx.command));
+                }
+            }
+            let bytes = bcs::to_bytes(&transactions)?;
+            writer.write_all(&bytes).await?;
+        }
         "best_chain" => {
             info!("Received best_chain command");
             let data_buffer = buffers.next().unwrap();
@@ -386,6 +600,16 @@ async fn handle_conn(
                     BlockHash::from_hashv1(parent_pcb.protocol_state.previous_state_hash.clone());
                 best_chain.push(parent_pcb);
             }
+            let best_chain: Vec<(PrecomputedBlock, ConsensusTime)> = best_chain
+                .into_iter()
+                .map(|block| {
+                    let consensus_time = ConsensusTime::from_global_slot(
+                        block.global_slot_since_genesis(),
+                        &network,
+                    );
+                    (block, consensus_time)
+                })
+                .collect();
             let bytes = bcs::to_bytes(&best_chain)?;
             writer.write_all(&bytes).await?;
         }
@@ -407,6 +631,24 @@ async fn handle_conn(
                 writer.write_all(&bytes).await?;
             }
         }
+        "ledger_at_height" => {
+            info!("Received ledger_at_height command");
+            let height = String::from_utf8(buffers.next().unwrap().to_vec())?.parse::<u32>()?;
+            let data_buffer = buffers.next().unwrap();
+            let path = &String::from_utf8(data_buffer[..data_buffer.len() - 1].to_vec())?
+                .parse::<PathBuf>()?;
+            let msg = if path.is_dir() {
+                format!("The path provided must be a file: {}", path.display())
+            } else if let Some(ledger) = db.get_ledger_at_height(height)? {
+                debug!("Writing ledger at height {height} to {}", path.display());
+                fs::write(path, format!("{ledger:?}")).await?;
+                format!("Ledger at height {height} written to {}", path.display())
+            } else {
+                format!("No canonical ledger at height {height}")
+            };
+            let bytes = bcs::to_bytes(&msg)?;
+            writer.write_all(&bytes).await?;
+        }
         "summary" => {
             info!("Received summary command");
             let data_buffer = buffers.next().unwrap();
@@ -421,6 +663,22 @@ async fn handle_conn(
                 writer.write_all(&bytes).await?;
             }
         }
+        "transaction" => {
+            let data_buffer = buffers.next().unwrap();
+            let hash = String::from_utf8(data_buffer[..data_buffer.len() - 1].to_vec())?;
+            info!("Received transaction command for {hash}");
+
+            // the transaction may be included in several blocks
+            let mut transactions = vec![];
+            for (state_hash, key) in db.get_tx_blocks(&hash)? {
+                if let Some(tx) = db.get_tx(&key)? {
+                    let canonicity = db.get_canonicity(&state_hash)?;
+                    transactions.push((state_hash, key, canonicity, tx.command));
+                }
+            }
+            let bytes = bcs::to_bytes(&transactions)?;
+            writer.write_all(&bytes).await?;
+        }
         "save_state" => {
             info!("Received save_state command");
             let data_buffer = buffers.next().unwrap();
@@ -1,13 +1,31 @@
 pub mod staking_ledger_store;
 
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use rust_decimal_macros::dec;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::state::ledger::{account::nanomina_to_mina, Ledger};
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct StakingLedger {
     pub epoch_number: u32,
+    /// Computed from the accounts for derived ledgers, which may not match
+    /// the snarked ledger hash the chain commits to
     pub ledger_hash: String,
     pub accounts: Vec<StakingLedgerAccount>,
+    /// Derived from the indexer's staged ledger rather than ingested
+    pub derived: bool,
+}
+
+/// Encoding of staking ledgers stored before derived ledgers were flagged,
+/// all of them ingested
+#[derive(Deserialize)]
+struct LegacyStakingLedger {
+    epoch_number: u32,
+    ledger_hash: String,
+    accounts: Vec<StakingLedgerAccount>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
@@ -29,3 +47,110 @@ pub struct StakingLedgerAccount {
     pub ledger_hash: Option<String>,
     pub delegation_totals: Option<DelegationTotals>,
 }
+
+impl StakingLedger {
+    /// Annotates the accounts with the epoch, ledger hash and delegation totals
+    pub fn new(
+        epoch_number: u32,
+        ledger_hash: String,
+        accounts: Vec<StakingLedgerAccount>,
+    ) -> Self {
+        let mut delegation_totals: HashMap<String, DelegationTotals> = HashMap::new();
+        for account in accounts.iter() {
+            let totals =
+                delegation_totals
+                    .entry(account.delegate.clone())
+                    .or_insert(DelegationTotals {
+                        count_delegates: 0,
+                        total_delegations: 0,
+                    });
+            totals.count_delegates += 1;
+            totals.total_delegations += account.balance_nanomina();
+        }
+
+        let accounts = accounts
+            .into_iter()
+            .map(|account| StakingLedgerAccount {
+                epoch_number: Some(epoch_number as i32),
+                ledger_hash: Some(ledger_hash.clone()),
+                delegation_totals: Some(delegation_totals.get(&account.pk).cloned().unwrap_or(
+                    DelegationTotals {
+                        count_delegates: 0,
+                        total_delegations: 0,
+                    },
+                )),
+                ..account
+            })
+            .collect();
+
+        Self {
+            epoch_number,
+            ledger_hash,
+            accounts,
+            derived: false,
+        }
+    }
+
+    /// Decodes a stored staking ledger, including ones stored in the legacy encoding
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        match bcs::from_bytes(bytes) {
+            Ok(staking_ledger) => Ok(staking_ledger),
+            Err(e) => match bcs::from_bytes::<LegacyStakingLedger>(bytes) {
+                Ok(legacy) => Ok(Self {
+                    epoch_number: legacy.epoch_number,
+                    ledger_hash: legacy.ledger_hash,
+                    accounts: legacy.accounts,
+                    derived: false,
+                }),
+                Err(_) => Err(e.into()),
+            },
+        }
+    }
+
+    /// Staking ledger of `epoch_number` derived from the indexer's `ledger`,
+    /// labeled with its Merkle root
+    ///
+    /// Receipt chain hashes and votes aren't tracked by the indexer, they're left empty
+    pub fn from_ledger(epoch_number: u32, ledger: &Ledger, depth: u32) -> Self {
+        let mut accounts: Vec<StakingLedgerAccount> = ledger
+            .accounts
+            .values()
+            .map(|account| {
+                let pk = account.public_key.to_address();
+                StakingLedgerAccount {
+                    delegate: account
+                        .delegate
+                        .as_ref()
+                        .map(|delegate| delegate.to_address())
+                        .unwrap_or(pk.clone()),
+                    pk,
+                    balance: nanomina_to_mina(account.balance.0),
+                    nonce: Some(account.nonce.0.to_string()),
+                    receipt_chain_hash: String::new(),
+                    token: "1".to_string(),
+                    voting_for: String::new(),
+                    epoch_number: None,
+                    ledger_hash: None,
+                    delegation_totals: None,
+                }
+            })
+            .collect();
+        accounts.sort_by(|x, y| x.pk.cmp(&y.pk));
+
+        Self {
+            derived: true,
+            ..Self::new(epoch_number, ledger.merkle_root(depth).0, accounts)
+        }
+    }
+}
+
+impl StakingLedgerAccount {
+    pub fn balance_nanomina(&self) -> i64 {
+        self.balance
+            .parse::<Decimal>()
+            .map(|balance| balance * dec!(1_000_000_000))
+            .unwrap_or(Decimal::ZERO)
+            .to_i64()
+            .unwrap()
+    }
+}
@@ -1,7 +1,4 @@
-use crate::{
-    block::{precomputed::PrecomputedBlock, Block, BlockHash},
-    MAINNET_CANONICAL_THRESHOLD,
-};
+use crate::block::{precomputed::PrecomputedBlock, Block, BlockHash};
 use id_tree::{
     InsertBehavior::{AsRoot, UnderNode},
     MoveBehavior::ToRoot,
@@ -112,7 +109,7 @@ impl Branch {
 
     #[instrument(skip(self))]
     /// Returns the node id of the canonical tip, if it exists
-    pub fn canonical_tip_id(&self) -> Option<NodeId> {
+    pub fn canonical_tip_id(&self, canonical_threshold: u32) -> Option<NodeId> {
         for (n, ancestor_id) in self
             .branches
             .ancestor_ids(&self.best_tip_id())
@@ -121,7 +118,7 @@ impl Branch {
         {
             let state_hash = &self.branches.get(ancestor_id).unwrap().data().state_hash;
             trace!("{}th state_hash: {}", n, state_hash.0);
-            if n + 1 == MAINNET_CANONICAL_THRESHOLD as usize {
+            if n + 1 == canonical_threshold as usize {
                 trace!("returning id for {}", state_hash.0);
                 return Some(ancestor_id.clone());
             }
@@ -436,6 +433,15 @@ impl Branch {
         self.branches.height() as u32
     }
 
+    /// Returns the id of the node with the given state hash, if present
+    pub fn node_id(&self, state_hash: &BlockHash) -> Option<NodeId> {
+        let root_id = self.branches.root_node_id()?;
+        self.branches
+            .traverse_level_order_ids(root_id)
+            .unwrap()
+            .find(|node_id| &self.branches.get(node_id).unwrap().data().state_hash == state_hash)
+    }
+
     pub fn mem(&self, state_hash: &BlockHash) -> bool {
         for node in self
             .branches
@@ -17,6 +17,39 @@ pub struct Account {
     pub balance: Amount,
     pub nonce: Nonce,
     pub delegate: Option<PublicKey>,
+    pub timing: Option<Timing>,
+}
+
+/// Vesting schedule of a timed account
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Timing {
+    pub initial_minimum_balance: Amount,
+    /// Global slot at which `cliff_amount` unlocks
+    pub cliff_time: u32,
+    pub cliff_amount: Amount,
+    /// Number of slots between vesting increments after the cliff
+    pub vesting_period: u32,
+    pub vesting_increment: Amount,
+}
+
+impl Timing {
+    /// Minimum balance the account must hold at `global_slot`
+    pub fn min_balance_at_slot(&self, global_slot: u32) -> Amount {
+        if global_slot < self.cliff_time {
+            self.initial_minimum_balance
+        } else if self.vesting_period == 0 {
+            Amount(0)
+        } else {
+            let num_periods = ((global_slot - self.cliff_time) / self.vesting_period) as u64;
+            let vesting_decrement = num_periods.saturating_mul(self.vesting_increment.0);
+            Amount(
+                self.initial_minimum_balance
+                    .0
+                    .saturating_sub(self.cliff_amount.0)
+                    .saturating_sub(vesting_decrement),
+            )
+        }
+    }
 }
 
 impl Account {
@@ -26,9 +59,23 @@ impl Account {
             balance: Amount::default(),
             nonce: Nonce::default(),
             delegate: None,
+            timing: None,
         }
     }
 
+    /// Part of the balance still locked by the account's vesting schedule at `global_slot`
+    pub fn locked_balance(&self, global_slot: u32) -> Amount {
+        match &self.timing {
+            Some(timing) => timing.min_balance_at_slot(global_slot).min(self.balance),
+            None => Amount(0),
+        }
+    }
+
+    /// Part of the balance that can be spent at `global_slot`
+    pub fn liquid_balance(&self, global_slot: u32) -> Amount {
+        self.balance.sub(&self.locked_balance(global_slot))
+    }
+
     pub fn from_deduction(pre: Self, amount: Amount) -> Option<Self> {
         if amount > pre.balance {
             None
@@ -36,18 +83,36 @@ impl Account {
             Some(Account {
                 public_key: pre.public_key.clone(),
                 balance: pre.balance.sub(&amount),
-                nonce: Nonce(pre.nonce.0 + 1),
+                nonce: pre.nonce,
                 delegate: pre.delegate,
+                timing: pre.timing,
             })
         }
     }
 
+    /// Each user command consumes one nonce of its fee payer, applied or not
+    pub fn from_fee_payment(pre: Self, fee: Amount) -> Option<Self> {
+        Self::from_deduction(pre, fee).map(|account| Account {
+            nonce: Nonce(account.nonce.0 + 1),
+            ..account
+        })
+    }
+
     pub fn from_deposit(pre: Self, amount: Amount) -> Self {
         Account {
             public_key: pre.public_key.clone(),
             balance: pre.balance.add(&amount),
-            nonce: Nonce(pre.nonce.0 + 1),
+            nonce: pre.nonce,
             delegate: pre.delegate,
+            timing: pre.timing,
+        }
+    }
+
+    /// The account creation fee is burned, it doesn't count towards the nonce
+    pub fn from_creation_fee(pre: Self, fee: Amount) -> Self {
+        Account {
+            balance: Amount(pre.balance.0.saturating_sub(fee.0)),
+            ..pre
         }
     }
 
@@ -55,8 +120,9 @@ impl Account {
         Account {
             public_key: pre.public_key,
             balance: pre.balance,
-            nonce: Nonce(pre.nonce.0 + 1),
+            nonce: pre.nonce,
             delegate: Some(delegate),
+            timing: pre.timing,
         }
     }
 }
@@ -75,7 +141,7 @@ impl Ord for Account {
 
 const MINA_SCALE: u32 = 9;
 
-fn nanomina_to_mina(num: u64) -> String {
+pub(crate) fn nanomina_to_mina(num: u64) -> String {
     let mut dec = Decimal::from(num);
     dec.set_scale(MINA_SCALE).unwrap();
     let mut dec_str = dec.to_string();
@@ -103,10 +169,49 @@ impl std::fmt::Debug for Account {
         writeln!(f, "  balance:  {}", nanomina_to_mina(self.balance.0))?;
         writeln!(f, "  nonce:    {}", self.nonce.0)?;
         writeln!(f, "  delegate: {delegate}")?;
+        if let Some(timing) = &self.timing {
+            writeln!(f, "  timing:   {{")?;
+            writeln!(
+                f,
+                "    initial_minimum_balance: {}",
+                nanomina_to_mina(timing.initial_minimum_balance.0)
+            )?;
+            writeln!(f, "    cliff_time:              {}", timing.cliff_time)?;
+            writeln!(
+                f,
+                "    cliff_amount:            {}",
+                nanomina_to_mina(timing.cliff_amount.0)
+            )?;
+            writeln!(f, "    vesting_period:          {}", timing.vesting_period)?;
+            writeln!(
+                f,
+                "    vesting_increment:       {}",
+                nanomina_to_mina(timing.vesting_increment.0)
+            )?;
+            writeln!(f, "  }}")?;
+        }
         writeln!(f, "}}")
     }
 }
 
+#[test]
+fn test_vesting_schedule() {
+    let timing = Timing {
+        initial_minimum_balance: Amount(1_000),
+        cliff_time: 10,
+        cliff_amount: Amount(400),
+        vesting_period: 5,
+        vesting_increment: Amount(100),
+    };
+    assert_eq!(timing.min_balance_at_slot(0), Amount(1_000));
+    assert_eq!(timing.min_balance_at_slot(9), Amount(1_000));
+    assert_eq!(timing.min_balance_at_slot(10), Amount(600));
+    assert_eq!(timing.min_balance_at_slot(14), Amount(600));
+    assert_eq!(timing.min_balance_at_slot(15), Amount(500));
+    assert_eq!(timing.min_balance_at_slot(40), Amount(0));
+    assert_eq!(timing.min_balance_at_slot(u32::MAX), Amount(0));
+}
+
 #[test]
 fn test_nanomina_to_mina_conversion() {
     let actual = 1_000_000_001;
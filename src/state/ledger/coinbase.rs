@@ -1,11 +1,18 @@
-use crate::block::precomputed::PrecomputedBlock;
+use crate::{block::precomputed::PrecomputedBlock, network::NetworkProfile};
+use mina_serialization_types::staged_ledger_diff::CoinBase;
 
-use super::{diff::account::AccountDiff, PublicKey};
+use super::{
+    diff::account::{AccountDiff, PaymentDiff, UpdateType},
+    fee_transfer::FeeTransfer,
+    Amount, PublicKey,
+};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Coinbase {
     pub receiver: PublicKey,
+    pub fee_transfers: Vec<FeeTransfer>,
     supercharge: bool,
+    applied: bool,
 }
 
 impl Coinbase {
@@ -22,13 +29,71 @@ impl Coinbase {
         let receiver = consensus_state.coinbase_receiver.into();
         let supercharge = consensus_state.supercharge_coinbase;
 
+        // coinbase fee transfers pay SNARK workers out of the coinbase
+        let (applied, fee_transfers) = match precomputed_block.coinbase() {
+            CoinBase::Zero => (false, vec![]),
+            CoinBase::One(fee_transfer) => (true, vec![fee_transfer]),
+            CoinBase::Two(fee_transfer0, fee_transfer1) => {
+                (true, vec![fee_transfer0, fee_transfer1])
+            }
+        };
+        let fee_transfers = fee_transfers
+            .into_iter()
+            .flatten()
+            .map(|fee_transfer| {
+                let fee_transfer = fee_transfer.inner().inner();
+                FeeTransfer {
+                    receiver: fee_transfer.receiver_pk.into(),
+                    fee: fee_transfer.fee.inner().inner().into(),
+                }
+            })
+            .collect();
+
         Self {
             receiver,
+            fee_transfers,
             supercharge,
+            applied,
         }
     }
 
-    pub fn as_account_diff(self) -> AccountDiff {
-        AccountDiff::from_coinbase(self.receiver, self.supercharge)
+    pub fn amount(&self, network: &NetworkProfile) -> Amount {
+        network.coinbase(self.supercharge)
+    }
+
+    /// What the coinbase receiver gets, the coinbase less its fee transfers
+    pub fn net_amount(&self, network: &NetworkProfile) -> Amount {
+        let fee_transfers_total: u64 = self
+            .fee_transfers
+            .iter()
+            .map(|fee_transfer| fee_transfer.fee.0)
+            .sum();
+        self.amount(network)
+            .0
+            .saturating_sub(fee_transfers_total)
+            .into()
+    }
+
+    pub fn is_applied(&self) -> bool {
+        self.applied
+    }
+
+    pub fn as_account_diffs(self, network: &NetworkProfile) -> Vec<AccountDiff> {
+        if !self.applied {
+            return vec![];
+        }
+
+        let mut account_diffs = vec![AccountDiff::Payment(PaymentDiff {
+            public_key: self.receiver.clone(),
+            amount: self.net_amount(network),
+            update_type: UpdateType::Deposit,
+        })];
+
+        for fee_transfer in self.fee_transfers {
+            if fee_transfer.fee.0 > 0 {
+                account_diffs.push(fee_transfer.as_account_diff());
+            }
+        }
+        account_diffs
     }
 }
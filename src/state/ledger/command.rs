@@ -1,11 +1,13 @@
 use crate::{
     block::precomputed::PrecomputedBlock,
-    state::ledger::{public_key::PublicKey, Amount},
+    network::NetworkProfile,
+    state::ledger::{coinbase::Coinbase, fee_transfer::FeeTransfer, public_key::PublicKey, Amount},
 };
 use mina_serialization_types::{
     staged_ledger_diff::{
-        SignedCommandPayloadBody, SignedCommandPayloadCommon, StakeDelegation,
-        TransactionStatusBalanceData, UserCommand,
+        SignedCommandPayloadBody, SignedCommandPayloadCommon, StakeDelegation, TransactionStatus,
+        TransactionStatusAuxiliaryData, TransactionStatusBalanceData, TransactionStatusFailedType,
+        UserCommand,
     },
     v1::{PaymentPayloadV1, PublicKeyV1, SignedCommandV1, UserCommandWithStatusV1},
 };
@@ -39,12 +41,27 @@ pub enum Command {
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct SignedCommand(pub SignedCommandV1);
 
+/// Balance changes applied by the block producer rather than a signed command
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub enum InternalCommand {
+    /// Fee transfer paid out of the block's user command fees
+    FeeTransfer { receiver: PublicKey, amount: Amount },
+    /// Fee transfer paid out of the coinbase
+    FeeTransferViaCoinbase { receiver: PublicKey, amount: Amount },
+    /// Coinbase, net of any fee transfers via coinbase
+    Coinbase { receiver: PublicKey, amount: Amount },
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub enum CommandStatusData {
     Applied {
         balance_data: TransactionStatusBalanceData,
     },
-    Failed,
+    /// Failed commands still pay their fee and consume their nonce
+    Failed {
+        failure_reason: String,
+        balance_data: TransactionStatusBalanceData,
+    },
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
@@ -53,23 +70,95 @@ pub struct UserCommandWithStatus(pub UserCommandWithStatusV1);
 impl UserCommandWithStatus {
     pub fn status_data(&self) -> CommandStatusData {
         match self.0.t.status.t.clone() {
-            mina_serialization_types::staged_ledger_diff::TransactionStatus::Applied(
-                _,
-                balance_data,
-            ) => CommandStatusData::Applied {
+            TransactionStatus::Applied(_, balance_data) => CommandStatusData::Applied {
+                balance_data: balance_data.t,
+            },
+            TransactionStatus::Failed(_, balance_data) => CommandStatusData::Failed {
+                failure_reason: self.failure_reason().unwrap_or_default(),
                 balance_data: balance_data.t,
             },
-            mina_serialization_types::staged_ledger_diff::TransactionStatus::Failed(_, _) => {
-                CommandStatusData::Failed
+        }
+    }
+
+    pub fn is_applied(&self) -> bool {
+        matches!(self.0.t.status.t, TransactionStatus::Applied(_, _))
+    }
+
+    /// Reason the command failed, e.g. `Amount_insufficient_to_create_account`
+    pub fn failure_reason(&self) -> Option<String> {
+        match self.0.t.status.t.clone() {
+            TransactionStatus::Applied(_, _) => None,
+            TransactionStatus::Failed(failure, _) => {
+                Some(failure_reason_name(&failure.t).to_string())
             }
         }
     }
+
+    fn auxiliary_data(&self) -> Option<TransactionStatusAuxiliaryData> {
+        match self.0.t.status.t.clone() {
+            TransactionStatus::Applied(auxiliary_data, _) => Some(auxiliary_data.t),
+            TransactionStatus::Failed(_, _) => None,
+        }
+    }
+
+    /// Account creation fee paid if the command created the fee payer's account
+    pub fn fee_payer_account_creation_fee_paid(&self) -> Option<Amount> {
+        self.auxiliary_data()?
+            .fee_payer_account_creation_fee_paid
+            .map(|fee| fee.t.t.into())
+    }
+
+    /// Account creation fee paid if the command created the receiver's account
+    pub fn receiver_account_creation_fee_paid(&self) -> Option<Amount> {
+        self.auxiliary_data()?
+            .receiver_account_creation_fee_paid
+            .map(|fee| fee.t.t.into())
+    }
+}
+
+/// Failure reason as the Mina daemon names it, e.g. `Amount_insufficient_to_create_account`
+fn failure_reason_name(failure: &TransactionStatusFailedType) -> &'static str {
+    match failure {
+        TransactionStatusFailedType::Predicate => "Predicate",
+        TransactionStatusFailedType::SourceNotPresent => "Source_not_present",
+        TransactionStatusFailedType::ReceiverNotPresent => "Receiver_not_present",
+        TransactionStatusFailedType::AmountInsufficientToCreateAccount => {
+            "Amount_insufficient_to_create_account"
+        }
+        TransactionStatusFailedType::CannotPayCreationFeeInToken => {
+            "Cannot_pay_creation_fee_in_token"
+        }
+        TransactionStatusFailedType::SourceInsufficientBalance => "Source_insufficient_balance",
+        TransactionStatusFailedType::SourceMinimumBalanceViolation => {
+            "Source_minimum_balance_violation"
+        }
+        TransactionStatusFailedType::ReceiverAlreadyExists => "Receiver_already_exists",
+        TransactionStatusFailedType::NotTokenOwner => "Not_token_owner",
+        TransactionStatusFailedType::MismatchedTokenPermissions => "Mismatched_token_permissions",
+        TransactionStatusFailedType::Overflow => "Overflow",
+        TransactionStatusFailedType::SignedCommandOnSnappAccount => {
+            "Signed_command_on_snapp_account"
+        }
+        TransactionStatusFailedType::SnappAccountNotPresent => "Snapp_account_not_present",
+        TransactionStatusFailedType::UpdateNotPermitted => "Update_not_permitted",
+        TransactionStatusFailedType::IncorrectNonce => "Incorrect_nonce",
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct PaymentPayload(pub PaymentPayloadV1);
 
 impl Command {
+    /// Commands of the block that were applied, failed commands only pay their fees
+    pub fn applied_from_precomputed_block(precomputed_block: &PrecomputedBlock) -> Vec<Self> {
+        Self::from_precomputed_block(precomputed_block)
+            .into_iter()
+            .zip(precomputed_block.commands())
+            .filter(|(_, command)| UserCommandWithStatus(command.clone()).is_applied())
+            .map(|(command, _)| command)
+            .collect()
+    }
+
     pub fn from_precomputed_block(precomputed_block: &PrecomputedBlock) -> Vec<Self> {
         precomputed_block
             .commands()
@@ -107,6 +196,65 @@ impl Command {
     }
 }
 
+impl InternalCommand {
+    /// Internal commands of the block, in the order they're applied to the ledger
+    pub fn from_precomputed_block(
+        precomputed_block: &PrecomputedBlock,
+        network: &NetworkProfile,
+    ) -> Vec<Self> {
+        let mut internal_commands: Vec<Self> =
+            FeeTransfer::from_precomputed_block(precomputed_block)
+                .into_iter()
+                .map(|fee_transfer| Self::FeeTransfer {
+                    receiver: fee_transfer.receiver,
+                    amount: fee_transfer.fee,
+                })
+                .collect();
+
+        let coinbase = Coinbase::from_precomputed_block(precomputed_block);
+        if coinbase.is_applied() {
+            internal_commands.push(Self::Coinbase {
+                receiver: coinbase.receiver.clone(),
+                amount: coinbase.net_amount(network),
+            });
+
+            for fee_transfer in coinbase.fee_transfers {
+                if fee_transfer.fee.0 > 0 {
+                    internal_commands.push(Self::FeeTransferViaCoinbase {
+                        receiver: fee_transfer.receiver,
+                        amount: fee_transfer.fee,
+                    });
+                }
+            }
+        }
+        internal_commands
+    }
+
+    pub fn receiver(&self) -> &PublicKey {
+        match self {
+            Self::FeeTransfer { receiver, .. }
+            | Self::FeeTransferViaCoinbase { receiver, .. }
+            | Self::Coinbase { receiver, .. } => receiver,
+        }
+    }
+
+    pub fn amount(&self) -> Amount {
+        match self {
+            Self::FeeTransfer { amount, .. }
+            | Self::FeeTransferViaCoinbase { amount, .. }
+            | Self::Coinbase { amount, .. } => *amount,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::FeeTransfer { .. } => "FEE_TRANSFER",
+            Self::FeeTransferViaCoinbase { .. } => "FEE_TRANSFER_VIA_COINBASE",
+            Self::Coinbase { .. } => "COINBASE",
+        }
+    }
+}
+
 impl SignedCommand {
     pub fn payload_body(&self) -> SignedCommandPayloadBody {
         self.0
@@ -164,16 +312,22 @@ impl PaymentPayload {
 mod test {
     use std::path::PathBuf;
 
-    use super::{Command, Delegation, Payment};
+    use super::{Command, Delegation, InternalCommand, Payment};
     use crate::{
-        block::parser::BlockParser, state::ledger::PublicKey, MAINNET_CANONICAL_THRESHOLD,
+        block::parser::BlockParser, network::NetworkProfile, state::ledger::PublicKey,
+        MAINNET_CANONICAL_THRESHOLD,
     };
 
     #[tokio::test]
     async fn from_precomputed() {
         // mainnet-220897-3NL4HLb7MQrxmAqVw8D4vEXCj2tdT8zgP9DFWGRoDxP72b4wxyUw
         let log_dir = PathBuf::from("./tests/data/non_sequential_blocks");
-        let mut bp = BlockParser::new(&log_dir, MAINNET_CANONICAL_THRESHOLD).unwrap();
+        let mut bp = BlockParser::new(
+            &log_dir,
+            &NetworkProfile::mainnet(),
+            MAINNET_CANONICAL_THRESHOLD,
+        )
+        .unwrap();
         let block = bp
             .get_precomputed_block("3NL4HLb7MQrxmAqVw8D4vEXCj2tdT8zgP9DFWGRoDxP72b4wxyUw")
             .await
@@ -318,4 +472,59 @@ mod test {
             );
         }
     }
+
+    #[tokio::test]
+    async fn internal_commands() {
+        // mainnet-220897-3NL4HLb7MQrxmAqVw8D4vEXCj2tdT8zgP9DFWGRoDxP72b4wxyUw
+        let log_dir = PathBuf::from("./tests/data/non_sequential_blocks");
+        let mut bp = BlockParser::new(
+            &log_dir,
+            &NetworkProfile::mainnet(),
+            MAINNET_CANONICAL_THRESHOLD,
+        )
+        .unwrap();
+        let block = bp
+            .get_precomputed_block("3NL4HLb7MQrxmAqVw8D4vEXCj2tdT8zgP9DFWGRoDxP72b4wxyUw")
+            .await
+            .unwrap();
+
+        let internal_commands: Vec<(&str, String, u64)> =
+            InternalCommand::from_precomputed_block(&block, &NetworkProfile::mainnet())
+                .iter()
+                .map(|cmd| (cmd.kind(), cmd.receiver().to_address(), cmd.amount().0))
+                .collect();
+        let expected_internal_commands = Vec::from([
+            (
+                "FEE_TRANSFER",
+                "B62qospDjUj43x2yMKiNehojWWRUsE1wpdUDVpfxH8V3n5Y1QgJKFfw".to_string(),
+                598380000,
+            ),
+            (
+                "FEE_TRANSFER",
+                "B62qpcENWiR5VKkrHscV9cWfPwNs56ExFeb94FDiVz9GeV2mBNpMCkY".to_string(),
+                10000,
+            ),
+            (
+                "FEE_TRANSFER",
+                "B62qpcENWiR5VKkrHscV9cWfPwNs56ExFeb94FDiVz9GeV2mBNpMCkY".to_string(),
+                10000,
+            ),
+            (
+                "FEE_TRANSFER",
+                "B62qn2Ne2JGRdbHXdfD8wkA6PTWuBjaxUDQ6QuPAmggrcYjTP3HwWkF".to_string(),
+                1000000,
+            ),
+            (
+                "FEE_TRANSFER",
+                "B62qqWzWHjUmJSSB9db6BDpGjFJkRNjjtZorwJdpeASzSPHpRe4CoJS".to_string(),
+                100000,
+            ),
+            (
+                "COINBASE",
+                "B62qospDjUj43x2yMKiNehojWWRUsE1wpdUDVpfxH8V3n5Y1QgJKFfw".to_string(),
+                1440000000000,
+            ),
+        ]);
+        assert_eq!(expected_internal_commands, internal_commands);
+    }
 }
@@ -1,7 +1,9 @@
 use crate::{
-    block::precomputed::PrecomputedBlock,
+    block::{precomputed::PrecomputedBlock, signed_command},
+    network::NetworkProfile,
     state::ledger::{
-        command::{Command, SignedCommand},
+        command::{Command, InternalCommand, SignedCommand, UserCommandWithStatus},
+        fee_transfer::FeeTransfer,
         Amount, PublicKey,
     },
 };
@@ -27,10 +29,29 @@ pub struct DelegationDiff {
     pub delegate: PublicKey,
 }
 
+/// User command fee paid by the fee payer, consuming one of its nonces
+#[derive(PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
+pub struct FeePaymentDiff {
+    pub public_key: PublicKey,
+    pub fee: Amount,
+}
+
+/// Account created by a command, paying the account creation fee
+#[derive(PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
+pub struct AccountCreationDiff {
+    pub public_key: PublicKey,
+    pub fee: Amount,
+}
+
 #[derive(PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
 pub enum AccountDiff {
     Payment(PaymentDiff),
     Delegation(DelegationDiff),
+    AccountCreation(AccountCreationDiff),
+    /// Account credited by a coinbase or fee transfer, paying the account
+    /// creation fee if it isn't in the ledger yet
+    InternalAccountCreation(AccountCreationDiff),
+    FeePayment(FeePaymentDiff),
 }
 
 impl AccountDiff {
@@ -71,17 +92,41 @@ impl AccountDiff {
         match self {
             AccountDiff::Payment(payment_diff) => payment_diff.public_key.clone(),
             AccountDiff::Delegation(delegation_diff) => delegation_diff.delegator.clone(),
+            AccountDiff::AccountCreation(creation_diff)
+            | AccountDiff::InternalAccountCreation(creation_diff) => {
+                creation_diff.public_key.clone()
+            }
+            AccountDiff::FeePayment(fee_payment_diff) => fee_payment_diff.public_key.clone(),
         }
     }
 
-    pub fn from_block_fees(
-        coinbase_receiver: PublicKey,
-        precomputed_block: &PrecomputedBlock,
-    ) -> Vec<AccountDiff> {
+    pub fn from_fee_transfer(receiver: PublicKey, fee: Amount) -> Self {
+        AccountDiff::Payment(PaymentDiff {
+            public_key: receiver,
+            amount: fee,
+            update_type: UpdateType::Deposit,
+        })
+    }
+
+    /// User command fees are deducted from the fee payers and paid out
+    /// to the SNARK workers and the coinbase receiver via fee transfers
+    pub fn from_block_fees(precomputed_block: &PrecomputedBlock) -> Vec<AccountDiff> {
+        let mut account_diffs = Self::from_user_command_fees(precomputed_block);
+        account_diffs.append(
+            &mut FeeTransfer::from_precomputed_block(precomputed_block)
+                .into_iter()
+                .map(FeeTransfer::as_account_diff)
+                .collect(),
+        );
+        account_diffs
+    }
+
+    /// User command fees deducted from the fee payers, applied or failed
+    pub fn from_user_command_fees(precomputed_block: &PrecomputedBlock) -> Vec<AccountDiff> {
         precomputed_block
             .commands()
             .iter()
-            .flat_map(
+            .map(
                 |command| match command.clone().inner().data.inner().inner() {
                     UserCommand::SignedCommand(signed_command) => {
                         let SignedCommandPayloadCommon {
@@ -92,23 +137,67 @@ impl AccountDiff {
                             valid_until: _valid_until,
                             memo: _memo,
                         } = SignedCommand(signed_command).payload_common();
-                        vec![
-                            AccountDiff::Payment(PaymentDiff {
-                                public_key: fee_payer_pk.into(),
-                                amount: fee.clone().inner().inner().into(),
-                                update_type: UpdateType::Deduction,
-                            }),
-                            AccountDiff::Payment(PaymentDiff {
-                                public_key: coinbase_receiver.clone(),
-                                amount: fee.inner().inner().into(),
-                                update_type: UpdateType::Deposit,
-                            }),
-                        ]
+                        AccountDiff::FeePayment(FeePaymentDiff {
+                            public_key: fee_payer_pk.into(),
+                            fee: fee.inner().inner().into(),
+                        })
                     }
                 },
             )
             .collect()
     }
+
+    /// Accounts created by the block's user commands, the creation fee is
+    /// deducted from the amount the new account receives
+    pub fn from_account_creation_fees(precomputed_block: &PrecomputedBlock) -> Vec<AccountDiff> {
+        precomputed_block
+            .commands()
+            .into_iter()
+            .flat_map(|command| {
+                let command = UserCommandWithStatus(command);
+                let signed_command =
+                    signed_command::SignedCommand::from_user_command(command.clone());
+                let mut account_diffs = vec![];
+
+                if let Some(fee) = command.fee_payer_account_creation_fee_paid() {
+                    account_diffs.push(AccountDiff::AccountCreation(AccountCreationDiff {
+                        public_key: signed_command.fee_payer(),
+                        fee,
+                    }));
+                }
+                if let Some(fee) = command.receiver_account_creation_fee_paid() {
+                    account_diffs.push(AccountDiff::AccountCreation(AccountCreationDiff {
+                        public_key: signed_command.receiver_pk(),
+                        fee,
+                    }));
+                }
+                account_diffs
+            })
+            .collect()
+    }
+
+    /// Receivers of the block's coinbase and fee transfers, each may be new to the ledger
+    pub fn from_internal_account_creation_fees(
+        precomputed_block: &PrecomputedBlock,
+        network: &NetworkProfile,
+    ) -> Vec<AccountDiff> {
+        let mut receivers: Vec<PublicKey> = vec![];
+        for internal_command in InternalCommand::from_precomputed_block(precomputed_block, network)
+        {
+            if !receivers.contains(internal_command.receiver()) {
+                receivers.push(internal_command.receiver().clone());
+            }
+        }
+        receivers
+            .into_iter()
+            .map(|public_key| {
+                AccountDiff::InternalAccountCreation(AccountCreationDiff {
+                    public_key,
+                    fee: network.account_creation_fee.into(),
+                })
+            })
+            .collect()
+    }
 }
 
 impl std::fmt::Debug for PaymentDiff {
@@ -127,11 +216,32 @@ impl std::fmt::Debug for DelegationDiff {
     }
 }
 
+impl std::fmt::Debug for FeePaymentDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} | {}", self.public_key, self.fee.0)
+    }
+}
+
+impl std::fmt::Debug for AccountCreationDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} | {}", self.public_key, self.fee.0)
+    }
+}
+
 impl std::fmt::Debug for AccountDiff {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             AccountDiff::Payment(pay_diff) => write!(f, "Payment: {pay_diff:?}"),
             AccountDiff::Delegation(del_diff) => write!(f, "Delegation: {del_diff:?}"),
+            AccountDiff::AccountCreation(creation_diff) => {
+                write!(f, "AccountCreation: {creation_diff:?}")
+            }
+            AccountDiff::InternalAccountCreation(creation_diff) => {
+                write!(f, "InternalAccountCreation: {creation_diff:?}")
+            }
+            AccountDiff::FeePayment(fee_payment_diff) => {
+                write!(f, "FeePayment: {fee_payment_diff:?}")
+            }
         }
     }
 }
@@ -1,7 +1,7 @@
 use account::AccountDiff;
 use serde::{Deserialize, Serialize};
 
-use crate::block::precomputed::PrecomputedBlock;
+use crate::{block::precomputed::PrecomputedBlock, network::NetworkProfile};
 
 use super::{coinbase::Coinbase, command::Command, PublicKey};
 
@@ -15,26 +15,37 @@ pub struct LedgerDiff {
 
 impl LedgerDiff {
     /// the deserialization used by the types used by this function has a lot of room for improvement
-    pub fn from_precomputed_block(precomputed_block: &PrecomputedBlock) -> Self {
+    pub fn from_precomputed_block(
+        precomputed_block: &PrecomputedBlock,
+        network: &NetworkProfile,
+    ) -> Self {
         // [A] fallible deserialization function doesn't specify if it fails because it couldn't read a block or because there weren't any of the requested data in a block
         let coinbase = Coinbase::from_precomputed_block(precomputed_block);
-        let coinbase_update = coinbase.clone().as_account_diff();
+        let mut coinbase_updates = coinbase.as_account_diffs(network);
 
-        let commands = Command::from_precomputed_block(precomputed_block); // [A]
+        let commands = Command::applied_from_precomputed_block(precomputed_block); // [A]
         let mut account_diffs_fees: Vec<AccountDiff> =
-            AccountDiff::from_block_fees(coinbase.receiver, precomputed_block); // [A]
+            AccountDiff::from_block_fees(precomputed_block); // [A]
         let mut account_diffs_transactions = commands
             .iter()
             .cloned()
             .flat_map(AccountDiff::from_command)
             .collect();
+        let mut account_diffs_creation_fees =
+            AccountDiff::from_account_creation_fees(precomputed_block);
 
         let mut account_diffs = Vec::new();
         account_diffs.append(&mut account_diffs_fees);
         account_diffs.append(&mut account_diffs_transactions);
-        account_diffs.push(coinbase_update);
+        account_diffs.append(&mut account_diffs_creation_fees);
+        account_diffs.append(&mut coinbase_updates);
+        account_diffs.append(&mut AccountDiff::from_internal_account_creation_fees(
+            precomputed_block,
+            network,
+        ));
 
-        let public_keys_seen = precomputed_block.block_public_keys().into_iter().collect();
+        // failed commands don't touch their receivers
+        let public_keys_seen = account_diffs.iter().map(AccountDiff::public_key).collect();
 
         LedgerDiff {
             public_keys_seen,
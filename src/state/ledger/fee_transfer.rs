@@ -0,0 +1,68 @@
+use crate::block::{precomputed::PrecomputedBlock, signed_command::SignedCommand};
+
+use super::{
+    coinbase::Coinbase, command::UserCommandWithStatus, diff::account::AccountDiff, Amount,
+    PublicKey,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FeeTransfer {
+    pub receiver: PublicKey,
+    pub fee: Amount,
+}
+
+impl FeeTransfer {
+    /// Fee transfers paid out of the block's user command fees.
+    ///
+    /// SNARK workers are paid for their completed works, unless the work is
+    /// already paid by a coinbase fee transfer. The remaining fees go to the
+    /// coinbase receiver.
+    pub fn from_precomputed_block(precomputed_block: &PrecomputedBlock) -> Vec<Self> {
+        let coinbase = Coinbase::from_precomputed_block(precomputed_block);
+        let total_fees: u64 = precomputed_block
+            .commands()
+            .into_iter()
+            .map(|command| {
+                SignedCommand::from_user_command(UserCommandWithStatus(command))
+                    .fee()
+                    .0
+            })
+            .sum();
+
+        let mut snark_fee_transfers: Vec<Self> = precomputed_block
+            .completed_works()
+            .into_iter()
+            .map(|work| Self {
+                receiver: work.prover.into(),
+                fee: work.fee.inner().inner().into(),
+            })
+            .collect();
+
+        // works paid by the coinbase are not paid out of the transaction fees
+        for coinbase_fee_transfer in coinbase.fee_transfers.iter() {
+            if let Some(idx) = snark_fee_transfers
+                .iter()
+                .position(|fee_transfer| fee_transfer == coinbase_fee_transfer)
+            {
+                snark_fee_transfers.remove(idx);
+            }
+        }
+
+        let total_snark_fees: u64 = snark_fee_transfers
+            .iter()
+            .map(|fee_transfer| fee_transfer.fee.0)
+            .sum();
+        let mut fee_transfers = vec![Self {
+            receiver: coinbase.receiver,
+            fee: total_fees.saturating_sub(total_snark_fees).into(),
+        }];
+
+        fee_transfers.append(&mut snark_fee_transfers);
+        fee_transfers.retain(|fee_transfer| fee_transfer.fee.0 > 0);
+        fee_transfers
+    }
+
+    pub fn as_account_diff(self) -> AccountDiff {
+        AccountDiff::from_fee_transfer(self.receiver, self.fee)
+    }
+}
@@ -1,14 +1,18 @@
 use super::{
-    account::{Account, Amount, Nonce},
-    Ledger,
+    account::{Account, Amount, Nonce, Timing},
+    hash::merkle_root,
+    Ledger, LedgerHash,
 };
+use chrono::DateTime;
 use mina_serialization_types::{
     signatures::{CompressedCurvePoint, PublicKeyJson},
     v1::PublicKeyV1,
 };
 use mina_signer::CompressedPubKey;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use rust_decimal_macros::dec;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, error::Error, path::Path};
+use std::{error::Error, path::Path};
 use tokio::io::AsyncReadExt;
 use tracing::debug;
 
@@ -17,6 +21,15 @@ pub struct GenesisTimestamp {
     pub genesis_state_timestamp: String,
 }
 
+impl GenesisTimestamp {
+    /// Genesis state timestamp in milliseconds since the unix epoch
+    pub fn timestamp_millis(&self) -> Result<u64, chrono::ParseError> {
+        // some genesis ledgers separate the date and time with a space
+        DateTime::parse_from_rfc3339(&self.genesis_state_timestamp.replacen(' ', "T", 1))
+            .map(|datetime| datetime.timestamp_millis() as u64)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenesisRoot {
     pub genesis: GenesisTimestamp,
@@ -40,6 +53,24 @@ pub struct GenesisAccountTiming {
     pub vesting_increment: String,
 }
 
+impl GenesisAccountTiming {
+    /// Amounts are given in mina, times in global slots
+    pub fn to_timing(&self) -> Option<Timing> {
+        Some(Timing {
+            initial_minimum_balance: mina_to_nanomina(&self.initial_minimum_balance)?,
+            cliff_time: self.cliff_time.parse().ok()?,
+            cliff_amount: mina_to_nanomina(&self.cliff_amount)?,
+            vesting_period: self.vesting_period.parse().ok()?,
+            vesting_increment: mina_to_nanomina(&self.vesting_increment)?,
+        })
+    }
+}
+
+fn mina_to_nanomina(amount: &str) -> Option<Amount> {
+    let amount = amount.parse::<Decimal>().ok()? * dec!(1_000_000_000);
+    amount.to_u64().map(Amount)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenesisLedger {
     pub name: String,
@@ -52,20 +83,21 @@ pub fn string_to_public_key_json(s: String) -> Result<PublicKeyJson, Box<dyn Err
     Ok(pk.into())
 }
 
-impl From<GenesisRoot> for Ledger {
-    fn from(value: GenesisRoot) -> Self {
-        value.ledger.into()
+impl TryFrom<GenesisRoot> for Ledger {
+    type Error = anyhow::Error;
+
+    fn try_from(value: GenesisRoot) -> anyhow::Result<Self> {
+        value.ledger.try_into()
     }
 }
 
-impl From<GenesisLedger> for Ledger {
-    fn from(genesis_ledger: GenesisLedger) -> Ledger {
-        let mut accounts = HashMap::new();
-        for genesis_account in genesis_ledger.accounts {
-            let balance = match str::parse::<u64>(&genesis_account.balance) {
-                Ok(amt) => Amount(amt * 1_000_000_000),
-                Err(_) => Amount::default(),
-            };
+impl GenesisLedger {
+    /// Accounts in the order they're listed, which is their ledger index order
+    pub fn ledger_accounts(&self) -> anyhow::Result<Vec<Account>> {
+        let mut accounts = vec![];
+        for genesis_account in self.accounts.iter().cloned() {
+            // balances are given in mina
+            let balance = mina_to_nanomina(&genesis_account.balance).unwrap_or_default();
             // Temporary hack to ignore bad PKs in mainnet genesis ledger
             if genesis_account.pk == "B62qpyhbvLobnd4Mb52vP7LPFAasb2S6Qphq8h5VV8Sq1m7VNK1VZcW"
                 || genesis_account.pk == "B62qqdcf6K9HyBSaxqH5JVFJkc1SUEe1VzDc5kYZFQZXWSQyGHoino1"
@@ -76,23 +108,57 @@ impl From<GenesisLedger> for Ledger {
                 );
                 continue;
             }
-            if let Ok(pk) = string_to_public_key_json(genesis_account.pk) {
-                accounts.insert(
-                    PublicKeyV1::from(pk.clone()).into(),
-                    Account {
-                        public_key: PublicKeyV1::from(pk.clone()).into(),
-                        delegate: genesis_account.delegate.clone().map(|delegate| {
-                            PublicKeyV1::from(string_to_public_key_json(delegate).unwrap()).into()
-                        }),
-                        balance,
-                        nonce: Nonce::default(),
-                    },
-                );
-            } else {
-                panic!("Unparsable public key");
-            }
+            let pk = string_to_public_key_json(genesis_account.pk.clone()).map_err(|err| {
+                anyhow::anyhow!("Unparsable public key {}: {err}", genesis_account.pk)
+            })?;
+            let delegate = match genesis_account.delegate {
+                Some(delegate) => Some(
+                    PublicKeyV1::from(string_to_public_key_json(delegate.clone()).map_err(
+                        |err| anyhow::anyhow!("Unparsable delegate public key {delegate}: {err}"),
+                    )?)
+                    .into(),
+                ),
+                None => None,
+            };
+            let timing = match genesis_account.timing.as_ref() {
+                Some(timing) => Some(timing.to_timing().ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Unparsable account timing for {}: {timing:?}",
+                        genesis_account.pk
+                    )
+                })?),
+                None => None,
+            };
+            accounts.push(Account {
+                public_key: PublicKeyV1::from(pk).into(),
+                delegate,
+                balance,
+                nonce: Nonce::default(),
+                timing,
+            });
         }
-        Ledger { accounts }
+        Ok(accounts)
+    }
+
+    /// Merkle root of the genesis ledger, with the accounts at their ledger indices
+    pub fn merkle_root(&self, depth: u32) -> anyhow::Result<LedgerHash> {
+        Ok(LedgerHash::from_field(merkle_root(
+            &self.ledger_accounts()?,
+            depth,
+        )))
+    }
+}
+
+impl TryFrom<GenesisLedger> for Ledger {
+    type Error = anyhow::Error;
+
+    fn try_from(genesis_ledger: GenesisLedger) -> anyhow::Result<Ledger> {
+        let accounts = genesis_ledger
+            .ledger_accounts()?
+            .into_iter()
+            .map(|account| (account.public_key.clone(), account))
+            .collect();
+        Ok(Ledger { accounts })
     }
 }
 
@@ -0,0 +1,241 @@
+use super::{
+    account::{Account, Amount, Timing},
+    Ledger, LedgerError, LedgerHash,
+};
+use crate::block::precomputed::PrecomputedBlock;
+use mina_hasher::{create_legacy, Hashable, Hasher, ROInput};
+use mina_signer::{BaseField, CompressedPubKey};
+use o1_utils::FieldHelpers;
+
+const DEFAULT_TOKEN_ID: u64 = 1;
+
+/// Account fields in the order Mina hashes them
+///
+/// The indexer doesn't track receipt chain hashes, voting-for hashes,
+/// permissions or snapp state, those are hashed as a fresh account's.
+#[derive(Clone)]
+struct AccountInput {
+    public_key: CompressedPubKey,
+    balance: u64,
+    nonce: u32,
+    delegate: CompressedPubKey,
+    timing: Option<Timing>,
+}
+
+/// Pair of sibling hashes in the ledger's Merkle tree
+#[derive(Clone)]
+struct MerkleNode(BaseField, BaseField);
+
+impl AccountInput {
+    /// Occupies the ledger's unused leaves
+    fn empty() -> Self {
+        let empty_public_key = CompressedPubKey {
+            x: BaseField::from(0u64),
+            is_odd: false,
+        };
+        Self {
+            public_key: empty_public_key.clone(),
+            balance: 0,
+            nonce: 0,
+            delegate: empty_public_key,
+            timing: None,
+        }
+    }
+}
+
+impl From<&Account> for AccountInput {
+    fn from(account: &Account) -> Self {
+        let public_key = CompressedPubKey::from(&account.public_key);
+        Self {
+            delegate: account
+                .delegate
+                .as_ref()
+                .map(CompressedPubKey::from)
+                .unwrap_or_else(|| public_key.clone()),
+            public_key,
+            balance: account.balance.0,
+            nonce: account.nonce.0,
+            timing: account.timing.clone(),
+        }
+    }
+}
+
+/// Bits of an authorization requirement: constant, signature necessary, signature sufficient
+fn append_auth_required(roi: ROInput, signature: bool) -> ROInput {
+    match signature {
+        true => roi.append_bool(false).append_bool(true).append_bool(true),
+        false => roi.append_bool(true).append_bool(false).append_bool(true),
+    }
+}
+
+/// Default user permissions: stake, and every action but receiving requires a signature
+fn append_default_permissions(roi: ROInput) -> ROInput {
+    let roi = roi.append_bool(true);
+    [true, true, false, true, true, true]
+        .into_iter()
+        .fold(roi, append_auth_required)
+}
+
+impl Hashable for AccountInput {
+    type D = ();
+
+    fn to_roinput(&self) -> ROInput {
+        let timing = self.timing.clone().unwrap_or(Timing {
+            initial_minimum_balance: Amount(0),
+            cliff_time: 0,
+            cliff_amount: Amount(0),
+            vesting_period: 0,
+            vesting_increment: Amount(0),
+        });
+
+        let roi = ROInput::new()
+            .append_hashable(&self.public_key)
+            .append_u64(DEFAULT_TOKEN_ID)
+            // token permissions: not a token owner, not disabled
+            .append_bool(false)
+            .append_bool(false)
+            .append_u64(self.balance)
+            .append_u32(self.nonce)
+            // receipt chain hash
+            .append_field(BaseField::from(0u64))
+            .append_hashable(&self.delegate)
+            // voting for
+            .append_field(BaseField::from(0u64))
+            .append_bool(self.timing.is_some())
+            .append_u64(timing.initial_minimum_balance.0)
+            .append_u32(timing.cliff_time)
+            .append_u64(timing.cliff_amount.0)
+            .append_u32(timing.vesting_period)
+            .append_u64(timing.vesting_increment.0);
+
+        // permissions, then an empty snapp state
+        append_default_permissions(roi).append_field(BaseField::from(0u64))
+    }
+
+    fn domain_string(_: Self::D) -> Option<String> {
+        Some("CodaAccount".to_string())
+    }
+}
+
+impl Hashable for MerkleNode {
+    /// Height of the children in the tree, leaves are at height 0
+    type D = u32;
+
+    fn to_roinput(&self) -> ROInput {
+        ROInput::new().append_field(self.0).append_field(self.1)
+    }
+
+    fn domain_string(height: Self::D) -> Option<String> {
+        Some(format!("CodaMklTree{height:03}"))
+    }
+}
+
+impl Account {
+    /// Poseidon hash of the account, as a leaf of the ledger's Merkle tree
+    pub fn hash(&self) -> BaseField {
+        create_legacy::<AccountInput>(()).hash(&AccountInput::from(self))
+    }
+}
+
+impl LedgerHash {
+    pub fn from_field(field: BaseField) -> Self {
+        let bytes: [u8; 32] = field
+            .to_bytes()
+            .try_into()
+            .expect("field elements are 32 bytes");
+        Self::from_hashv1(bytes.into())
+    }
+}
+
+/// Merkle root of a tree of the given depth with `accounts` as its leftmost leaves
+pub fn merkle_root<'a>(accounts: impl IntoIterator<Item = &'a Account>, depth: u32) -> BaseField {
+    let mut account_hasher = create_legacy::<AccountInput>(());
+    let mut level: Vec<BaseField> = accounts
+        .into_iter()
+        .map(|account| account_hasher.hash(&AccountInput::from(account)))
+        .collect();
+    assert!(
+        level.len() as u64 <= 1 << depth,
+        "{} accounts don't fit in a ledger of depth {depth}",
+        level.len()
+    );
+
+    // root of an unoccupied subtree at the current height
+    let mut empty = account_hasher.hash(&AccountInput::empty());
+    for height in 0..depth {
+        let mut node_hasher = create_legacy::<MerkleNode>(height);
+        if level.len() % 2 == 1 {
+            level.push(empty);
+        }
+        level = level
+            .chunks(2)
+            .map(|pair| node_hasher.hash(&MerkleNode(pair[0], pair[1])))
+            .collect();
+        empty = node_hasher.hash(&MerkleNode(empty, empty));
+    }
+    level.first().copied().unwrap_or(empty)
+}
+
+impl Ledger {
+    /// Merkle root of the ledger with its accounts ordered by public key
+    ///
+    /// Mina orders accounts by the index they were added at, which the
+    /// indexer doesn't record.
+    pub fn merkle_root(&self, depth: u32) -> LedgerHash {
+        let mut accounts: Vec<&Account> = self.accounts.values().collect();
+        accounts.sort();
+        LedgerHash::from_field(merkle_root(accounts, depth))
+    }
+
+    /// Checks the ledger's Merkle root against the staged and snarked ledger
+    /// hashes `block` commits to, returning the root if either matches
+    pub fn verify_hash(
+        &self,
+        block: &PrecomputedBlock,
+        depth: u32,
+    ) -> Result<LedgerHash, LedgerError> {
+        let computed = self.merkle_root(depth);
+        let staged = block.staged_ledger_hash();
+        let snarked = block.snarked_ledger_hash();
+        if computed == staged || computed == snarked {
+            Ok(computed)
+        } else {
+            Err(LedgerError::HashMismatch {
+                computed,
+                staged,
+                snarked,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::merkle_root;
+    use crate::state::ledger::{account::Account, public_key::PublicKey};
+
+    #[test]
+    fn merkle_root_pads_with_empty_leaves() {
+        let account = Account::empty(
+            PublicKey::from_address("B62qrRvo5wngd5WA1dgXkQpCdQMRDndusmjfWXWT1LgsSFFdBS9RCsV")
+                .unwrap(),
+        );
+
+        // a single account at depth 0 is the root
+        assert_eq!(merkle_root([&account], 0), account.hash());
+
+        // the root depends on the accounts and their order, not just their number
+        let other = Account::empty(
+            PublicKey::from_address("B62qrdhG66vK71Jbdz6Xs7cnDxQ8f6jZUFvefkp3pje4EejYUTvotGP")
+                .unwrap(),
+        );
+        assert_ne!(merkle_root([&account], 4), merkle_root([&other], 4));
+        assert_ne!(
+            merkle_root([&account, &other], 4),
+            merkle_root([&other, &account], 4)
+        );
+        let no_accounts: [&Account; 0] = [];
+        assert_ne!(merkle_root([&account], 4), merkle_root(no_accounts, 4));
+        assert_ne!(merkle_root(no_accounts, 3), merkle_root(no_accounts, 4));
+    }
+}
@@ -2,23 +2,30 @@ pub mod account;
 pub mod coinbase;
 pub mod command;
 pub mod diff;
+pub mod fee_transfer;
 pub mod genesis;
+pub mod hash;
 pub mod post_balances;
 pub mod public_key;
+pub mod reconciliation;
 pub mod store;
 
 use crate::{block::precomputed::PrecomputedBlock, state::ledger::post_balances::UserCommandType};
 
 use self::{
     account::{Amount, Nonce},
-    post_balances::{PostBalance, PostBalanceUpdate},
+    post_balances::{BlockPostBalances, PostBalance, PostBalanceUpdate},
 };
 use account::Account;
 use diff::LedgerDiff;
+use mina_serialization_types::{common::Base58EncodableVersionedType, v1::HashV1, version_bytes};
 use mina_signer::pubkey::PubKeyError;
 use public_key::PublicKey;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, result::Result};
+use std::{
+    collections::{HashMap, HashSet},
+    result::Result,
+};
 use tracing::debug;
 
 impl ExtendWithLedgerDiff for LedgerMock {
@@ -39,6 +46,9 @@ pub struct Ledger {
     pub accounts: HashMap<PublicKey, Account>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LedgerHash(pub String);
+
 #[derive(Default, Clone, Serialize, Deserialize)]
 pub struct NonGenesisLedger {
     pub ledger: Ledger,
@@ -48,6 +58,19 @@ pub struct NonGenesisLedger {
 pub enum LedgerError {
     AccountNotFound,
     InvalidDelegation,
+    HashMismatch {
+        computed: LedgerHash,
+        staged: LedgerHash,
+        snarked: LedgerHash,
+    },
+}
+
+impl LedgerHash {
+    pub fn from_hashv1(hashv1: HashV1) -> Self {
+        let versioned: Base58EncodableVersionedType<{ version_bytes::LEDGER_HASH }, _> =
+            hashv1.into();
+        Self(versioned.to_base58_string().unwrap())
+    }
 }
 
 impl Ledger {
@@ -63,12 +86,19 @@ impl Ledger {
         }
     }
 
-    pub fn apply_balance_update(&mut self, balance_update: PostBalance, nonce: Option<i32>) {
+    /// Only accounts created by the command may be missing from the ledger
+    pub fn apply_balance_update(
+        &mut self,
+        balance_update: PostBalance,
+        nonce: Option<i32>,
+    ) -> Result<(), LedgerError> {
         if let Some(account) = self.accounts.get_mut(&balance_update.public_key) {
             if let Some(nonce) = nonce {
                 account.nonce = Nonce(nonce as u32 + 1);
             }
             account.balance = Amount(balance_update.balance);
+        } else if !balance_update.created {
+            return Err(LedgerError::AccountNotFound);
         } else {
             let new_account = Account {
                 public_key: balance_update.public_key.clone(),
@@ -79,25 +109,64 @@ impl Ledger {
                     Nonce(0)
                 },
                 delegate: None,
+                timing: None,
             };
             self.accounts.insert(balance_update.public_key, new_account);
         }
+        Ok(())
+    }
+
+    /// Applies the post balances of the block's user commands, then those of
+    /// its coinbase and fee transfers
+    pub fn apply_post_balances(
+        &mut self,
+        precomputed_block: &PrecomputedBlock,
+    ) -> anyhow::Result<()> {
+        self.apply_block_post_balances(&BlockPostBalances::from_precomputed(precomputed_block))
+    }
+
+    /// Applies a block's post balances as stored in the db
+    pub fn apply_block_post_balances(
+        &mut self,
+        post_balances: &BlockPostBalances,
+    ) -> anyhow::Result<()> {
+        self.apply_post_balance_updates(&post_balances.user_commands)?;
+        for balance_update in &post_balances.internal_commands {
+            self.apply_balance_update(balance_update.clone(), None)?;
+        }
+        Ok(())
     }
 
-    pub fn apply_post_balances(&mut self, precomputed_block: &PrecomputedBlock) {
-        PostBalanceUpdate::from_precomputed(precomputed_block)
-            .into_iter()
-            .for_each(|user_command| {
+    pub fn apply_user_command_post_balances(
+        &mut self,
+        precomputed_block: &PrecomputedBlock,
+    ) -> anyhow::Result<()> {
+        self.apply_post_balance_updates(&PostBalanceUpdate::from_precomputed(precomputed_block))
+    }
+
+    fn apply_post_balance_updates(
+        &mut self,
+        user_commands: &[PostBalanceUpdate],
+    ) -> anyhow::Result<()> {
+        for user_command in user_commands {
+            if let Some(receiver) = &user_command.receiver {
                 if UserCommandType::Delegation == user_command.command_type {
                     self.apply_delegation(
                         user_command.source.public_key.clone(),
-                        user_command.receiver.public_key.clone(),
+                        receiver.public_key.clone(),
                     );
                 }
-                self.apply_balance_update(user_command.fee_payer, None);
-                self.apply_balance_update(user_command.receiver, None);
-                self.apply_balance_update(user_command.source, Some(user_command.source_nonce));
-            });
+                self.apply_balance_update(receiver.clone(), None)?;
+            }
+
+            // failed commands still pay the fee and consume the source's nonce
+            self.apply_balance_update(user_command.fee_payer.clone(), None)?;
+            self.apply_balance_update(
+                user_command.source.clone(),
+                Some(user_command.source_nonce),
+            )?;
+        }
+        Ok(())
     }
 
     pub fn from(value: Vec<(&str, u64, Option<u32>, Option<&str>)>) -> Result<Self, PubKeyError> {
@@ -115,6 +184,7 @@ impl Ledger {
                                         balance: balance.into(),
                                         nonce: Nonce(nonce.unwrap_or_default()),
                                         delegate: Some(delegate),
+                                        timing: None,
                                     },
                                 );
                             }
@@ -126,6 +196,7 @@ impl Ledger {
                             balance: balance.into(),
                             nonce: Nonce(nonce.unwrap_or_default()),
                             delegate: None,
+                            timing: None,
                         };
                         ledger.accounts.insert(pk, acct);
                     }
@@ -140,10 +211,12 @@ impl Ledger {
     pub fn apply_diff(&mut self, diff: &LedgerDiff) -> anyhow::Result<()> {
         let diff = diff.clone();
 
+        let mut new_accounts = HashSet::new();
         diff.public_keys_seen.into_iter().for_each(|public_key| {
             if self.accounts.get(&public_key).is_none() {
                 self.accounts
-                    .insert(public_key.clone(), Account::empty(public_key));
+                    .insert(public_key.clone(), Account::empty(public_key.clone()));
+                new_accounts.insert(public_key);
             }
         });
 
@@ -174,13 +247,37 @@ impl Ledger {
                                 delegation_diff.delegate.clone(),
                             )
                         }
+                        diff::account::AccountDiff::AccountCreation(creation_diff) => {
+                            new_accounts.remove(&creation_diff.public_key);
+                            Account::from_creation_fee(account_before, creation_diff.fee)
+                        }
+                        diff::account::AccountDiff::InternalAccountCreation(creation_diff) => {
+                            // accounts created by a user command already paid the fee
+                            if new_accounts.remove(&creation_diff.public_key) {
+                                Account::from_creation_fee(account_before, creation_diff.fee)
+                            } else {
+                                account_before
+                            }
+                        }
+                        diff::account::AccountDiff::FeePayment(fee_payment_diff) => {
+                            match Account::from_fee_payment(
+                                account_before.clone(),
+                                fee_payment_diff.fee,
+                            ) {
+                                Some(account) => account,
+                                None => account_before,
+                            }
+                        }
                     };
 
                     self.accounts.insert(diff.public_key(), account_after);
                 }
                 None => {
                     let error = match diff {
-                        diff::account::AccountDiff::Payment(_) => LedgerError::AccountNotFound,
+                        diff::account::AccountDiff::Payment(_)
+                        | diff::account::AccountDiff::AccountCreation(_)
+                        | diff::account::AccountDiff::InternalAccountCreation(_)
+                        | diff::account::AccountDiff::FeePayment(_) => LedgerError::AccountNotFound,
                         diff::account::AccountDiff::Delegation(_) => LedgerError::InvalidDelegation,
                     };
 
@@ -272,6 +369,15 @@ impl std::fmt::Display for LedgerError {
             LedgerError::InvalidDelegation => {
                 write!(f, "Invalid data or parameters: delegation error")
             }
+            LedgerError::HashMismatch {
+                computed,
+                staged,
+                snarked,
+            } => write!(
+                f,
+                "Ledger hash mismatch: computed {}, block commits to staged {} and snarked {}",
+                computed.0, staged.0, snarked.0
+            ),
         }
     }
 }
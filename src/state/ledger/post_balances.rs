@@ -1,81 +1,193 @@
 use crate::block::{precomputed::PrecomputedBlock, signed_command::SignedCommand};
+use mina_serialization_types::staged_ledger_diff::InternalCommandBalanceData;
+use mina_signer::CompressedPubKey;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
 
 use super::{
+    coinbase::Coinbase,
     command::{CommandStatusData, UserCommandWithStatus},
+    fee_transfer::FeeTransfer,
     public_key::PublicKey,
 };
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash, Serialize, Deserialize)]
 pub enum UserCommandType {
     Payment,
     Delegation,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PostBalance {
     pub public_key: PublicKey,
     pub balance: u64,
+    /// Whether the command created the account
+    pub created: bool,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PostBalanceUpdate {
     pub source_nonce: i32,
     pub command_type: UserCommandType,
     pub fee_payer: PostBalance,
     pub source: PostBalance,
-    pub receiver: PostBalance,
+    /// Failed commands don't touch the receiver
+    pub receiver: Option<PostBalance>,
+}
+
+/// A block's post balances in the order they're applied. They're stored
+/// per block so ledgers can be rebuilt without reading the blocks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockPostBalances {
+    pub user_commands: Vec<PostBalanceUpdate>,
+    pub internal_commands: Vec<PostBalance>,
+}
+
+impl BlockPostBalances {
+    pub fn from_precomputed(precomputed_block: &PrecomputedBlock) -> Self {
+        Self {
+            user_commands: PostBalanceUpdate::from_precomputed(precomputed_block),
+            internal_commands: PostBalance::from_internal_commands(precomputed_block),
+        }
+    }
 }
 
 impl PostBalanceUpdate {
     pub fn from_precomputed(precomputed_block: &PrecomputedBlock) -> Vec<Self> {
         precomputed_block
             .commands()
+            .into_iter()
+            .filter_map(|command| {
+                let command = UserCommandWithStatus(command);
+                let post_balance_update = Self::from_command(&command);
+                if post_balance_update.is_none() {
+                    warn!(
+                        "User command {} of block {} is missing its post balances, skipping it",
+                        SignedCommand::from_user_command(command)
+                            .hash_signed_command()
+                            .unwrap_or_default(),
+                        precomputed_block.state_hash,
+                    );
+                }
+                post_balance_update
+            })
+            .collect()
+    }
+
+    /// `None` if the command's balance data is missing the fee payer's, the
+    /// source's or, for applied commands, the receiver's balance
+    ///
+    /// Failed commands may leave out the source's balance, it's the fee payer's
+    /// if they're the same account.
+    pub fn from_command(command: &UserCommandWithStatus) -> Option<Self> {
+        let signed_command = SignedCommand::from_user_command(command.clone());
+        let source_nonce = signed_command.source_nonce();
+        let (applied, balance_data) = match command.status_data() {
+            CommandStatusData::Applied { balance_data } => (true, balance_data),
+            CommandStatusData::Failed { balance_data, .. } => (false, balance_data),
+        };
+        let user_command_type = if signed_command.is_delegation() {
+            UserCommandType::Delegation
+        } else {
+            UserCommandType::Payment
+        };
+
+        let fee_payer_balance = balance_data
+            .fee_payer_balance
+            .map(|balance| balance.t.t.t)?;
+        let source_balance = match balance_data.source_balance {
+            Some(balance) => balance.t.t.t,
+            None if !applied && signed_command.source_pk() == signed_command.fee_payer() => {
+                fee_payer_balance
+            }
+            None => return None,
+        };
+        let receiver = if applied {
+            Some(PostBalance {
+                public_key: signed_command.receiver_pk(),
+                balance: balance_data.receiver_balance?.t.t.t,
+                created: command.receiver_account_creation_fee_paid().is_some(),
+            })
+        } else {
+            None
+        };
+
+        Some(PostBalanceUpdate {
+            source_nonce,
+            command_type: user_command_type,
+            fee_payer: PostBalance {
+                public_key: signed_command.fee_payer(),
+                balance: fee_payer_balance,
+                created: command.fee_payer_account_creation_fee_paid().is_some(),
+            },
+            source: PostBalance {
+                public_key: signed_command.source_pk(),
+                balance: source_balance,
+                created: false,
+            },
+            receiver,
+        })
+    }
+}
+
+impl PostBalance {
+    /// Post balances of the block's coinbase and fee transfer receivers, in the
+    /// order they're applied. Receivers may be new to the ledger.
+    pub fn from_internal_commands(precomputed_block: &PrecomputedBlock) -> Vec<Self> {
+        let coinbase = Coinbase::from_precomputed_block(precomputed_block);
+        let mut coinbase_fee_transfer_receivers = coinbase
+            .fee_transfers
             .iter()
-            .map(|command| UserCommandWithStatus(command.clone()))
-            .flat_map(|command| {
-                let signed_command = SignedCommand::from_user_command(command.clone());
-                let source_nonce = signed_command.source_nonce();
-                if let CommandStatusData::Applied { balance_data } = command.status_data() {
-                    let delegation = signed_command.is_delegation();
-                    let fee_payer = signed_command.fee_payer();
-                    let source = signed_command.source_pk();
-                    let receiver = signed_command.receiver_pk();
+            .map(|fee_transfer| fee_transfer.receiver.clone());
 
-                    let fee_payer_balance =
-                        balance_data.fee_payer_balance.map(|balance| balance.t.t.t);
-                    let receiver_balance =
-                        balance_data.receiver_balance.map(|balance| balance.t.t.t);
-                    let source_balance = balance_data.source_balance.map(|balance| balance.t.t.t);
+        // fee transfers are merged by receiver and paired up in increasing
+        // compressed public key order
+        let mut fee_transfer_receivers: Vec<PublicKey> = vec![];
+        for fee_transfer in FeeTransfer::from_precomputed_block(precomputed_block) {
+            if !fee_transfer_receivers.contains(&fee_transfer.receiver) {
+                fee_transfer_receivers.push(fee_transfer.receiver);
+            }
+        }
+        fee_transfer_receivers.sort_by_key(|receiver| {
+            let compressed = CompressedPubKey::from(receiver);
+            (compressed.x, compressed.is_odd)
+        });
+        let mut fee_transfer_receivers = fee_transfer_receivers.into_iter();
 
-                    if let (Some(fee_payer_balance), Some(receiver_balance), Some(source_balance)) =
-                        (fee_payer_balance, receiver_balance, source_balance)
-                    {
-                        let user_command_type = if delegation {
-                            UserCommandType::Delegation
-                        } else {
-                            UserCommandType::Payment
-                        };
-                        Some(PostBalanceUpdate {
-                            source_nonce,
-                            command_type: user_command_type,
-                            fee_payer: PostBalance {
-                                public_key: fee_payer,
-                                balance: fee_payer_balance,
-                            },
-                            source: PostBalance {
-                                public_key: source,
-                                balance: source_balance,
-                            },
-                            receiver: PostBalance {
-                                public_key: receiver,
-                                balance: receiver_balance,
-                            },
-                        })
-                    } else {
-                        None
+        let mut post_balances = vec![];
+        let mut push = |public_key: Option<PublicKey>, balance: u64| {
+            if let Some(public_key) = public_key {
+                post_balances.push(PostBalance {
+                    public_key,
+                    balance,
+                    created: true,
+                });
+            }
+        };
+        for balance_data in precomputed_block.internal_command_balances() {
+            match balance_data {
+                InternalCommandBalanceData::CoinBase(balances) => {
+                    let balances = balances.t;
+                    push(
+                        Some(coinbase.receiver.clone()),
+                        balances.coinbase_receiver_balance.t.t.t,
+                    );
+                    if let Some(balance) = balances.fee_transfer_receiver_balance {
+                        push(coinbase_fee_transfer_receivers.next(), balance.t.t.t);
                     }
-                } else {
-                    None
                 }
-            })
-            .collect()
+                InternalCommandBalanceData::FeeTransfer(balances) => {
+                    let balances = balances.t;
+                    push(
+                        fee_transfer_receivers.next(),
+                        balances.receiver1_balance.t.t.t,
+                    );
+                    if let Some(balance) = balances.receiver2_balance {
+                        push(fee_transfer_receivers.next(), balance.t.t.t);
+                    }
+                }
+            }
+        }
+        post_balances
     }
 }
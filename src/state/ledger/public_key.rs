@@ -59,6 +59,12 @@ impl From<PublicKey> for PublicKeyV1 {
     }
 }
 
+impl From<&PublicKey> for CompressedPubKey {
+    fn from(value: &PublicKey) -> Self {
+        CompressedPubKey::from(&value.0)
+    }
+}
+
 impl From<PublicKey> for PubKey {
     fn from(value: PublicKey) -> Self {
         PubKey::from_address(&CompressedPubKey::from(&value.0).into_address()).unwrap()
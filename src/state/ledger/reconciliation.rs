@@ -0,0 +1,163 @@
+use super::{
+    account::{nanomina_to_mina, Account},
+    diff::LedgerDiff,
+    post_balances::BlockPostBalances,
+    public_key::PublicKey,
+    Ledger,
+};
+use crate::{block::precomputed::PrecomputedBlock, network::NetworkProfile};
+use serde::Serialize;
+
+/// An account on which the two ledger update paths disagree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccountDiscrepancy {
+    pub public_key: String,
+    pub field: &'static str,
+    /// Value computed by applying the block's ledger diff
+    pub diff_ledger: Option<String>,
+    /// Value computed by applying the block's post balances
+    pub post_balance_ledger: Option<String>,
+}
+
+/// Discrepancies between the two ledger update paths for a block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlockReconciliation {
+    pub blockchain_length: u32,
+    pub state_hash: String,
+    pub discrepancies: Vec<AccountDiscrepancy>,
+    /// Set if the post balances couldn't be applied at all
+    pub error: Option<String>,
+}
+
+impl BlockReconciliation {
+    /// Applies the block to the accounts it touches in `ledger`, once as its full
+    /// ledger diff and once from its post balances, and compares them
+    ///
+    /// Both cover user commands, fee transfers and the coinbase, including the
+    /// accounts they create. `ledger` isn't modified.
+    pub fn new(ledger: &Ledger, block: &PrecomputedBlock, network: &NetworkProfile) -> Self {
+        let ledger_diff = LedgerDiff::from_precomputed_block(block, network);
+        let post_balances = BlockPostBalances::from_precomputed(block);
+        let public_keys = touched_public_keys(&ledger_diff, &post_balances);
+
+        let mut touched = Ledger::new();
+        for public_key in public_keys.iter() {
+            if let Some(account) = ledger.accounts.get(public_key) {
+                touched.accounts.insert(public_key.clone(), account.clone());
+            }
+        }
+
+        let mut reconciliation = Self {
+            blockchain_length: block.blockchain_length,
+            state_hash: block.state_hash.clone(),
+            discrepancies: vec![],
+            error: None,
+        };
+
+        let mut diff_ledger = touched.clone();
+        if let Err(e) = diff_ledger.apply_diff(&ledger_diff) {
+            reconciliation.error = Some(e.to_string());
+            return reconciliation;
+        }
+
+        let mut post_balance_ledger = touched;
+        if let Err(e) = post_balance_ledger.apply_block_post_balances(&post_balances) {
+            reconciliation.error = Some(e.to_string());
+            return reconciliation;
+        }
+
+        for public_key in public_keys.iter() {
+            reconciliation.discrepancies.append(&mut compare_accounts(
+                public_key,
+                diff_ledger.accounts.get(public_key),
+                post_balance_ledger.accounts.get(public_key),
+            ));
+        }
+        reconciliation
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.discrepancies.is_empty() && self.error.is_none()
+    }
+}
+
+/// Accounts touched by either update path, in order of appearance
+fn touched_public_keys(
+    ledger_diff: &LedgerDiff,
+    post_balances: &BlockPostBalances,
+) -> Vec<PublicKey> {
+    let post_balance_keys = post_balances.user_commands.iter().flat_map(|update| {
+        [&update.fee_payer, &update.source]
+            .into_iter()
+            .chain(update.receiver.as_ref())
+            .map(|post_balance| post_balance.public_key.clone())
+    });
+    let internal_command_keys = post_balances
+        .internal_commands
+        .iter()
+        .map(|post_balance| post_balance.public_key.clone());
+
+    let mut public_keys = vec![];
+    for public_key in ledger_diff
+        .public_keys_seen
+        .iter()
+        .cloned()
+        .chain(post_balance_keys)
+        .chain(internal_command_keys)
+    {
+        if !public_keys.contains(&public_key) {
+            public_keys.push(public_key);
+        }
+    }
+    public_keys
+}
+
+fn compare_accounts(
+    public_key: &PublicKey,
+    diff_account: Option<&Account>,
+    post_balance_account: Option<&Account>,
+) -> Vec<AccountDiscrepancy> {
+    let discrepancy = |field, diff_ledger, post_balance_ledger| AccountDiscrepancy {
+        public_key: public_key.to_address(),
+        field,
+        diff_ledger,
+        post_balance_ledger,
+    };
+
+    match (diff_account, post_balance_account) {
+        (None, None) => vec![],
+        (Some(_), None) | (None, Some(_)) => vec![discrepancy(
+            "account",
+            diff_account.map(|_| "present".to_string()),
+            post_balance_account.map(|_| "present".to_string()),
+        )],
+        (Some(diff_account), Some(post_balance_account)) => {
+            let mut discrepancies = vec![];
+            if diff_account.balance != post_balance_account.balance {
+                discrepancies.push(discrepancy(
+                    "balance",
+                    Some(nanomina_to_mina(diff_account.balance.0)),
+                    Some(nanomina_to_mina(post_balance_account.balance.0)),
+                ));
+            }
+            if diff_account.nonce != post_balance_account.nonce {
+                discrepancies.push(discrepancy(
+                    "nonce",
+                    Some(diff_account.nonce.0.to_string()),
+                    Some(post_balance_account.nonce.0.to_string()),
+                ));
+            }
+            if diff_account.delegate != post_balance_account.delegate {
+                discrepancies.push(discrepancy(
+                    "delegate",
+                    diff_account.delegate.as_ref().map(PublicKey::to_address),
+                    post_balance_account
+                        .delegate
+                        .as_ref()
+                        .map(PublicKey::to_address),
+                ));
+            }
+            discrepancies
+        }
+    }
+}
@@ -1,12 +1,45 @@
 use crate::block::BlockHash;
 
-use super::Ledger;
+use super::{post_balances::BlockPostBalances, Ledger};
 
-/// Store of canonical and epoch boundary ledgers
+/// Store of ledger checkpoints and per-block post balances
 pub trait LedgerStore {
-    /// Add ledger associated with a canonical block
+    /// Add a full ledger checkpoint associated with a block
     fn add_ledger(&self, state_hash: &BlockHash, ledger: Ledger) -> anyhow::Result<()>;
 
-    /// Get a ledger associated with an arbitrary block
+    /// Add a block's post balances, applied on top of its parent's ledger
+    fn add_post_balances(
+        &self,
+        state_hash: &BlockHash,
+        parent_hash: &BlockHash,
+        post_balances: &BlockPostBalances,
+    ) -> anyhow::Result<()>;
+
+    /// Get the ledger associated with an arbitrary block,
+    /// rebuilt from the nearest checkpoint below it
     fn get_ledger(&self, state_hash: &BlockHash) -> anyhow::Result<Option<Ledger>>;
+
+    /// Index a canonical block by its blockchain length and global slot,
+    /// marking it canonical
+    fn add_canonical_block(
+        &self,
+        blockchain_length: u32,
+        global_slot: u32,
+        state_hash: &BlockHash,
+    ) -> anyhow::Result<()>;
+
+    /// Get the state hash of the canonical block with the given blockchain length
+    fn get_canonical_hash_at_height(
+        &self,
+        blockchain_length: u32,
+    ) -> anyhow::Result<Option<BlockHash>>;
+
+    /// Get the ledger of the canonical block with the given blockchain length
+    fn get_ledger_at_height(&self, blockchain_length: u32) -> anyhow::Result<Option<Ledger>>;
+
+    /// Get the ledger of the last canonical block at or before the given global slot
+    fn get_ledger_at_slot(&self, global_slot: u32) -> anyhow::Result<Option<Ledger>>;
+
+    /// Get the blockchain length of the highest canonical block
+    fn get_max_canonical_height(&self) -> anyhow::Result<Option<u32>>;
 }
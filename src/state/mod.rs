@@ -1,37 +1,44 @@
 use self::{
-    snapshot::{StateSnapshot, StateStore},
+    snapshot::{StateSnapshot, StateStore, STATE_SNAPSHOT_VERSION},
     summary::{
         DbStats, SummaryShort, SummaryVerbose, WitnessTreeSummaryShort, WitnessTreeSummaryVerbose,
     },
 };
 use crate::{
     block::{
-        parser::BlockParser, precomputed::PrecomputedBlock, store::BlockStore, Block, BlockHash,
-        BlockWithoutHeight,
+        parser::BlockParser, precomputed::PrecomputedBlock, signed_command::SignedCommand,
+        store::BlockStore, Block, BlockHash, BlockWithoutHeight,
     },
     display_duration,
+    network::NetworkProfile,
+    staking_ledger::{staking_ledger_store::StakingLedgerStore, StakingLedger},
     state::{
         branch::Branch,
         ledger::{
-            command::Command, diff::LedgerDiff, genesis::GenesisLedger, store::LedgerStore, Ledger,
+            command::{Command, UserCommandWithStatus},
+            diff::LedgerDiff,
+            genesis::GenesisLedger,
+            store::LedgerStore,
+            Ledger,
         },
     },
-    store::IndexerStore,
+    store::{block_index_key, IndexerStore},
     BLOCK_REPORTING_FREQ_NUM, BLOCK_REPORTING_FREQ_SEC, CANONICAL_UPDATE_THRESHOLD,
-    MAINNET_CANONICAL_THRESHOLD, MAINNET_TRANSITION_FRONTIER_K, PRUNE_INTERVAL_DEFAULT,
+    LEDGER_CHECKPOINT_INTERVAL, MAINNET_CANONICAL_THRESHOLD, MAINNET_TRANSITION_FRONTIER_K,
+    PRUNE_INTERVAL_DEFAULT,
 };
 use anyhow::anyhow;
 use id_tree::NodeId;
 use serde_derive::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     process,
     str::FromStr,
     sync::Arc,
     time::{Duration, Instant},
 };
 use time::{format_description, OffsetDateTime};
-use tracing::{debug, error, info, instrument, trace};
+use tracing::{debug, error, info, instrument, trace, warn};
 use uuid::Uuid;
 
 pub mod branch;
@@ -59,12 +66,24 @@ pub struct IndexerState {
     pub dangling_branches: Vec<Branch>,
     /// Block database
     pub indexer_store: Option<Arc<IndexerStore>>,
+    /// Protocol constants of the network being indexed
+    pub network: NetworkProfile,
     /// Threshold amount of confirmations to trigger a pruning event
     pub transition_frontier_length: u32,
+    /// Number of confirmations after which a block is considered canonical
+    pub canonical_threshold: u32,
     /// Interval to the prune the root branch
     pub prune_interval: u32,
     /// Threshold for updating the canonical tip and db ledger
     pub canonical_update_threshold: u32,
+    /// Interval (in blockchain length) between full ledger checkpoints in the db
+    pub ledger_checkpoint_interval: u32,
+    /// Check canonical ledgers' Merkle roots against the hashes their blocks commit to
+    pub verify_ledger_hashes: bool,
+    /// Check the signatures of ingested blocks' user commands
+    pub verify_signatures: bool,
+    /// Best tip ledger with its state hash, so it's only rebuilt when the best tip moves
+    pub best_ledger_cache: Option<(BlockHash, Arc<Ledger>)>,
     /// Number of blocks added to the state
     pub blocks_processed: u32,
     /// Datetime the indexer started running
@@ -110,19 +129,24 @@ pub enum Canonicity {
 
 impl IndexerState {
     /// Creates a new indexer state from the genesis ledger
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         root_hash: BlockHash,
         genesis_ledger: GenesisLedger,
         indexer_store: Arc<IndexerStore>,
-        transition_frontier_length: u32,
+        network: NetworkProfile,
+        canonical_threshold: u32,
         prune_interval: u32,
         canonical_update_threshold: u32,
     ) -> anyhow::Result<Self> {
         let root_branch = Branch::new_genesis(root_hash.clone());
 
         indexer_store
-            .add_ledger(&root_hash, genesis_ledger.into())
+            .add_ledger(&root_hash, genesis_ledger.try_into()?)
             .expect("ledger add succeeds");
+        indexer_store
+            .add_canonical_block(1, 0, &root_hash)
+            .expect("canonical block add succeeds");
 
         let tip = Tip {
             state_hash: root_branch.root_block().state_hash.clone(),
@@ -137,9 +161,15 @@ impl IndexerState {
             root_branch,
             dangling_branches: Vec::new(),
             indexer_store: Some(indexer_store),
-            transition_frontier_length,
+            transition_frontier_length: network.k,
+            canonical_threshold,
+            network,
             prune_interval,
             canonical_update_threshold,
+            ledger_checkpoint_interval: LEDGER_CHECKPOINT_INTERVAL,
+            verify_ledger_hashes: false,
+            verify_signatures: false,
+            best_ledger_cache: None,
             blocks_processed: 0,
             init_time: Instant::now(),
         })
@@ -153,7 +183,8 @@ impl IndexerState {
         blockchain_length: u32,
         global_slot_since_genesis: u32,
         indexer_store: Arc<IndexerStore>,
-        transition_frontier_length: u32,
+        network: NetworkProfile,
+        canonical_threshold: u32,
         prune_interval: u32,
         canonical_update_threshold: u32,
     ) -> anyhow::Result<Self> {
@@ -166,6 +197,9 @@ impl IndexerState {
         indexer_store
             .add_ledger(&root_hash, ledger)
             .expect("ledger add succeeds");
+        indexer_store
+            .add_canonical_block(blockchain_length, global_slot_since_genesis, &root_hash)
+            .expect("canonical block add succeeds");
 
         let tip = Tip {
             state_hash: root_branch.root_block().state_hash.clone(),
@@ -180,9 +214,15 @@ impl IndexerState {
             root_branch,
             dangling_branches: Vec::new(),
             indexer_store: Some(indexer_store),
-            transition_frontier_length,
+            transition_frontier_length: network.k,
+            canonical_threshold,
+            network,
             prune_interval,
             canonical_update_threshold,
+            ledger_checkpoint_interval: LEDGER_CHECKPOINT_INTERVAL,
+            verify_ledger_hashes: false,
+            verify_signatures: false,
+            best_ledger_cache: None,
             blocks_processed: 0,
             init_time: Instant::now(),
         })
@@ -199,9 +239,17 @@ impl IndexerState {
         let indexer_store = rocksdb_path.map(|path| {
             let store = IndexerStore::new(path).unwrap();
             if let Some(ledger) = root_ledger {
+                let root_hash = BlockHash(root_block.state_hash.clone());
                 store
-                    .add_ledger(&BlockHash(root_block.state_hash.clone()), ledger)
+                    .add_ledger(&root_hash, ledger)
                     .expect("ledger add succeeds");
+                store
+                    .add_canonical_block(
+                        root_block.blockchain_length,
+                        root_block.global_slot_since_genesis(),
+                        &root_hash,
+                    )
+                    .expect("canonical block add succeeds");
             }
             store
         });
@@ -219,10 +267,16 @@ impl IndexerState {
             root_branch,
             dangling_branches: Vec::new(),
             indexer_store: indexer_store.map(Arc::new),
+            network: NetworkProfile::mainnet(),
             transition_frontier_length: transition_frontier_length
                 .unwrap_or(MAINNET_TRANSITION_FRONTIER_K),
+            canonical_threshold: MAINNET_CANONICAL_THRESHOLD,
             prune_interval: PRUNE_INTERVAL_DEFAULT,
             canonical_update_threshold: CANONICAL_UPDATE_THRESHOLD,
+            ledger_checkpoint_interval: LEDGER_CHECKPOINT_INTERVAL,
+            verify_ledger_hashes: false,
+            verify_signatures: false,
+            best_ledger_cache: None,
             blocks_processed: 0,
             init_time: Instant::now(),
         })
@@ -261,61 +315,91 @@ impl IndexerState {
         Ok(())
     }
 
+    /// Writes the state snapshot to the db so the next startup can resume from it
+    pub fn persist_state_snapshot(&self) -> anyhow::Result<()> {
+        if let Some(indexer_store) = self.indexer_store.as_ref() {
+            indexer_store.store_state_snapshot(&self.to_state_snapshot())?;
+        }
+        Ok(())
+    }
+
     pub fn to_state_snapshot(&self) -> StateSnapshot {
         StateSnapshot {
+            version: STATE_SNAPSHOT_VERSION,
+            network: self.network.network,
+            genesis_hash: self.network.genesis_hash.clone(),
             root_branch: self.root_branch.clone(),
+            dangling_branches: self.dangling_branches.clone(),
             diffs_map: self.diffs_map.clone(),
+            best_tip: self.best_tip.state_hash.clone(),
+            canonical_tip: self.canonical_tip.state_hash.clone(),
+            blocks_processed: self.blocks_processed,
         }
     }
 
     #[instrument]
     pub fn from_state_snapshot(
         indexer_store: Arc<IndexerStore>,
-        transition_frontier_length: u32,
+        network: NetworkProfile,
+        canonical_threshold: u32,
         prune_interval: u32,
         canonical_update_threshold: u32,
     ) -> anyhow::Result<Self> {
         if let Some(snapshot) = indexer_store.read_snapshot()? {
             let mut snapshot = snapshot;
+            if snapshot.network != network.network || snapshot.genesis_hash != network.genesis_hash
+            {
+                anyhow::bail!(
+                    "State snapshot of the {} network (genesis hash {:?}) doesn't match the configured {} network (genesis hash {:?})",
+                    snapshot.network,
+                    snapshot.genesis_hash,
+                    network.network,
+                    network.genesis_hash,
+                );
+            }
+
+            // node ids are not preserved across serialization
             snapshot.root_branch.root = snapshot
                 .root_branch
                 .branches
                 .root_node_id()
                 .expect("root node id exists")
                 .clone();
+            for dangling_branch in snapshot.dangling_branches.iter_mut() {
+                dangling_branch.root = dangling_branch
+                    .branches
+                    .root_node_id()
+                    .expect("root node id exists")
+                    .clone();
+            }
 
             trace!("snapshot root brach: {:?}", snapshot.root_branch);
 
-            let best_tip_id = snapshot.root_branch.best_tip_id();
+            let best_tip_id = match snapshot.root_branch.node_id(&snapshot.best_tip) {
+                Some(best_tip_id) => best_tip_id,
+                None => {
+                    error!("best tip {:?} not in root branch!", snapshot.best_tip);
+                    process::exit(100);
+                }
+            };
             let best_tip = Tip {
-                state_hash: snapshot
-                    .root_branch
-                    .branches
-                    .get(&best_tip_id)
-                    .expect("best_tip_id guaranteed")
-                    .data()
-                    .state_hash
-                    .clone(),
+                state_hash: snapshot.best_tip,
                 node_id: best_tip_id,
             };
             trace!("read best tip {:?}", best_tip.state_hash);
 
-            let canonical_tip_id =
-                if let Some(canonical_tip_id) = snapshot.root_branch.canonical_tip_id() {
-                    canonical_tip_id
-                } else {
-                    error!("branch has no canonical tip!");
+            let canonical_tip_id = match snapshot.root_branch.node_id(&snapshot.canonical_tip) {
+                Some(canonical_tip_id) => canonical_tip_id,
+                None => {
+                    error!(
+                        "canonical tip {:?} not in root branch!",
+                        snapshot.canonical_tip
+                    );
                     process::exit(100);
-                };
+                }
+            };
             let canonical_tip = Tip {
-                state_hash: snapshot
-                    .root_branch
-                    .branches
-                    .get(&canonical_tip_id)
-                    .expect("canonical_tip_id guaranteed")
-                    .data()
-                    .state_hash
-                    .clone(),
+                state_hash: snapshot.canonical_tip,
                 node_id: canonical_tip_id,
             };
             trace!("read canonical tip {:?}", canonical_tip.state_hash);
@@ -326,12 +410,18 @@ impl IndexerState {
                 canonical_tip,
                 diffs_map: snapshot.diffs_map,
                 root_branch: snapshot.root_branch,
-                dangling_branches: Vec::new(),
+                dangling_branches: snapshot.dangling_branches,
                 indexer_store: Some(indexer_store),
-                transition_frontier_length,
+                transition_frontier_length: network.k,
+                canonical_threshold,
+                network,
                 prune_interval,
                 canonical_update_threshold,
-                blocks_processed: 0,
+                ledger_checkpoint_interval: LEDGER_CHECKPOINT_INTERVAL,
+                verify_ledger_hashes: false,
+                verify_signatures: false,
+                best_ledger_cache: None,
+                blocks_processed: snapshot.blocks_processed,
                 init_time: Instant::now(),
             })
         } else {
@@ -380,9 +470,8 @@ impl IndexerState {
         if self.best_tip_block().height - self.canonical_tip_block().height
             > self.canonical_update_threshold
         {
-            let mut canonical_hashes = vec![];
+            let mut canonical_blocks = vec![];
             let old_canonical_tip_id = self.canonical_tip.node_id.clone();
-            let old_canonical_tip_hash = self.canonical_tip_block().state_hash.clone();
 
             // update canonical_tip
             for (n, ancestor_id) in self
@@ -392,75 +481,199 @@ impl IndexerState {
                 .unwrap()
                 .enumerate()
             {
-                // only add blocks between the old_canonical_tip and the new one
-                if n + 1 == MAINNET_CANONICAL_THRESHOLD as usize {
-                    self.canonical_tip.node_id = ancestor_id.clone();
-                    self.canonical_tip.state_hash =
-                        self.get_block_from_id(ancestor_id).state_hash.clone();
-                } else if n > MAINNET_CANONICAL_THRESHOLD as usize
-                    && ancestor_id != &old_canonical_tip_id
-                {
-                    let ancestor_block = self.get_block_from_id(ancestor_id);
-                    canonical_hashes.push(ancestor_block.state_hash.clone());
-                } else if ancestor_id == &old_canonical_tip_id {
+                if ancestor_id == &old_canonical_tip_id {
                     break;
                 }
+
+                // only add blocks between the old_canonical_tip and the new one
+                if n + 1 >= self.canonical_threshold as usize {
+                    if n + 1 == self.canonical_threshold as usize {
+                        self.canonical_tip.node_id = ancestor_id.clone();
+                        self.canonical_tip.state_hash =
+                            self.get_block_from_id(ancestor_id).state_hash.clone();
+                    }
+                    canonical_blocks.push(self.get_block_from_id(ancestor_id).clone());
+                }
+            }
+
+            if canonical_blocks.is_empty() {
+                return Ok(());
             }
 
-            canonical_hashes.reverse();
+            canonical_blocks.reverse();
+            let canonical_hashes: Vec<BlockHash> = canonical_blocks
+                .iter()
+                .map(|block| block.state_hash.clone())
+                .collect();
 
-            // update canonical ledger
+            // index the new canonical blocks, checkpoint the canonical ledger
+            // and derive staking ledgers at epoch boundaries
             if let Some(indexer_store) = &self.indexer_store {
-                let mut ledger = indexer_store
-                    .get_ledger(&old_canonical_tip_hash)
-                    .unwrap()
-                    .unwrap();
-
-                // apply the new canonical diffs to the old canonical ledger
-                for canonical_hash in &canonical_hashes {
-                    if let Some(precomputed_block) = indexer_store.get_block(canonical_hash)? {
-                        ledger.apply_post_balances(&precomputed_block);
+                let mut parent_global_slot = self
+                    .get_block_from_id(&old_canonical_tip_id)
+                    .global_slot_since_genesis;
+                for canonical_block in &canonical_blocks {
+                    if self.is_epoch_boundary(
+                        parent_global_slot,
+                        canonical_block.global_slot_since_genesis,
+                    ) {
+                        if let (Some(precomputed_block), Some(parent_ledger)) = (
+                            indexer_store.get_block(&canonical_block.state_hash)?,
+                            indexer_store.get_ledger(&canonical_block.parent_hash)?,
+                        ) {
+                            self.add_staking_ledger(
+                                indexer_store,
+                                &precomputed_block,
+                                &parent_ledger,
+                            )?;
+                        }
                     }
-                }
+                    parent_global_slot = canonical_block.global_slot_since_genesis;
 
-                // update the ledger
-                indexer_store
-                    .add_ledger(&self.canonical_tip_block().state_hash, ledger)
-                    .unwrap();
-            }
+                    indexer_store.add_canonical_block(
+                        canonical_block.blockchain_length,
+                        canonical_block.global_slot_since_genesis,
+                        &canonical_block.state_hash,
+                    )?;
 
-            // update canonicity store
-            for block_hash in self.diffs_map.keys() {
-                if let Some(indexer_store) = &self.indexer_store {
-                    if canonical_hashes.contains(block_hash) {
-                        indexer_store
-                            .set_canonicity(block_hash, Canonicity::Canonical)
-                            .unwrap();
-                    } else {
-                        indexer_store
-                            .set_canonicity(block_hash, Canonicity::Orphaned)
-                            .unwrap();
+                    if self.verify_ledger_hashes {
+                        self.verify_ledger_hash(indexer_store, &canonical_block.state_hash)?;
+                    }
+
+                    if canonical_block.blockchain_length % self.ledger_checkpoint_interval == 0 {
+                        if let Some(ledger) =
+                            indexer_store.get_ledger(&canonical_block.state_hash)?
+                        {
+                            indexer_store.add_ledger(&canonical_block.state_hash, ledger)?;
+                        }
                     }
                 }
             }
 
-            // remove diffs corresponding to blocks at or beneath the height of the new canonical tip
-            for node_id in self
+            // blocks at or beneath the height of the new canonical tip are now either
+            // canonical or orphaned, blocks above it remain pending
+            let canonical_tip_height = self.canonical_tip_block().height;
+            let decided_hashes: Vec<BlockHash> = self
                 .root_branch
                 .branches
                 .traverse_level_order_ids(&old_canonical_tip_id)
                 .unwrap()
-            {
-                if self.get_block_from_id(&node_id).height <= self.canonical_tip_block().height {
-                    self.diffs_map
-                        .remove(&self.get_block_from_id(&node_id).state_hash.clone());
+                .filter(|node_id| *node_id != old_canonical_tip_id)
+                .map(|node_id| self.get_block_from_id(&node_id).clone())
+                .filter(|block| block.height <= canonical_tip_height)
+                .map(|block| block.state_hash)
+                .collect();
+
+            for block_hash in decided_hashes {
+                // canonical blocks were marked when indexed above
+                if let Some(indexer_store) = &self.indexer_store {
+                    if !canonical_hashes.contains(&block_hash) {
+                        indexer_store.set_canonicity(&block_hash, Canonicity::Orphaned)?;
+                    }
                 }
+
+                // remove diffs corresponding to decided blocks
+                self.diffs_map.remove(&block_hash);
             }
         }
 
         Ok(())
     }
 
+    /// Logs a warning if the canonical ledger at `state_hash` doesn't hash to
+    /// the staged or snarked ledger hash the block commits to
+    fn verify_ledger_hash(
+        &self,
+        indexer_store: &IndexerStore,
+        state_hash: &BlockHash,
+    ) -> anyhow::Result<()> {
+        if let (Some(precomputed_block), Some(ledger)) = (
+            indexer_store.get_block(state_hash)?,
+            indexer_store.get_ledger(state_hash)?,
+        ) {
+            match ledger.verify_hash(&precomputed_block, self.network.ledger_depth) {
+                Ok(ledger_hash) => debug!(
+                    "Verified ledger {} of canonical block {}",
+                    ledger_hash.0, state_hash.0
+                ),
+                Err(e) => warn!(
+                    "Canonical block {} (length {}): {e}",
+                    state_hash.0, precomputed_block.blockchain_length
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Hashes of the block's user commands whose signatures don't verify,
+    /// empty if signature verification is off
+    fn invalid_signatures(&self, block: &PrecomputedBlock) -> anyhow::Result<Vec<String>> {
+        if !self.verify_signatures {
+            return Ok(vec![]);
+        }
+
+        let invalid_tx_hashes = block
+            .commands()
+            .into_iter()
+            .map(|command| SignedCommand::from_user_command(UserCommandWithStatus(command)))
+            .filter(|signed_command| !signed_command.verify(self.network.network))
+            .map(|signed_command| signed_command.hash_signed_command())
+            .collect::<anyhow::Result<Vec<String>>>()?;
+
+        if !invalid_tx_hashes.is_empty() {
+            warn!(
+                "Block {} (length {}) has user commands with invalid signatures: {invalid_tx_hashes:?}",
+                block.state_hash, block.blockchain_length
+            );
+        }
+        Ok(invalid_tx_hashes)
+    }
+
+    /// Whether a block at `global_slot` is the first of its epoch, given its parent's slot
+    fn is_epoch_boundary(&self, parent_global_slot: u32, global_slot: u32) -> bool {
+        global_slot / self.network.slots_per_epoch
+            > parent_global_slot / self.network.slots_per_epoch
+    }
+
+    /// Stores the staking ledger of the epoch after the one `block` starts
+    ///
+    /// The first block of epoch `e` fixes its parent's snarked ledger as the
+    /// staking ledger of epoch `e + 1`. The indexer only tracks staged ledgers,
+    /// so the parent's staged ledger stands in for the snarked one. It's stored
+    /// as derived, labeled with its own Merkle root, unless the official ledger
+    /// of the epoch was already ingested. A warning is logged if the root isn't
+    /// the snarked ledger hash the block commits to.
+    fn add_staking_ledger(
+        &self,
+        indexer_store: &IndexerStore,
+        block: &PrecomputedBlock,
+        parent_ledger: &Ledger,
+    ) -> anyhow::Result<()> {
+        let epoch = block.global_slot_since_genesis() / self.network.slots_per_epoch + 1;
+
+        // an ingested official ledger takes precedence
+        if let Some(staking_ledger) = indexer_store.get_epoch(epoch)? {
+            if !staking_ledger.derived {
+                return Ok(());
+            }
+        }
+
+        debug!(
+            "Deriving staking ledger of epoch {epoch} from the parent of block {}",
+            block.state_hash
+        );
+        let staking_ledger =
+            StakingLedger::from_ledger(epoch, parent_ledger, self.network.ledger_depth);
+        let chain_ledger_hash = block.next_epoch_ledger_hash();
+        if staking_ledger.ledger_hash != chain_ledger_hash.0 {
+            warn!(
+                "Derived staking ledger of epoch {epoch} hashes to {}, block {} commits to {}",
+                staking_ledger.ledger_hash, block.state_hash, chain_ledger_hash.0
+            );
+        }
+        indexer_store.add_epoch(epoch, &staking_ledger)
+    }
+
     /// Initialize indexer state from a collection of contiguous canonical blocks
     pub async fn initialize_with_contiguous_canonical(
         &mut self,
@@ -470,6 +683,7 @@ impl IndexerState {
             let mut ledger = indexer_store
                 .get_ledger(&self.canonical_tip.state_hash)?
                 .unwrap();
+            let mut parent_global_slot = self.canonical_tip_block().global_slot_since_genesis;
             let total_time = Instant::now();
 
             if block_parser.num_canonical > BLOCK_REPORTING_FREQ_NUM {
@@ -498,24 +712,27 @@ impl IndexerState {
                 }
 
                 let precomputed_block = block_parser.next().await?.unwrap();
+                let global_slot = precomputed_block.global_slot_since_genesis();
 
-                // apply and add to db
-                ledger.apply_post_balances(&precomputed_block);
-                indexer_store.add_block(&precomputed_block)?;
-
-                let timestamp = precomputed_block.timestamp();
-
-                for cmd in precomputed_block.commands() {
-                    indexer_store.put_tx(precomputed_block.blockchain_length, timestamp, cmd)?;
+                // the running ledger is the parent's until the block is applied
+                if self.is_epoch_boundary(parent_global_slot, global_slot) {
+                    self.add_staking_ledger(indexer_store, &precomputed_block, &ledger)?;
                 }
+                parent_global_slot = global_slot;
 
-                // TODO: store ledger at specified cadence, e.g. at epoch boundaries
-                // for now, just store every 1000 blocks
-                if self.blocks_processed % 1000 == 0 {
-                    indexer_store.add_ledger(
-                        &BlockHash(precomputed_block.state_hash.clone()),
-                        ledger.clone(),
-                    )?;
+                // apply and add to db
+                let state_hash = BlockHash(precomputed_block.state_hash.clone());
+                ledger.apply_post_balances(&precomputed_block)?;
+                indexer_store.ingest_block(
+                    &precomputed_block,
+                    &self.network,
+                    true,
+                    &self.invalid_signatures(&precomputed_block)?,
+                )?;
+
+                // checkpoint the ledger at the configured interval
+                if precomputed_block.blockchain_length % self.ledger_checkpoint_interval == 0 {
+                    indexer_store.add_ledger(&state_hash, ledger.clone())?;
                 }
 
                 if self.blocks_processed == block_parser.num_canonical {
@@ -545,6 +762,127 @@ impl IndexerState {
         self.add_blocks(block_parser).await
     }
 
+    /// Catches a state restored from the db up with the blocks in `block_parser`
+    ///
+    /// Blocks ingested into the db after the state snapshot was written are
+    /// missing from the witness tree, so they're added to it without being
+    /// written to the db again
+    pub async fn catch_up(&mut self, block_parser: &mut BlockParser) -> anyhow::Result<u32> {
+        let total_time = Instant::now();
+        let mut num_added = 0;
+
+        while let Some(block) = block_parser.next().await? {
+            if self.is_block_in_witness_tree(&block) {
+                continue;
+            }
+
+            // blocks ingested after the snapshot only need to rejoin the witness tree
+            self.prune_root_branch()?;
+            let extension = if self.is_block_already_in_db(&block)? {
+                let ledger_diff = LedgerDiff::from_precomputed_block(&block, &self.network);
+                self.extend_witness_tree(&block, ledger_diff)?
+            } else {
+                self.add_block_to_witness_tree(&block)?
+            };
+            if extension != ExtensionType::BlockNotAdded {
+                num_added += 1;
+            }
+        }
+
+        info!(
+            "Caught up with {num_added} blocks in {}",
+            display_duration(total_time.elapsed()),
+        );
+
+        debug!("Phase change: {} -> {}", self.phase, IndexerPhase::Watching);
+        self.phase = IndexerPhase::Watching;
+        Ok(num_added)
+    }
+
+    /// Rebuilds the witness tree from the blocks already in the db, e.g. when
+    /// the db has no state snapshot to restore from
+    ///
+    /// The tree is rooted at the last canonical block, whose ledger the db
+    /// rebuilds from its checkpoints, so only the blocks above it are read
+    ///
+    /// Returns the number of blocks added
+    pub fn rebuild_from_store(&mut self) -> anyhow::Result<u32> {
+        let indexer_store = match self.indexer_store.clone() {
+            Some(indexer_store) => indexer_store,
+            None => return Ok(0),
+        };
+        let total_time = Instant::now();
+        let mut num_added = 0;
+
+        if let Some(canonical_height) = indexer_store.get_max_canonical_height()? {
+            if canonical_height > self.root_branch.root_block().blockchain_length {
+                let canonical_hash = indexer_store
+                    .get_canonical_hash_at_height(canonical_height)?
+                    .expect("canonical block indexed");
+                if let Some(canonical_block) = indexer_store.get_block(&canonical_hash)? {
+                    self.root_branch = Branch::new_non_genesis(
+                        canonical_hash,
+                        canonical_height,
+                        canonical_block.global_slot_since_genesis(),
+                    );
+
+                    let tip = Tip {
+                        state_hash: self.root_branch.root_block().state_hash.clone(),
+                        node_id: self.root_branch.root.clone(),
+                    };
+                    self.canonical_tip = tip.clone();
+                    self.best_tip = tip;
+                    self.diffs_map.clear();
+                    self.dangling_branches.clear();
+                }
+            }
+        }
+
+        // the height index yields blocks in order of blockchain length
+        let root_hash = self.root_branch.root_block().state_hash.clone();
+        let start_key = block_index_key(self.root_branch.root_block().blockchain_length + 1, "");
+        let mut state_hashes = vec![];
+        for entry in indexer_store.iterator_cf_from("blocks-height", &start_key) {
+            let (_, state_hash) = entry?;
+            state_hashes.push(BlockHash(String::from_utf8(state_hash.to_vec())?));
+        }
+
+        // forks off decided blocks beneath the root are orphaned, so they and
+        // their descendants are left out rather than kept as dangling branches
+        let mut orphaned_hashes = HashSet::new();
+        for state_hash in state_hashes {
+            if let Some(block) = indexer_store.get_block(&state_hash)? {
+                if self.is_block_in_witness_tree(&block) {
+                    continue;
+                }
+
+                let parent_hash = BlockHash::previous_state_hash(&block);
+                if orphaned_hashes.contains(&parent_hash)
+                    || parent_hash != root_hash
+                        && matches!(
+                            indexer_store.get_canonicity(&parent_hash)?,
+                            Some(Canonicity::Canonical | Canonicity::Orphaned)
+                        )
+                {
+                    orphaned_hashes.insert(state_hash);
+                    continue;
+                }
+
+                self.prune_root_branch()?;
+                let ledger_diff = LedgerDiff::from_precomputed_block(&block, &self.network);
+                if self.extend_witness_tree(&block, ledger_diff)? != ExtensionType::BlockNotAdded {
+                    num_added += 1;
+                }
+            }
+        }
+
+        info!(
+            "Rebuilt the witness tree from {num_added} blocks in the db in {}",
+            display_duration(total_time.elapsed()),
+        );
+        Ok(num_added)
+    }
+
     /// Adds blocks to the state according to block_parser then changes phase to Watching
     ///
     /// Returns the number of blocks parsed
@@ -618,6 +956,14 @@ impl IndexerState {
             return Ok(ExtensionType::BlockNotAdded);
         }
 
+        self.add_block_to_witness_tree(precomputed_block)
+    }
+
+    /// Adds the block to the witness tree regardless of whether it's already in the db
+    fn add_block_to_witness_tree(
+        &mut self,
+        precomputed_block: &PrecomputedBlock,
+    ) -> anyhow::Result<ExtensionType> {
         let incoming_length = precomputed_block.blockchain_length;
         if self.root_branch.root_block().blockchain_length > incoming_length {
             debug!(
@@ -627,22 +973,29 @@ impl IndexerState {
             return Ok(ExtensionType::BlockNotAdded);
         }
 
-        // add block to the db
+        // add block and its post balances to the db
+        let ledger_diff = LedgerDiff::from_precomputed_block(precomputed_block, &self.network);
         if let Some(indexer_store) = self.indexer_store.as_ref() {
-            indexer_store.add_block(precomputed_block)?;
-
-            let tmstmp = precomputed_block.timestamp();
-
-            for cmd in precomputed_block.commands() {
-                indexer_store.put_tx(precomputed_block.blockchain_length, tmstmp, cmd)?;
-            }
+            indexer_store.ingest_block(
+                precomputed_block,
+                &self.network,
+                false,
+                &self.invalid_signatures(precomputed_block)?,
+            )?;
         }
 
+        self.extend_witness_tree(precomputed_block, ledger_diff)
+    }
+
+    /// Adds the block to the root or a dangling branch without touching the db
+    fn extend_witness_tree(
+        &mut self,
+        precomputed_block: &PrecomputedBlock,
+        ledger_diff: LedgerDiff,
+    ) -> anyhow::Result<ExtensionType> {
+        let state_hash = BlockHash(precomputed_block.state_hash.clone());
         self.blocks_processed += 1;
-        self.diffs_map.insert(
-            BlockHash(precomputed_block.state_hash.clone()),
-            LedgerDiff::from_precomputed_block(precomputed_block),
-        );
+        self.diffs_map.insert(state_hash, ledger_diff);
 
         // forward extension on root branch
         if self.is_length_within_root_bounds(precomputed_block) {
@@ -829,6 +1182,15 @@ impl IndexerState {
         }
     }
 
+    fn is_block_in_witness_tree(&self, precomputed_block: &PrecomputedBlock) -> bool {
+        let state_hash = BlockHash(precomputed_block.state_hash.clone());
+        self.root_branch.mem(&state_hash)
+            || self
+                .dangling_branches
+                .iter()
+                .any(|dangling_branch| dangling_branch.mem(&state_hash))
+    }
+
     /// Update the best tip of the root branch
     fn update_best_tip(&mut self, incoming_block: &Block, node_id: &NodeId) {
         let best_tip_length = self.best_tip_block().blockchain_length;
@@ -870,46 +1232,35 @@ impl IndexerState {
         None
     }
 
-    // TODO: maybe we should add another function for getting a ledger at a specific slot/"height"?
-    pub fn best_ledger(&mut self) -> anyhow::Result<Option<Ledger>> {
+    pub fn best_ledger(&mut self) -> anyhow::Result<Option<Arc<Ledger>>> {
         self.update_canonical()?;
 
-        // get the most recent canonical ledger
-        if let Some(indexer_store) = &self.indexer_store {
-            if let Some(mut ledger) =
-                indexer_store.get_ledger(&self.canonical_tip_block().state_hash)?
-            {
-                // collect diffs from canonical tip to best tip
-                let mut hashes_since_canonical_tip =
-                    if self.best_tip.state_hash != self.canonical_tip.state_hash {
-                        vec![self.best_tip.state_hash.clone()]
-                    } else {
-                        vec![]
-                    };
+        let best_tip = self.best_tip.state_hash.clone();
+        if let Some((state_hash, ledger)) = &self.best_ledger_cache {
+            if *state_hash == best_tip {
+                return Ok(Some(ledger.clone()));
+            }
+        }
 
-                for ancestor in self
-                    .root_branch
-                    .branches
-                    .ancestors(&self.best_tip.node_id)
-                    .unwrap()
+        if let Some(indexer_store) = &self.indexer_store {
+            // extend the cached ledger if it's the best tip's parent's,
+            // otherwise rebuild from the nearest checkpoint below the best tip
+            let ledger = match (
+                self.best_ledger_cache.take(),
+                indexer_store.get_post_balances(&best_tip)?,
+            ) {
+                (Some((state_hash, mut ledger)), Some((parent_hash, post_balances)))
+                    if state_hash == parent_hash =>
                 {
-                    if ancestor.data().state_hash != self.canonical_tip.state_hash {
-                        hashes_since_canonical_tip.push(ancestor.data().state_hash.clone());
-                    } else {
-                        break;
-                    }
-                }
-
-                // apply diffs from canonical tip to best tip
-                hashes_since_canonical_tip.reverse();
-                for hash in hashes_since_canonical_tip {
-                    if let Some(precomputed_block) = indexer_store.get_block(&hash)? {
-                        ledger.apply_post_balances(&precomputed_block);
-                    }
+                    Arc::make_mut(&mut ledger).apply_block_post_balances(&post_balances)?;
+                    Some(ledger)
                 }
-
-                return Ok(Some(ledger));
+                _ => indexer_store.get_ledger(&best_tip)?.map(Arc::new),
+            };
+            if let Some(ledger) = &ledger {
+                self.best_ledger_cache = Some((best_tip, ledger.clone()));
             }
+            return Ok(ledger);
         }
 
         Ok(None)
@@ -960,6 +1311,7 @@ impl IndexerState {
         SummaryShort {
             uptime: Instant::now() - self.init_time,
             blocks_processed: self.blocks_processed,
+            num_invalid_signature_blocks: self.num_invalid_signature_blocks(),
             witness_tree,
             db_stats: db_stats_str.map(|s| DbStats::from_str(&format!("{mem}\n{s}")).unwrap()),
         }
@@ -1002,11 +1354,20 @@ impl IndexerState {
         SummaryVerbose {
             uptime: Instant::now() - self.init_time,
             blocks_processed: self.blocks_processed,
+            num_invalid_signature_blocks: self.num_invalid_signature_blocks(),
             witness_tree,
             db_stats: db_stats_str.map(|s| DbStats::from_str(&format!("{mem}\n{s}")).unwrap()),
         }
     }
 
+    /// Number of blocks flagged with invalid signatures
+    fn num_invalid_signature_blocks(&self) -> u32 {
+        self.indexer_store
+            .as_ref()
+            .and_then(|db| db.get_num_blocks_with_invalid_signatures().ok())
+            .unwrap_or_default()
+    }
+
     fn is_initializing(&self) -> bool {
         self.phase == IndexerPhase::InitializingFromBlockDir
             || self.phase == IndexerPhase::InitializingFromDB
@@ -2,17 +2,32 @@ use std::collections::HashMap;
 
 use serde_derive::{Deserialize, Serialize};
 
-use crate::block::BlockHash;
+use crate::{block::BlockHash, network::Network};
 
 use super::{branch::Branch, ledger::diff::LedgerDiff};
 
+/// Version of the [StateSnapshot] format, bumped whenever its fields or the
+/// encoding of the types they hold change
+pub const STATE_SNAPSHOT_VERSION: u32 = 2;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct StateSnapshot {
+    /// Must stay the first field, it's read before the rest of the snapshot
+    pub version: u32,
+    /// Network the state was indexed from
+    pub network: Network,
+    pub genesis_hash: Option<BlockHash>,
     pub root_branch: Branch,
+    pub dangling_branches: Vec<Branch>,
     pub diffs_map: HashMap<BlockHash, LedgerDiff>,
+    pub best_tip: BlockHash,
+    pub canonical_tip: BlockHash,
+    pub blocks_processed: u32,
 }
 
 pub trait StateStore {
     fn store_state_snapshot(&self, snapshot: &StateSnapshot) -> anyhow::Result<()>;
     fn read_snapshot(&self) -> anyhow::Result<Option<StateSnapshot>>;
+    /// Version of the stored snapshot, without decoding the rest of it
+    fn snapshot_version(&self) -> anyhow::Result<Option<u32>>;
 }
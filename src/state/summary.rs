@@ -17,12 +17,15 @@ pub trait Summary {
     fn max_dangling_height(&self) -> u32;
     fn max_dangling_length(&self) -> u32;
     fn db_stats(&self) -> DbStats;
+    fn num_invalid_signature_blocks(&self) -> u32;
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SummaryShort {
     pub uptime: std::time::Duration,
     pub blocks_processed: u32,
+    /// Blocks with user commands whose signatures don't verify
+    pub num_invalid_signature_blocks: u32,
     pub witness_tree: WitnessTreeSummaryShort,
     pub db_stats: Option<DbStats>,
 }
@@ -31,6 +34,8 @@ pub struct SummaryShort {
 pub struct SummaryVerbose {
     pub uptime: std::time::Duration,
     pub blocks_processed: u32,
+    /// Blocks with user commands whose signatures don't verify
+    pub num_invalid_signature_blocks: u32,
     pub witness_tree: WitnessTreeSummaryVerbose,
     pub db_stats: Option<DbStats>,
 }
@@ -98,6 +103,7 @@ impl From<SummaryVerbose> for SummaryShort {
         Self {
             uptime: value.uptime,
             blocks_processed: value.blocks_processed,
+            num_invalid_signature_blocks: value.num_invalid_signature_blocks,
             witness_tree: value.witness_tree.into(),
             db_stats: value.db_stats,
         }
@@ -148,6 +154,11 @@ fn summary_short(state: &impl Summary, f: &mut std::fmt::Formatter<'_>) -> std::
         writeln!(f, "  Max length: {}", state.max_dangling_height())?;
     }
 
+    if state.num_invalid_signature_blocks() > 0 {
+        writeln!(f, "\n=== Invalid signatures ===")?;
+        writeln!(f, "  Num blocks: {}", state.num_invalid_signature_blocks())?;
+    }
+
     // let db_stats = state.db_stats.as_ref().unwrap();
     writeln!(f, "\n=== DB stats ===")?;
     writeln!(
@@ -191,6 +202,10 @@ impl Summary for SummaryShort {
         self.db_stats.as_ref().unwrap().clone()
     }
 
+    fn num_invalid_signature_blocks(&self) -> u32 {
+        self.num_invalid_signature_blocks
+    }
+
     fn max_dangling_height(&self) -> u32 {
         self.witness_tree.max_dangling_height
     }
@@ -249,6 +264,10 @@ impl Summary for SummaryVerbose {
         self.db_stats.as_ref().unwrap().clone()
     }
 
+    fn num_invalid_signature_blocks(&self) -> u32 {
+        self.num_invalid_signature_blocks
+    }
+
     fn max_dangling_height(&self) -> u32 {
         self.witness_tree.max_dangling_height
     }
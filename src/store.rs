@@ -1,9 +1,16 @@
 use crate::{
     block::{precomputed::PrecomputedBlock, signed_command, store::BlockStore, BlockHash},
+    network::NetworkProfile,
     staking_ledger::{staking_ledger_store::StakingLedgerStore, StakingLedger},
     state::{
-        ledger::{store::LedgerStore, Ledger},
-        snapshot::{StateSnapshot, StateStore},
+        ledger::{
+            command::{InternalCommand, UserCommandWithStatus},
+            post_balances::BlockPostBalances,
+            public_key::PublicKey,
+            store::LedgerStore,
+            Ledger,
+        },
+        snapshot::{StateSnapshot, StateStore, STATE_SNAPSHOT_VERSION},
         Canonicity,
     },
 };
@@ -11,20 +18,23 @@ use data_encoding::BASE32HEX;
 use mina_serialization_types::{staged_ledger_diff::UserCommand, v1::UserCommandWithStatusV1};
 use rocksdb::{
     backup::{BackupEngine, BackupEngineOptions, RestoreOptions},
-    ColumnFamilyDescriptor, DBIterator, DBRawIterator, DB,
+    ColumnFamilyDescriptor, DBIterator, DBRawIterator, WriteBatch, DB,
 };
+use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashMap,
     fs::remove_dir_all,
     path::{Path, PathBuf},
     str::FromStr,
 };
-use tracing::{info, instrument, trace};
+use tracing::{info, instrument, trace, warn};
 use zstd::DEFAULT_COMPRESSION_LEVEL;
 
-/// {Timestamp}-{Height}-{Hash} -> Transaction
-/// The height is padded to 12 digits for sequential iteration
-#[derive(Debug, Clone)]
-pub struct TransactionKey(u64, u32, String);
+/// {Timestamp}-{Height}-{Hash}-{StateHash} -> StoredTransaction
+/// The height is padded to 12 digits for sequential iteration.
+/// The state hash keeps the same command in sibling blocks apart.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TransactionKey(u64, u32, String, String);
 
 impl std::fmt::Display for TransactionKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -32,17 +42,18 @@ impl std::fmt::Display for TransactionKey {
         let bytes = self.0.to_string().into_bytes();
         let timestamp_hash = BASE32HEX.encode(&bytes);
 
-        write!(f, "{}-{:012}-{}", timestamp_hash, self.1, self.2)
+        write!(f, "{}-{:012}-{}-{}", timestamp_hash, self.1, self.2, self.3)
     }
 }
 
 impl TransactionKey {
-    /// Creates a new key for a transaction
-    pub fn new<S>(h: u32, t: u64, s: S) -> Self
+    /// Creates a new key for a transaction in the block with the given state hash
+    pub fn new<S, T>(h: u32, t: u64, s: S, state_hash: T) -> Self
     where
         S: Into<String>,
+        T: Into<String>,
     {
-        Self(t, h, s.into())
+        Self(t, h, s.into(), state_hash.into())
     }
 
     /// Returns the key as bytes
@@ -55,7 +66,7 @@ impl TransactionKey {
         let key = std::str::from_utf8(bytes)?;
         let parts: Vec<&str> = key.split('-').collect();
 
-        if parts.len() != 3 {
+        if parts.len() != 4 {
             anyhow::bail!("Invalid transaction key: {}", key);
         }
         // decode timestamp hash
@@ -67,6 +78,7 @@ impl TransactionKey {
             u64::from_str(millis_str)?,
             u32::from_str(parts[1])?,
             parts[2].to_string(),
+            parts[3].to_string(),
         ))
     }
 
@@ -84,8 +96,29 @@ impl TransactionKey {
     pub fn hash(&self) -> &str {
         &self.2
     }
+
+    /// Returns the state hash of the transaction's block
+    pub fn state_hash(&self) -> &str {
+        &self.3
+    }
+}
+
+/// A user command as stored in the "tx" column family
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredTransaction {
+    /// State hash of the containing block
+    pub state_hash: BlockHash,
+    /// Global slot since genesis of the containing block
+    pub global_slot: u32,
+    pub command: UserCommandWithStatusV1,
+    /// Reason the command failed, `None` if it was applied
+    pub failure_reason: Option<String>,
 }
 
+/// Version of the db layout, bumped whenever the keys or encoded values of a
+/// column family change, since existing dbs can't be read anymore
+pub const DB_VERSION: u32 = 1;
+
 #[derive(Debug)]
 pub struct IndexerStore {
     pub db_path: PathBuf,
@@ -99,21 +132,47 @@ impl IndexerStore {
             &database_opts,
             path,
             secondary,
-            vec!["blocks", "ledgers"],
+            vec![
+                "blocks",
+                "blocks-height",
+                "blocks-slot",
+                "canonicity",
+                "ledgers",
+                "post-balances",
+                "tx",
+                "tx-from",
+                "tx-to",
+                "tx-hash",
+                "invalid-signatures",
+                "metadata",
+            ],
         )?;
-        Ok(Self {
+        let indexer_store = Self {
             db_path: PathBuf::from(secondary),
             database,
-        })
+        };
+        if let Some(version) = indexer_store.db_version()? {
+            check_db_version(version, path)?;
+        }
+        Ok(indexer_store)
     }
     pub fn new(path: &Path) -> anyhow::Result<Self> {
         let mut cf_opts = rocksdb::Options::default();
         cf_opts.set_max_write_buffer_number(16);
         let blocks = ColumnFamilyDescriptor::new("blocks", cf_opts.clone());
+        let blocks_height = ColumnFamilyDescriptor::new("blocks-height", cf_opts.clone());
+        let blocks_slot = ColumnFamilyDescriptor::new("blocks-slot", cf_opts.clone());
         let ledgers = ColumnFamilyDescriptor::new("ledgers", cf_opts.clone());
+        let post_balances = ColumnFamilyDescriptor::new("post-balances", cf_opts.clone());
         let canonicity = ColumnFamilyDescriptor::new("canonicity", cf_opts.clone());
         let tx = ColumnFamilyDescriptor::new("tx", cf_opts.clone());
-        let staking_ledgers = ColumnFamilyDescriptor::new("staking-ledgers", cf_opts);
+        let tx_from = ColumnFamilyDescriptor::new("tx-from", cf_opts.clone());
+        let tx_to = ColumnFamilyDescriptor::new("tx-to", cf_opts.clone());
+        let tx_hash = ColumnFamilyDescriptor::new("tx-hash", cf_opts.clone());
+        let internal_commands = ColumnFamilyDescriptor::new("internal-commands", cf_opts.clone());
+        let staking_ledgers = ColumnFamilyDescriptor::new("staking-ledgers", cf_opts.clone());
+        let invalid_signatures = ColumnFamilyDescriptor::new("invalid-signatures", cf_opts.clone());
+        let metadata = ColumnFamilyDescriptor::new("metadata", cf_opts);
 
         let mut database_opts = rocksdb::Options::default();
         database_opts.create_missing_column_families(true);
@@ -121,41 +180,537 @@ impl IndexerStore {
         let database = rocksdb::DBWithThreadMode::open_cf_descriptors(
             &database_opts,
             path,
-            vec![blocks, ledgers, canonicity, tx, staking_ledgers],
+            vec![
+                blocks,
+                blocks_height,
+                blocks_slot,
+                ledgers,
+                post_balances,
+                canonicity,
+                tx,
+                tx_from,
+                tx_to,
+                tx_hash,
+                internal_commands,
+                staking_ledgers,
+                invalid_signatures,
+                metadata,
+            ],
         )?;
-        Ok(Self {
+        let indexer_store = Self {
             db_path: PathBuf::from(path),
             database,
-        })
+        };
+
+        // dbs written before the version marker have no version
+        match indexer_store.db_version()? {
+            Some(version) => check_db_version(version, path)?,
+            None if indexer_store.has_blocks() => check_db_version(0, path)?,
+            None => indexer_store.put_db_version()?,
+        }
+        Ok(indexer_store)
     }
 
     pub fn db_path(&self) -> &Path {
         &self.db_path
     }
 
+    /// Version of the db's layout, `None` for a fresh db
+    pub fn db_version(&self) -> anyhow::Result<Option<u32>> {
+        let cf_handle = self
+            .database
+            .cf_handle("metadata")
+            .expect("column family exists");
+        Ok(self
+            .database
+            .get_pinned_cf(&cf_handle, DB_VERSION_KEY)?
+            .map(|bytes| bcs::from_bytes(&bytes))
+            .transpose()?)
+    }
+
+    fn put_db_version(&self) -> anyhow::Result<()> {
+        let cf_handle = self
+            .database
+            .cf_handle("metadata")
+            .expect("column family exists");
+        self.database
+            .put_cf(&cf_handle, DB_VERSION_KEY, bcs::to_bytes(&DB_VERSION)?)?;
+        Ok(())
+    }
+
     pub fn put_tx(
         &self,
         height: u32,
         timestamp: u64,
+        global_slot: u32,
+        state_hash: &BlockHash,
+        tx: UserCommandWithStatusV1,
+    ) -> anyhow::Result<()> {
+        let mut batch = WriteBatch::default();
+        self.batch_tx(&mut batch, height, timestamp, global_slot, state_hash, tx)?;
+        self.database.write(batch)?;
+        Ok(())
+    }
+
+    fn batch_tx(
+        &self,
+        batch: &mut WriteBatch,
+        height: u32,
+        timestamp: u64,
+        global_slot: u32,
+        state_hash: &BlockHash,
         tx: UserCommandWithStatusV1,
     ) -> anyhow::Result<()> {
         let cf_handle = self.database.cf_handle("tx").expect("column family exists");
 
         match tx.clone().inner().data.inner().inner() {
             UserCommand::SignedCommand(cmd) => {
-                let hash = signed_command::SignedCommand(cmd)
-                    .hash_signed_command()
-                    .unwrap();
-                let key = TransactionKey::new(height, timestamp, hash).bytes();
-                let value = bcs::to_bytes(&tx)?;
+                let cmd = signed_command::SignedCommand(cmd);
+                let hash = cmd.hash_signed_command()?;
+                let key = TransactionKey::new(height, timestamp, hash, state_hash.0.clone());
+                let value = bcs::to_bytes(&StoredTransaction {
+                    state_hash: state_hash.clone(),
+                    global_slot,
+                    failure_reason: UserCommandWithStatus(tx.clone()).failure_reason(),
+                    command: tx,
+                })?;
+
+                batch.put_cf(&cf_handle, key.bytes(), value);
+
+                // index by sender and receiver
+                let tx_from_cf = self
+                    .database
+                    .cf_handle("tx-from")
+                    .expect("column family exists");
+                let tx_to_cf = self
+                    .database
+                    .cf_handle("tx-to")
+                    .expect("column family exists");
 
-                self.database.put_cf(&cf_handle, key, value)?;
+                batch.put_cf(
+                    &tx_from_cf,
+                    account_tx_key(&cmd.source_pk(), &key),
+                    key.bytes(),
+                );
+                batch.put_cf(
+                    &tx_to_cf,
+                    account_tx_key(&cmd.receiver_pk(), &key),
+                    key.bytes(),
+                );
+
+                // index by transaction hash and containing block
+                let tx_hash_cf = self
+                    .database
+                    .cf_handle("tx-hash")
+                    .expect("column family exists");
+                batch.put_cf(
+                    &tx_hash_cf,
+                    tx_hash_key(key.hash(), state_hash),
+                    key.bytes(),
+                );
 
                 Ok(())
             }
         }
     }
 
+    /// Get the transaction with the given key, along with its block's state hash
+    pub fn get_tx(&self, key: &TransactionKey) -> anyhow::Result<Option<StoredTransaction>> {
+        let cf_handle = self.database.cf_handle("tx").expect("column family exists");
+        self.database.try_catch_up_with_primary().ok();
+
+        let mut tx = None;
+        if let Some(bytes) = self
+            .database
+            .get_pinned_cf(&cf_handle, key.bytes())?
+            .map(|bytes| bytes.to_vec())
+        {
+            tx = Some(bcs::from_bytes(&bytes)?);
+        }
+        Ok(tx)
+    }
+
+    /// Keys of the transactions sent by the public key, in timestamp order
+    pub fn get_tx_keys_from(&self, public_key: &PublicKey) -> anyhow::Result<Vec<TransactionKey>> {
+        self.get_account_tx_keys("tx-from", public_key)
+    }
+
+    /// Keys of the transactions received by the public key, in timestamp order
+    pub fn get_tx_keys_to(&self, public_key: &PublicKey) -> anyhow::Result<Vec<TransactionKey>> {
+        self.get_account_tx_keys("tx-to", public_key)
+    }
+
+    fn get_account_tx_keys(
+        &self,
+        cf: &str,
+        public_key: &PublicKey,
+    ) -> anyhow::Result<Vec<TransactionKey>> {
+        self.database.try_catch_up_with_primary().ok();
+
+        let prefix = format!("{}-", public_key.to_address());
+        let mut tx_keys = vec![];
+        for entry in self.iter_prefix_cf(cf, prefix.as_bytes()) {
+            let (key, value) = entry?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            tx_keys.push(TransactionKey::from_slice(&value)?);
+        }
+        Ok(tx_keys)
+    }
+
+    /// Blocks containing the transaction with the given hash, with the transaction's key
+    pub fn get_tx_blocks(&self, hash: &str) -> anyhow::Result<Vec<(BlockHash, TransactionKey)>> {
+        self.database.try_catch_up_with_primary().ok();
+
+        let prefix = format!("{hash}-");
+        let mut tx_blocks = vec![];
+        for entry in self.iter_prefix_cf("tx-hash", prefix.as_bytes()) {
+            let (key, value) = entry?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            let state_hash = std::str::from_utf8(&key[prefix.len()..])?;
+            tx_blocks.push((
+                BlockHash(state_hash.to_string()),
+                TransactionKey::from_slice(&value)?,
+            ));
+        }
+        Ok(tx_blocks)
+    }
+
+    /// Stores the block's internal commands under its [TransactionKey],
+    /// using the block's state hash as the hash component too
+    pub fn put_internal_commands(
+        &self,
+        block: &PrecomputedBlock,
+        network: &NetworkProfile,
+    ) -> anyhow::Result<()> {
+        let mut batch = WriteBatch::default();
+        self.batch_internal_commands(&mut batch, block, network)?;
+        self.database.write(batch)?;
+        Ok(())
+    }
+
+    fn batch_internal_commands(
+        &self,
+        batch: &mut WriteBatch,
+        block: &PrecomputedBlock,
+        network: &NetworkProfile,
+    ) -> anyhow::Result<()> {
+        let cf_handle = self
+            .database
+            .cf_handle("internal-commands")
+            .expect("column family exists");
+        let key = TransactionKey::new(
+            block.blockchain_length,
+            block.timestamp(),
+            block.state_hash.clone(),
+            block.state_hash.clone(),
+        )
+        .bytes();
+        let value = bcs::to_bytes(&InternalCommand::from_precomputed_block(block, network))?;
+
+        batch.put_cf(&cf_handle, key, value);
+        Ok(())
+    }
+
+    /// Atomically writes everything derived from a block: the block and its
+    /// height/slot index, its post balances, user and internal commands with their
+    /// indexes, the hashes of commands with invalid signatures, if any, and, for
+    /// canonical blocks, the canonical index and canonicity
+    pub fn ingest_block(
+        &self,
+        block: &PrecomputedBlock,
+        network: &NetworkProfile,
+        canonical: bool,
+        invalid_signatures: &[String],
+    ) -> anyhow::Result<()> {
+        let state_hash = BlockHash(block.state_hash.clone());
+        let mut batch = WriteBatch::default();
+
+        self.batch_block(&mut batch, block)?;
+        self.batch_post_balances(
+            &mut batch,
+            &state_hash,
+            &BlockHash::previous_state_hash(block),
+            &BlockPostBalances::from_precomputed(block),
+        )?;
+        for cmd in block.commands() {
+            self.batch_tx(
+                &mut batch,
+                block.blockchain_length,
+                block.timestamp(),
+                block.global_slot_since_genesis(),
+                &state_hash,
+                cmd,
+            )?;
+        }
+        self.batch_internal_commands(&mut batch, block, network)?;
+        if !invalid_signatures.is_empty() {
+            self.batch_invalid_signatures(&mut batch, &state_hash, invalid_signatures)?;
+        }
+        if canonical {
+            self.batch_canonical_block(
+                &mut batch,
+                block.blockchain_length,
+                block.global_slot_since_genesis(),
+                &state_hash,
+            )?;
+        }
+
+        self.database.write(batch)?;
+        Ok(())
+    }
+
+    /// Flushes the WAL and the memtables of every column family to disk
+    pub fn flush(&self) -> anyhow::Result<()> {
+        self.database.flush_wal(true)?;
+        self.database.flush()?;
+        for cf in DB::list_cf(&rocksdb::Options::default(), &self.db_path)? {
+            if let Some(cf_handle) = self.database.cf_handle(&cf) {
+                self.database.flush_cf(&cf_handle)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks whether any block has been ingested into the db
+    pub fn has_blocks(&self) -> bool {
+        self.iterator_cf("blocks-height").next().is_some()
+    }
+
+    /// Finds blocks in the height or slot index whose ingestion didn't complete, i.e.
+    /// the block or its post balances are missing. These can only be left behind
+    /// by blocks added without [IndexerStore::ingest_block].
+    pub fn find_incomplete_blocks(&self) -> anyhow::Result<Vec<BlockHash>> {
+        Ok(self.incomplete_block_index_keys()?.into_keys().collect())
+    }
+
+    /// Height and slot index keys of incomplete blocks, found in a single
+    /// pass over each index
+    fn incomplete_block_index_keys(
+        &self,
+    ) -> anyhow::Result<HashMap<BlockHash, Vec<(&'static str, Box<[u8]>)>>> {
+        let blocks_cf = self
+            .database
+            .cf_handle("blocks")
+            .expect("column family exists");
+        let post_balances_cf = self
+            .database
+            .cf_handle("post-balances")
+            .expect("column family exists");
+
+        let mut incomplete: HashMap<BlockHash, Vec<(&'static str, Box<[u8]>)>> = HashMap::new();
+        for cf in ["blocks-height", "blocks-slot"] {
+            for entry in self.iterator_cf(cf) {
+                let (key, state_hash) = entry?;
+                if self
+                    .database
+                    .get_pinned_cf(&post_balances_cf, &state_hash)?
+                    .is_none()
+                    || self
+                        .database
+                        .get_pinned_cf(&blocks_cf, &state_hash)?
+                        .is_none()
+                {
+                    incomplete
+                        .entry(BlockHash(String::from_utf8(state_hash.to_vec())?))
+                        .or_default()
+                        .push((cf, key));
+                }
+            }
+        }
+        Ok(incomplete)
+    }
+
+    /// Completes the ingestion of half-written blocks that are still present,
+    /// otherwise removes their index entries so they're ingested again
+    ///
+    /// The indexes are only scanned if blocks were written since the last clean
+    /// shutdown, afterwards the db is marked clean
+    #[instrument(skip(self))]
+    pub fn recover_incomplete_blocks(&self, network: &NetworkProfile) -> anyhow::Result<()> {
+        if !self.is_dirty()? {
+            return Ok(());
+        }
+        for (state_hash, index_keys) in self.incomplete_block_index_keys()? {
+            if let Some(block) = self.get_block(&state_hash)? {
+                warn!("Completing ingestion of half-written block {state_hash:?}");
+                let canonical = self.get_canonicity(&state_hash)? == Some(Canonicity::Canonical);
+                self.ingest_block(
+                    &block,
+                    network,
+                    canonical,
+                    &self
+                        .get_invalid_signatures(&state_hash)?
+                        .unwrap_or_default(),
+                )?;
+            } else {
+                warn!("Removing index entries of half-written block {state_hash:?}");
+                self.remove_block_index(&index_keys)?;
+            }
+        }
+        self.mark_clean()
+    }
+
+    /// Whether blocks were written since the db was last marked clean
+    pub fn is_dirty(&self) -> anyhow::Result<bool> {
+        let cf_handle = self
+            .database
+            .cf_handle("metadata")
+            .expect("column family exists");
+        Ok(self
+            .database
+            .get_pinned_cf(&cf_handle, DIRTY_KEY)?
+            .is_some())
+    }
+
+    /// Marks the db clean, once every block written so far is complete
+    pub fn mark_clean(&self) -> anyhow::Result<()> {
+        let cf_handle = self
+            .database
+            .cf_handle("metadata")
+            .expect("column family exists");
+        self.database.delete_cf(&cf_handle, DIRTY_KEY)?;
+        Ok(())
+    }
+
+    /// Deletes the given height and slot index entries of a block
+    fn remove_block_index(&self, index_keys: &[(&'static str, Box<[u8]>)]) -> anyhow::Result<()> {
+        let mut batch = WriteBatch::default();
+        for (cf, key) in index_keys {
+            let cf_handle = self.database.cf_handle(cf).expect("column family exists");
+            batch.delete_cf(&cf_handle, key);
+        }
+        self.database.write(batch)?;
+        Ok(())
+    }
+
+    fn batch_block(&self, batch: &mut WriteBatch, block: &PrecomputedBlock) -> anyhow::Result<()> {
+        let cf_handle = self
+            .database
+            .cf_handle("blocks")
+            .expect("column family exists");
+        let key = block.state_hash.as_bytes();
+        let value = bcs::to_bytes(&block)?;
+        batch.put_cf(&cf_handle, key, value);
+
+        // index by blockchain length and global slot
+        let blocks_height_cf = self
+            .database
+            .cf_handle("blocks-height")
+            .expect("column family exists");
+        let blocks_slot_cf = self
+            .database
+            .cf_handle("blocks-slot")
+            .expect("column family exists");
+        batch.put_cf(
+            &blocks_height_cf,
+            block_index_key(block.blockchain_length, &block.state_hash),
+            key,
+        );
+        batch.put_cf(
+            &blocks_slot_cf,
+            block_index_key(block.global_slot_since_genesis(), &block.state_hash),
+            key,
+        );
+
+        let metadata_cf = self
+            .database
+            .cf_handle("metadata")
+            .expect("column family exists");
+        batch.put_cf(&metadata_cf, DIRTY_KEY, b"");
+        Ok(())
+    }
+
+    fn batch_canonicity(
+        &self,
+        batch: &mut WriteBatch,
+        state_hash: &BlockHash,
+        canonicity: Canonicity,
+    ) -> anyhow::Result<()> {
+        let cf_handle = self
+            .database
+            .cf_handle("canonicity")
+            .expect("column family exists");
+        let key = state_hash.0.as_bytes();
+        let value = bcs::to_bytes(&canonicity)?;
+        batch.put_cf(&cf_handle, key, value);
+        Ok(())
+    }
+
+    fn batch_post_balances(
+        &self,
+        batch: &mut WriteBatch,
+        state_hash: &BlockHash,
+        parent_hash: &BlockHash,
+        post_balances: &BlockPostBalances,
+    ) -> anyhow::Result<()> {
+        let cf_handle = self
+            .database
+            .cf_handle("post-balances")
+            .expect("column family exists");
+        let key = state_hash.0.as_bytes();
+        let value = bcs::to_bytes(&(parent_hash, post_balances))?;
+        batch.put_cf(&cf_handle, key, value);
+        Ok(())
+    }
+
+    /// Flags the block with its invalid signature tx hashes, counting it
+    /// if it wasn't flagged before
+    fn batch_invalid_signatures(
+        &self,
+        batch: &mut WriteBatch,
+        state_hash: &BlockHash,
+        tx_hashes: &[String],
+    ) -> anyhow::Result<()> {
+        let cf_handle = self
+            .database
+            .cf_handle("invalid-signatures")
+            .expect("column family exists");
+        let key = state_hash.0.as_bytes();
+        if self.database.get_pinned_cf(&cf_handle, key)?.is_none() {
+            let metadata_cf = self
+                .database
+                .cf_handle("metadata")
+                .expect("column family exists");
+            let num_blocks = self.get_num_blocks_with_invalid_signatures()? + 1;
+            batch.put_cf(
+                &metadata_cf,
+                NUM_INVALID_SIGNATURE_BLOCKS_KEY,
+                bcs::to_bytes(&num_blocks)?,
+            );
+        }
+        batch.put_cf(&cf_handle, key, bcs::to_bytes(&tx_hashes)?);
+        Ok(())
+    }
+
+    fn batch_canonical_block(
+        &self,
+        batch: &mut WriteBatch,
+        blockchain_length: u32,
+        global_slot: u32,
+        state_hash: &BlockHash,
+    ) -> anyhow::Result<()> {
+        let cf_handle = self
+            .database
+            .cf_handle("canonicity")
+            .expect("column family exists");
+
+        batch.put_cf(
+            &cf_handle,
+            canonical_height_key(blockchain_length),
+            state_hash.0.as_bytes(),
+        );
+        batch.put_cf(
+            &cf_handle,
+            canonical_slot_key(global_slot),
+            blockchain_length.to_string().as_bytes(),
+        );
+        self.batch_canonicity(batch, state_hash, Canonicity::Canonical)
+    }
+
     /// Creates a prefix iterator over a CF in the DB
     pub fn iter_prefix_cf(&self, cf: &str, prefix: &[u8]) -> DBIterator<'_> {
         let cf_handle = self.database.cf_handle(cf).expect("column family exists");
@@ -168,6 +723,15 @@ impl IndexerStore {
             .iterator_cf(cf_handle, rocksdb::IteratorMode::Start)
     }
 
+    /// Creates an iterator over a CF in the DB, starting at `key`
+    pub fn iterator_cf_from(&self, cf: &str, key: &[u8]) -> DBIterator<'_> {
+        let cf_handle = self.database.cf_handle(cf).expect("column family exists");
+        self.database.iterator_cf(
+            cf_handle,
+            rocksdb::IteratorMode::From(key, rocksdb::Direction::Forward),
+        )
+    }
+
     pub fn raw_iterator_cf(&self, cf: &str) -> DBRawIterator<'_> {
         let cf_handle = self.database.cf_handle(cf).expect("column family exists");
         self.database.raw_iterator_cf(cf_handle)
@@ -282,13 +846,9 @@ impl IndexerStore {
 
 impl BlockStore for IndexerStore {
     fn add_block(&self, block: &PrecomputedBlock) -> anyhow::Result<()> {
-        let cf_handle = self
-            .database
-            .cf_handle("blocks")
-            .expect("column family exists");
-        let key = block.state_hash.as_bytes();
-        let value = bcs::to_bytes(&block)?;
-        self.database.put_cf(&cf_handle, key, value)?;
+        let mut batch = WriteBatch::default();
+        self.batch_block(&mut batch, block)?;
+        self.database.write(batch)?;
         Ok(())
     }
 
@@ -311,27 +871,85 @@ impl BlockStore for IndexerStore {
     }
 
     fn set_canonicity(&self, state_hash: &BlockHash, canonicity: Canonicity) -> anyhow::Result<()> {
-        if let Some(precomputed_block) = self.get_block(state_hash)? {
-            let with_canonicity = PrecomputedBlock {
-                canonicity: Some(canonicity),
-                ..precomputed_block
-            };
-            self.add_block(&with_canonicity)?;
-        }
+        let mut batch = WriteBatch::default();
+        self.batch_canonicity(&mut batch, state_hash, canonicity)?;
+        self.database.write(batch)?;
         Ok(())
     }
 
     fn get_canonicity(&self, state_hash: &BlockHash) -> anyhow::Result<Option<Canonicity>> {
+        let cf_handle = self
+            .database
+            .cf_handle("canonicity")
+            .expect("column family exists");
         let mut canonicity = None;
-        if let Some(PrecomputedBlock {
-            canonicity: Some(block_canonicity),
-            ..
-        }) = self.get_block(state_hash)?
+        self.database.try_catch_up_with_primary().ok();
+        let key = state_hash.0.as_bytes();
+        if let Some(bytes) = self
+            .database
+            .get_pinned_cf(&cf_handle, key)?
+            .map(|bytes| bytes.to_vec())
         {
-            canonicity = Some(block_canonicity);
+            canonicity = Some(bcs::from_bytes(&bytes)?);
         }
         Ok(canonicity)
     }
+
+    fn set_invalid_signatures(
+        &self,
+        state_hash: &BlockHash,
+        tx_hashes: &[String],
+    ) -> anyhow::Result<()> {
+        let mut batch = WriteBatch::default();
+        self.batch_invalid_signatures(&mut batch, state_hash, tx_hashes)?;
+        self.database.write(batch)?;
+        Ok(())
+    }
+
+    fn get_invalid_signatures(
+        &self,
+        state_hash: &BlockHash,
+    ) -> anyhow::Result<Option<Vec<String>>> {
+        let cf_handle = self
+            .database
+            .cf_handle("invalid-signatures")
+            .expect("column family exists");
+        let mut tx_hashes = None;
+        self.database.try_catch_up_with_primary().ok();
+        let key = state_hash.0.as_bytes();
+        if let Some(bytes) = self
+            .database
+            .get_pinned_cf(&cf_handle, key)?
+            .map(|bytes| bytes.to_vec())
+        {
+            tx_hashes = Some(bcs::from_bytes(&bytes)?);
+        }
+        Ok(tx_hashes)
+    }
+
+    fn get_num_blocks_with_invalid_signatures(&self) -> anyhow::Result<u32> {
+        let cf_handle = self
+            .database
+            .cf_handle("metadata")
+            .expect("column family exists");
+        self.database.try_catch_up_with_primary().ok();
+        Ok(self
+            .database
+            .get_pinned_cf(&cf_handle, NUM_INVALID_SIGNATURE_BLOCKS_KEY)?
+            .map(|bytes| bcs::from_bytes(&bytes))
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    fn get_blocks_with_invalid_signatures(&self) -> anyhow::Result<Vec<BlockHash>> {
+        self.database.try_catch_up_with_primary().ok();
+        let mut state_hashes = vec![];
+        for entry in self.iterator_cf("invalid-signatures") {
+            let (key, _) = entry?;
+            state_hashes.push(BlockHash(String::from_utf8(key.to_vec())?));
+        }
+        Ok(state_hashes)
+    }
 }
 
 impl LedgerStore for IndexerStore {
@@ -346,7 +964,129 @@ impl LedgerStore for IndexerStore {
         Ok(())
     }
 
+    fn add_post_balances(
+        &self,
+        state_hash: &BlockHash,
+        parent_hash: &BlockHash,
+        post_balances: &BlockPostBalances,
+    ) -> anyhow::Result<()> {
+        let mut batch = WriteBatch::default();
+        self.batch_post_balances(&mut batch, state_hash, parent_hash, post_balances)?;
+        self.database.write(batch)?;
+        Ok(())
+    }
+
     fn get_ledger(&self, state_hash: &BlockHash) -> anyhow::Result<Option<Ledger>> {
+        self.database.try_catch_up_with_primary().ok();
+
+        // walk back to the nearest checkpoint via the post balances' parent links
+        let mut block_post_balances = vec![];
+        let mut state_hash = state_hash.clone();
+        let mut ledger = loop {
+            if let Some(ledger) = self.get_ledger_checkpoint(&state_hash)? {
+                break ledger;
+            }
+
+            match self.get_post_balances(&state_hash)? {
+                Some((parent_hash, post_balances)) => {
+                    block_post_balances.push(post_balances);
+                    state_hash = parent_hash;
+                }
+                None => return Ok(None),
+            }
+        };
+
+        // apply the post balances from the checkpoint up to the requested block
+        for post_balances in block_post_balances.iter().rev() {
+            ledger.apply_block_post_balances(post_balances)?;
+        }
+        Ok(Some(ledger))
+    }
+
+    fn add_canonical_block(
+        &self,
+        blockchain_length: u32,
+        global_slot: u32,
+        state_hash: &BlockHash,
+    ) -> anyhow::Result<()> {
+        let mut batch = WriteBatch::default();
+        self.batch_canonical_block(&mut batch, blockchain_length, global_slot, state_hash)?;
+        self.database.write(batch)?;
+        Ok(())
+    }
+
+    fn get_canonical_hash_at_height(
+        &self,
+        blockchain_length: u32,
+    ) -> anyhow::Result<Option<BlockHash>> {
+        let cf_handle = self
+            .database
+            .cf_handle("canonicity")
+            .expect("column family exists");
+
+        self.database.try_catch_up_with_primary().ok();
+
+        let mut state_hash = None;
+        if let Some(bytes) = self
+            .database
+            .get_pinned_cf(&cf_handle, canonical_height_key(blockchain_length))?
+            .map(|bytes| bytes.to_vec())
+        {
+            state_hash = Some(BlockHash(String::from_utf8(bytes)?));
+        }
+        Ok(state_hash)
+    }
+
+    fn get_ledger_at_height(&self, blockchain_length: u32) -> anyhow::Result<Option<Ledger>> {
+        match self.get_canonical_hash_at_height(blockchain_length)? {
+            Some(state_hash) => self.get_ledger(&state_hash),
+            None => Ok(None),
+        }
+    }
+
+    fn get_ledger_at_slot(&self, global_slot: u32) -> anyhow::Result<Option<Ledger>> {
+        let cf_handle = self
+            .database
+            .cf_handle("canonicity")
+            .expect("column family exists");
+
+        self.database.try_catch_up_with_primary().ok();
+
+        // find the last canonical block at or before the slot
+        let mut iter = self.database.raw_iterator_cf(&cf_handle);
+        iter.seek_for_prev(canonical_slot_key(global_slot));
+
+        if let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            if key.starts_with(CANONICAL_SLOT_PREFIX.as_bytes()) {
+                let blockchain_length = std::str::from_utf8(value)?.parse::<u32>()?;
+                return self.get_ledger_at_height(blockchain_length);
+            }
+        }
+        Ok(None)
+    }
+
+    fn get_max_canonical_height(&self) -> anyhow::Result<Option<u32>> {
+        let cf_handle = self
+            .database
+            .cf_handle("canonicity")
+            .expect("column family exists");
+
+        self.database.try_catch_up_with_primary().ok();
+
+        let mut iter = self.database.raw_iterator_cf(&cf_handle);
+        iter.seek_for_prev(canonical_height_key(u32::MAX));
+
+        if let Some(key) = iter.key() {
+            if let Some(blockchain_length) = key.strip_prefix(CANONICAL_HEIGHT_PREFIX.as_bytes()) {
+                return Ok(Some(std::str::from_utf8(blockchain_length)?.parse()?));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl IndexerStore {
+    fn get_ledger_checkpoint(&self, state_hash: &BlockHash) -> anyhow::Result<Option<Ledger>> {
         let mut ledger = None;
         let key = state_hash.0.as_bytes();
         let cf_handle = self
@@ -354,8 +1094,6 @@ impl LedgerStore for IndexerStore {
             .cf_handle("ledgers")
             .expect("column family exists");
 
-        self.database.try_catch_up_with_primary().ok();
-
         if let Some(bytes) = self
             .database
             .get_pinned_cf(&cf_handle, key)?
@@ -365,6 +1103,80 @@ impl LedgerStore for IndexerStore {
         }
         Ok(ledger)
     }
+
+    /// Get a block's parent state hash and post balances
+    pub fn get_post_balances(
+        &self,
+        state_hash: &BlockHash,
+    ) -> anyhow::Result<Option<(BlockHash, BlockPostBalances)>> {
+        let mut post_balances = None;
+        let key = state_hash.0.as_bytes();
+        let cf_handle = self
+            .database
+            .cf_handle("post-balances")
+            .expect("column family exists");
+
+        if let Some(bytes) = self
+            .database
+            .get_pinned_cf(&cf_handle, key)?
+            .map(|bytes| bytes.to_vec())
+        {
+            post_balances = Some(bcs::from_bytes(&bytes)?);
+        }
+        Ok(post_balances)
+    }
+}
+
+/// {PublicKey}-{TransactionKey} -> TransactionKey
+fn account_tx_key(public_key: &PublicKey, key: &TransactionKey) -> Vec<u8> {
+    format!("{}-{key}", public_key.to_address()).into_bytes()
+}
+
+/// {Height or GlobalSlot}-{StateHash} -> StateHash
+/// The height or slot is padded to 12 digits for sequential iteration
+pub fn block_index_key(n: u32, state_hash: &str) -> Vec<u8> {
+    format!("{n:012}-{state_hash}").into_bytes()
+}
+
+/// {Hash}-{StateHash} -> TransactionKey
+fn tx_hash_key(hash: &str, state_hash: &BlockHash) -> Vec<u8> {
+    format!("{hash}-{}", state_hash.0).into_bytes()
+}
+
+/// The canonicity CF maps {StateHash} -> Canonicity and indexes canonical blocks
+/// under these prefixes, keeping them apart from the state hash keys
+const CANONICAL_HEIGHT_PREFIX: &str = "canonical-height:";
+const CANONICAL_SLOT_PREFIX: &str = "canonical-slot:";
+
+/// Metadata CF key of the number of blocks flagged with invalid signatures,
+/// so summaries don't scan the invalid signatures CF
+const NUM_INVALID_SIGNATURE_BLOCKS_KEY: &[u8] = b"num-invalid-signature-blocks";
+
+/// Metadata CF key of the db layout version
+const DB_VERSION_KEY: &[u8] = b"db-version";
+
+/// Metadata CF key present from the first block written after a clean shutdown
+/// until the next one, only then can the db hold half-written blocks
+const DIRTY_KEY: &[u8] = b"dirty";
+
+/// Errors if the db's layout isn't the current one
+fn check_db_version(version: u32, path: &Path) -> anyhow::Result<()> {
+    if version != DB_VERSION {
+        anyhow::bail!(
+            "The db at {} has version {version}, expected {DB_VERSION}. Rerun with --reindex to rebuild it",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Heights and slots are padded to 12 digits for sequential iteration
+fn canonical_height_key(blockchain_length: u32) -> Vec<u8> {
+    format!("{CANONICAL_HEIGHT_PREFIX}{blockchain_length:012}").into_bytes()
+}
+
+fn canonical_slot_key(global_slot: u32) -> Vec<u8> {
+    format!("{CANONICAL_SLOT_PREFIX}{global_slot:012}").into_bytes()
 }
 
 impl StakingLedgerStore for IndexerStore {
@@ -397,7 +1209,7 @@ impl StakingLedgerStore for IndexerStore {
             .get_pinned_cf(&cf_handle, key)?
             .map(|bytes| bytes.to_vec())
         {
-            ledger = Some(bcs::from_bytes(&bytes)?);
+            ledger = Some(StakingLedger::from_bytes(&bytes)?);
         }
         Ok(ledger)
     }
@@ -418,10 +1230,30 @@ impl StateStore for IndexerStore {
             .get_pinned(b"STATE")?
             .map(|bytes| bytes.to_vec())
         {
+            let version = snapshot_version(&bytes);
+            if version != Some(STATE_SNAPSHOT_VERSION) {
+                anyhow::bail!(
+                    "Unsupported state snapshot version {version:?}, expected {STATE_SNAPSHOT_VERSION}"
+                );
+            }
             snapshot = Some(bcs::from_bytes(&bytes)?);
         }
         Ok(snapshot)
     }
+
+    fn snapshot_version(&self) -> anyhow::Result<Option<u32>> {
+        Ok(self
+            .database
+            .get_pinned(b"STATE")?
+            .and_then(|bytes| snapshot_version(&bytes)))
+    }
+}
+
+/// bcs encodes the leading version as a little-endian u32
+fn snapshot_version(bytes: &[u8]) -> Option<u32> {
+    bytes
+        .get(..4)
+        .map(|version| u32::from_le_bytes(version.try_into().unwrap()))
 }
 
 impl IndexerStore {
@@ -452,6 +1284,14 @@ impl IndexerStore {
             .unwrap()
     }
 
+    pub fn estimate_live_data_size_cf(&self, cf: &str) -> u64 {
+        let cf_handle = self.database.cf_handle(cf).expect("column family exists");
+        self.database
+            .property_int_value_cf(&cf_handle, rocksdb::properties::ESTIMATE_LIVE_DATA_SIZE)
+            .unwrap()
+            .unwrap()
+    }
+
     pub fn estimate_num_keys(&self) -> u64 {
         self.database
             .property_int_value(rocksdb::properties::ESTIMATE_NUM_KEYS)
@@ -1,10 +1,20 @@
-use mina_indexer::{block::parser::BlockParser, MAINNET_CANONICAL_THRESHOLD};
-use std::path::PathBuf;
+use mina_indexer::{
+    block::parser::BlockParser, network::NetworkProfile, MAINNET_CANONICAL_THRESHOLD,
+};
+use std::{io::Write, path::PathBuf};
+use tempfile::TempDir;
+
+use crate::helpers::temp_dir;
 
 #[tokio::test]
 async fn gaps() {
     let blocks_dir = PathBuf::from("./tests/data/canonical_chain_discovery/gaps");
-    let mut block_parser = BlockParser::new(&blocks_dir, MAINNET_CANONICAL_THRESHOLD).unwrap();
+    let mut block_parser = BlockParser::new(
+        &blocks_dir,
+        &NetworkProfile::mainnet(),
+        MAINNET_CANONICAL_THRESHOLD,
+    )
+    .unwrap();
 
     while let Some(precomputed_block) = block_parser.next().await.unwrap() {
         println!(
@@ -21,7 +31,12 @@ async fn gaps() {
 #[tokio::test]
 async fn contiguous() {
     let blocks_dir = PathBuf::from("./tests/data/canonical_chain_discovery/contiguous");
-    let mut block_parser = BlockParser::new(&blocks_dir, MAINNET_CANONICAL_THRESHOLD).unwrap();
+    let mut block_parser = BlockParser::new(
+        &blocks_dir,
+        &NetworkProfile::mainnet(),
+        MAINNET_CANONICAL_THRESHOLD,
+    )
+    .unwrap();
 
     while let Some(precomputed_block) = block_parser.next().await.unwrap() {
         println!(
@@ -38,7 +53,12 @@ async fn contiguous() {
 #[tokio::test]
 async fn missing_parent() {
     let blocks_dir = PathBuf::from("./tests/data/canonical_chain_discovery/missing_parent");
-    let mut block_parser = BlockParser::new(&blocks_dir, MAINNET_CANONICAL_THRESHOLD).unwrap();
+    let mut block_parser = BlockParser::new(
+        &blocks_dir,
+        &NetworkProfile::mainnet(),
+        MAINNET_CANONICAL_THRESHOLD,
+    )
+    .unwrap();
 
     while let Some(precomputed_block) = block_parser.next().await.unwrap() {
         println!(
@@ -56,3 +76,233 @@ async fn missing_parent() {
     assert_eq!(block_parser.num_canonical, 4);
     assert_eq!(block_parser.total_num_blocks, 35)
 }
+
+#[tokio::test]
+async fn compressed_and_archived() {
+    let tmp_dir = temp_dir("compressed-blocks-test");
+    let blocks_dir = tmp_dir.path();
+
+    // gzip the shortest blocks, zstd the next and bundle the rest in a zstd tarball
+    let mut paths: Vec<PathBuf> =
+        std::fs::read_dir("./tests/data/canonical_chain_discovery/contiguous")
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+    paths.sort();
+
+    let tarball = std::fs::File::create(blocks_dir.join("mainnet-blocks.tar.zst")).unwrap();
+    let mut archive = tar::Builder::new(zstd::Encoder::new(tarball, 0).unwrap().auto_finish());
+    for (n, path) in paths.iter().enumerate() {
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let contents = std::fs::read(path).unwrap();
+        match n % 3 {
+            0 => {
+                let file = std::fs::File::create(blocks_dir.join(format!("{file_name}.gz")));
+                let mut encoder =
+                    flate2::write::GzEncoder::new(file.unwrap(), flate2::Compression::default());
+                encoder.write_all(&contents).unwrap();
+                encoder.finish().unwrap();
+            }
+            1 => {
+                let file = std::fs::File::create(blocks_dir.join(format!("{file_name}.zst")));
+                zstd::stream::copy_encode(&contents[..], file.unwrap(), 0).unwrap();
+            }
+            _ => archive
+                .append_path_with_name(path, format!("blocks/{file_name}"))
+                .unwrap(),
+        }
+    }
+    archive.into_inner().unwrap();
+
+    let mut block_parser = BlockParser::new(
+        blocks_dir,
+        &NetworkProfile::mainnet(),
+        MAINNET_CANONICAL_THRESHOLD,
+    )
+    .unwrap();
+
+    let mut num_blocks = 0;
+    while let Some(precomputed_block) = block_parser.next().await.unwrap() {
+        println!(
+            "length: {}, hash: {}",
+            precomputed_block.blockchain_length, precomputed_block.state_hash
+        );
+        num_blocks += 1;
+    }
+
+    // same as the uncompressed contiguous blocks
+    assert_eq!(block_parser.num_canonical, 10);
+    assert_eq!(block_parser.total_num_blocks, 20);
+    assert_eq!(num_blocks, 20);
+}
+
+#[tokio::test]
+async fn archive_name_collisions() {
+    let tmp_dir = temp_dir("archive-collisions-test");
+    let blocks_dir = tmp_dir.path();
+    let quarantine_dir = blocks_dir.join("quarantine");
+
+    // the same file name in two directories of one archive
+    let path = PathBuf::from(
+        "./tests/data/canonical_chain_discovery/contiguous/mainnet-3-3NKd5So3VNqGZtRZiWsti4yaEe1fX79yz5TbfG6jBZqgMnCQQp3R.json",
+    );
+    let file_name = path.file_name().unwrap().to_str().unwrap();
+    let tarball = std::fs::File::create(blocks_dir.join("mainnet-blocks.tar")).unwrap();
+    let mut archive = tar::Builder::new(tarball);
+    for dir in ["a", "b"] {
+        archive
+            .append_path_with_name(&path, format!("{dir}/{file_name}"))
+            .unwrap();
+    }
+    archive.into_inner().unwrap();
+
+    // one is parsed, the other quarantined
+    let mut block_parser = BlockParser::new(
+        blocks_dir,
+        &NetworkProfile::mainnet(),
+        MAINNET_CANONICAL_THRESHOLD,
+    )
+    .unwrap();
+    block_parser.quarantine_dir = Some(quarantine_dir.clone());
+
+    let mut num_blocks = 0;
+    while block_parser.next().await.unwrap().is_some() {
+        num_blocks += 1;
+    }
+    assert_eq!(num_blocks, 1);
+    assert!(quarantine_dir.join(file_name).exists());
+    drop(block_parser);
+
+    // an archived block file named like one in the blocks dir
+    std::fs::remove_dir_all(&quarantine_dir).unwrap();
+    std::fs::remove_file(blocks_dir.join("mainnet-blocks.tar")).unwrap();
+    std::fs::copy(&path, blocks_dir.join(file_name)).unwrap();
+    let tarball = std::fs::File::create(blocks_dir.join("mainnet-blocks.tar")).unwrap();
+    let mut archive = tar::Builder::new(tarball);
+    archive.append_path_with_name(&path, file_name).unwrap();
+    archive.into_inner().unwrap();
+
+    // the plain block file is parsed, the archived one quarantined
+    let mut block_parser = BlockParser::new(
+        blocks_dir,
+        &NetworkProfile::mainnet(),
+        MAINNET_CANONICAL_THRESHOLD,
+    )
+    .unwrap();
+    block_parser.quarantine_dir = Some(quarantine_dir.clone());
+
+    let mut num_blocks = 0;
+    while block_parser.next().await.unwrap().is_some() {
+        num_blocks += 1;
+    }
+    assert_eq!(num_blocks, 1);
+    assert!(quarantine_dir.join(file_name).exists());
+    assert!(blocks_dir.join(file_name).exists());
+}
+
+#[tokio::test]
+async fn successive_archived_blocks() {
+    let tmp_dir = temp_dir("successive-archive-test");
+    let blocks_dir = tmp_dir.path();
+
+    let tarball = std::fs::File::create(blocks_dir.join("mainnet-blocks.tar")).unwrap();
+    let mut archive = tar::Builder::new(tarball);
+    for entry in std::fs::read_dir("./tests/data/canonical_chain_discovery/contiguous").unwrap() {
+        let path = entry.unwrap().path();
+        archive
+            .append_path_with_name(&path, path.file_name().unwrap())
+            .unwrap();
+    }
+    archive.into_inner().unwrap();
+
+    // only the archived blocks past length 15 are parsed
+    let mut block_parser =
+        BlockParser::new_successive(blocks_dir, &NetworkProfile::mainnet(), 15).unwrap();
+
+    let mut lengths = vec![];
+    while let Some(precomputed_block) = block_parser.next().await.unwrap() {
+        lengths.push(precomputed_block.blockchain_length);
+    }
+    assert_eq!(block_parser.total_num_blocks, 6);
+    assert_eq!(lengths, (16..=21).collect::<Vec<_>>());
+}
+
+/// Copies the contiguous blocks into a fresh temporary `name` directory
+fn contiguous_blocks_dir(name: &str) -> TempDir {
+    let blocks_dir = temp_dir(name);
+
+    for entry in std::fs::read_dir("./tests/data/canonical_chain_discovery/contiguous").unwrap() {
+        let path = entry.unwrap().path();
+        std::fs::copy(&path, blocks_dir.path().join(path.file_name().unwrap())).unwrap();
+    }
+    blocks_dir
+}
+
+#[tokio::test]
+async fn malformed_canonical_block() {
+    let tmp_dir = contiguous_blocks_dir("malformed-canonical-test");
+    let blocks_dir = tmp_dir.path();
+    let quarantine_dir = blocks_dir.join("quarantine");
+
+    // the parent hash can still be read, the JSON can't be parsed
+    let malformed_file =
+        blocks_dir.join("mainnet-5-3NKQUoBfi9vkbuqtDJmSEYBQrcSo4GjwG8bPCiii4yqM8AxEQvtY.json");
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&malformed_file)
+        .unwrap();
+    file.write_all(b"garbage").unwrap();
+
+    let mut block_parser = BlockParser::new(
+        blocks_dir,
+        &NetworkProfile::mainnet(),
+        MAINNET_CANONICAL_THRESHOLD,
+    )
+    .unwrap();
+    block_parser.quarantine_dir = Some(quarantine_dir.clone());
+    assert_eq!(block_parser.num_canonical, 10);
+
+    // the canonical block is skipped rather than failing the parser
+    let mut lengths = vec![];
+    while let Some(precomputed_block) = block_parser.next().await.unwrap() {
+        lengths.push(precomputed_block.blockchain_length);
+    }
+    assert_eq!(lengths.len(), 19);
+    assert!(!lengths.contains(&5));
+
+    assert!(!malformed_file.exists());
+    assert!(quarantine_dir
+        .join(malformed_file.file_name().unwrap())
+        .exists());
+}
+
+#[tokio::test]
+async fn blocks_named_without_state_hash() {
+    let tmp_dir = contiguous_blocks_dir("unnamed-blocks-test");
+    let blocks_dir = tmp_dir.path();
+    std::fs::copy(
+        "./tests/data/sequential_blocks/mainnet-105490-3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC.json",
+        blocks_dir.join("mainnet-105490.json"),
+    )
+    .unwrap();
+
+    let mut block_parser = BlockParser::new(
+        blocks_dir,
+        &NetworkProfile::mainnet(),
+        MAINNET_CANONICAL_THRESHOLD,
+    )
+    .unwrap();
+    assert_eq!(block_parser.num_canonical, 10);
+    assert_eq!(block_parser.total_num_blocks, 21);
+
+    // the unnamed block isn't dropped, it follows the named ones
+    let mut state_hashes = vec![];
+    while let Some(precomputed_block) = block_parser.next().await.unwrap() {
+        state_hashes.push(precomputed_block.state_hash);
+    }
+    assert_eq!(state_hashes.len(), 21);
+    assert_eq!(
+        state_hashes.last().unwrap(),
+        "3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC"
+    );
+}
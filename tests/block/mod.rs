@@ -1,3 +1,4 @@
 mod canonical_chain_discovery;
+mod network;
 mod parser;
 mod store;
@@ -0,0 +1,62 @@
+use mina_indexer::{
+    block::{parser::BlockParser, BlockHash},
+    network::{Network, NetworkProfile},
+    MAINNET_CANONICAL_THRESHOLD,
+};
+use std::path::PathBuf;
+
+use crate::helpers::temp_dir;
+
+#[tokio::test]
+async fn mainnet_blocks_match_mainnet_profile() {
+    let blocks_dir = PathBuf::from("./tests/data/sequential_blocks");
+    let mut block_parser =
+        BlockParser::new_successive(&blocks_dir, &NetworkProfile::mainnet(), 0).unwrap();
+    let mut num_blocks = 0;
+
+    while let Some(precomputed_block) = block_parser.next().await.unwrap() {
+        assert_eq!(
+            BlockHash::genesis_state_hash(&precomputed_block),
+            NetworkProfile::mainnet().genesis_hash.unwrap()
+        );
+        num_blocks += 1;
+    }
+    assert_eq!(num_blocks, block_parser.total_num_blocks);
+}
+
+#[tokio::test]
+async fn blocks_of_another_network_are_rejected() {
+    let network_profile: NetworkProfile = serde_yaml::from_str(
+        "
+genesis_hash: 3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC
+k: 290
+slots_per_epoch: 7140
+slot_duration: 180000
+coinbase_amount: 720000000000
+supercharged_coinbase_amount: 1440000000000
+genesis_timestamp: 1615939200000
+",
+    )
+    .unwrap();
+    assert_eq!(network_profile.network, Network::Custom);
+
+    let tmp_dir = temp_dir("other-network-test");
+    let blocks_dir = tmp_dir.path();
+    let quarantine_dir = blocks_dir.join("quarantine");
+
+    let file_name = "mainnet-105491-3NKizDx3nnhXha2WqHDNUvJk9jW7GsonsEGYs26tCPW2Wow1ZoR3.json";
+    let block_file = blocks_dir.join(file_name);
+    std::fs::copy(
+        PathBuf::from("./tests/data/sequential_blocks").join(file_name),
+        &block_file,
+    )
+    .unwrap();
+
+    // mainnet blocks are skipped and quarantined
+    let mut block_parser =
+        BlockParser::new(blocks_dir, &network_profile, MAINNET_CANONICAL_THRESHOLD).unwrap();
+    block_parser.quarantine_dir = Some(quarantine_dir.clone());
+    assert!(block_parser.next().await.unwrap().is_none());
+    assert!(!block_file.exists());
+    assert!(quarantine_dir.join(file_name).exists());
+}
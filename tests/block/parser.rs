@@ -1,7 +1,9 @@
-use mina_indexer::block::parser::BlockParser;
+use mina_indexer::block::{parse_file, parser::BlockParser, BlockParseError};
 use std::path::PathBuf;
 use tokio::time::Instant;
 
+use crate::helpers::temp_dir;
+
 #[tokio::test]
 async fn representative_benches() {
     let start = Instant::now();
@@ -59,3 +61,83 @@ async fn get_global_slot_since_genesis() {
     );
     assert_eq!(block.global_slot_since_genesis(), 155140);
 }
+
+#[tokio::test]
+async fn state_hash_from_protocol_state() {
+    let blocks_dir = temp_dir("state-hash-test");
+
+    let block_file = PathBuf::from(
+        "./tests/data/canonical_chain_discovery/contiguous/mainnet-2-3NLyWnjZqUECniE1q719CoLmes6WDQAod4vrTeLfN7XXJbHv6EHH.json",
+    );
+    let block = parse_file(&block_file).await.unwrap();
+    assert_eq!(
+        block.state_hash,
+        "3NLyWnjZqUECniE1q719CoLmes6WDQAod4vrTeLfN7XXJbHv6EHH"
+    );
+
+    // files named without a state hash get the computed one
+    let unnamed_file = blocks_dir.path().join("mainnet-2.json");
+    tokio::fs::copy(&block_file, &unnamed_file).await.unwrap();
+    assert_eq!(parse_file(&unnamed_file).await.unwrap(), block);
+
+    // files named with another block's state hash are rejected
+    let misnamed_file = blocks_dir
+        .path()
+        .join("mainnet-2-3NKd5So3VNqGZtRZiWsti4yaEe1fX79yz5TbfG6jBZqgMnCQQp3R.json");
+    tokio::fs::copy(&block_file, &misnamed_file).await.unwrap();
+    assert!(parse_file(&misnamed_file).await.is_err());
+}
+
+#[tokio::test]
+async fn quarantines_malformed_blocks() {
+    let tmp_dir = temp_dir("quarantine-test");
+    let blocks_dir = tmp_dir.path();
+    let quarantine_dir = blocks_dir.join("quarantine");
+
+    let block_file = PathBuf::from(
+        "./tests/data/canonical_chain_discovery/contiguous/mainnet-2-3NLyWnjZqUECniE1q719CoLmes6WDQAod4vrTeLfN7XXJbHv6EHH.json",
+    );
+    let valid_file =
+        blocks_dir.join("mainnet-2-3NLyWnjZqUECniE1q719CoLmes6WDQAod4vrTeLfN7XXJbHv6EHH.json");
+    tokio::fs::copy(&block_file, &valid_file).await.unwrap();
+
+    // files named with another length are rejected
+    let misnamed_file = blocks_dir.join("mainnet-3.json");
+    tokio::fs::copy(&block_file, &misnamed_file).await.unwrap();
+    assert!(matches!(
+        parse_file(&misnamed_file).await,
+        Err(BlockParseError::LengthMismatch {
+            filename_length: 3,
+            blockchain_length: 2
+        })
+    ));
+
+    let malformed_file =
+        blocks_dir.join("mainnet-3-3NKd5So3VNqGZtRZiWsti4yaEe1fX79yz5TbfG6jBZqgMnCQQp3R.json");
+    tokio::fs::write(&malformed_file, "{ not a block }")
+        .await
+        .unwrap();
+    assert!(matches!(
+        parse_file(&malformed_file).await,
+        Err(BlockParseError::Json(_))
+    ));
+
+    // bad files are skipped and quarantined
+    let mut block_parser = BlockParser::new_testing(blocks_dir).unwrap();
+    block_parser.quarantine_dir = Some(quarantine_dir.clone());
+
+    let mut state_hashes = vec![];
+    while let Some(precomputed_block) = block_parser.next().await.unwrap() {
+        state_hashes.push(precomputed_block.state_hash);
+    }
+    assert_eq!(
+        state_hashes,
+        vec!["3NLyWnjZqUECniE1q719CoLmes6WDQAod4vrTeLfN7XXJbHv6EHH".to_string()]
+    );
+
+    for bad_file in [misnamed_file, malformed_file] {
+        assert!(!bad_file.exists());
+        assert!(quarantine_dir.join(bad_file.file_name().unwrap()).exists());
+    }
+    assert!(valid_file.exists());
+}
@@ -0,0 +1,61 @@
+use std::path::PathBuf;
+
+use mina_indexer::{
+    block::{parser::BlockParser, signed_command::SignedCommand, BlockHash},
+    state::ledger::command::UserCommandWithStatus,
+    store::{IndexerStore, TransactionKey},
+};
+
+use crate::helpers::temp_dir;
+
+#[tokio::test]
+async fn index_by_sender_and_receiver() {
+    let store_dir = temp_dir("account-transactions-test");
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+
+    let db = IndexerStore::new(store_dir.path()).unwrap();
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // mainnet-105490-3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC.json
+    let block = block_parser
+        .get_precomputed_block("3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC")
+        .await
+        .unwrap();
+    let commands = block.commands();
+    assert!(!commands.is_empty());
+
+    for cmd in commands.clone() {
+        db.put_tx(
+            block.blockchain_length,
+            block.timestamp(),
+            block.global_slot_since_genesis(),
+            &BlockHash(block.state_hash.clone()),
+            cmd,
+        )
+        .unwrap();
+    }
+
+    for cmd in commands {
+        let signed_command = SignedCommand::from_user_command(UserCommandWithStatus(cmd));
+        let key = TransactionKey::new(
+            block.blockchain_length,
+            block.timestamp(),
+            signed_command.hash_signed_command().unwrap(),
+            block.state_hash.clone(),
+        );
+
+        let from_keys = db.get_tx_keys_from(&signed_command.source_pk()).unwrap();
+        let to_keys = db.get_tx_keys_to(&signed_command.receiver_pk()).unwrap();
+        assert!(from_keys.contains(&key));
+        assert!(to_keys.contains(&key));
+
+        // only the account's own transactions are indexed under it
+        for key in from_keys {
+            let tx = db.get_tx(&key).unwrap().unwrap();
+            let tx = SignedCommand::from_user_command(UserCommandWithStatus(tx.command));
+            assert!(tx.source_pk() == signed_command.source_pk());
+        }
+        let tx = db.get_tx(&key).unwrap().unwrap();
+        assert!(tx.state_hash == BlockHash(block.state_hash.clone()));
+    }
+}
@@ -2,6 +2,7 @@ use std::{collections::HashMap, path::PathBuf};
 
 use mina_indexer::{
     block::{parser::BlockParser, store::BlockStore},
+    network::NetworkProfile,
     store::IndexerStore,
     MAINNET_CANONICAL_THRESHOLD,
 };
@@ -19,7 +20,12 @@ async fn rocksdb() {
     tokio::fs::create_dir(&store_dir).await.unwrap_or(());
 
     let db = IndexerStore::new(&store_dir).unwrap();
-    let mut bp = BlockParser::new(log_dir, MAINNET_CANONICAL_THRESHOLD).unwrap();
+    let mut bp = BlockParser::new(
+        log_dir,
+        &NetworkProfile::mainnet(),
+        MAINNET_CANONICAL_THRESHOLD,
+    )
+    .unwrap();
 
     let mut blocks = HashMap::new();
 
@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+
+use mina_indexer::{
+    block::{parser::BlockParser, store::BlockStore},
+    store::{block_index_key, IndexerStore},
+};
+use rocksdb::{Direction, IteratorMode};
+
+use crate::helpers::temp_dir;
+
+#[tokio::test]
+async fn height_and_slot_index() {
+    let store_dir = temp_dir("block-index-test");
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+
+    let db = IndexerStore::new(store_dir.path()).unwrap();
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    let mut blocks = vec![];
+    while let Some(block) = block_parser.next().await.unwrap() {
+        db.add_block(&block).unwrap();
+        blocks.push(block);
+    }
+
+    // every block is indexed by height and slot, in sequential order
+    let mut expected: Vec<(u32, String)> = blocks
+        .iter()
+        .map(|block| (block.blockchain_length, block.state_hash.clone()))
+        .collect();
+    expected.sort();
+
+    let indexed: Vec<(u32, String)> = db
+        .iterator_cf("blocks-height")
+        .map(|entry| {
+            let (key, value) = entry.unwrap();
+            let height = std::str::from_utf8(&key[..12]).unwrap().parse().unwrap();
+            (height, String::from_utf8(value.to_vec()).unwrap())
+        })
+        .collect();
+    assert_eq!(indexed, expected);
+    assert_eq!(db.iterator_cf("blocks-slot").count(), blocks.len());
+
+    // seeking to a height only visits blocks at or above it
+    let mut iter = db.iterator_cf("blocks-height");
+    let key = block_index_key(105496, "");
+    iter.set_mode(IteratorMode::From(&key, Direction::Forward));
+    for entry in iter {
+        let (key, _) = entry.unwrap();
+        let height: u32 = std::str::from_utf8(&key[..12]).unwrap().parse().unwrap();
+        assert!(height >= 105496);
+    }
+}
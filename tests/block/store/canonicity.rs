@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use mina_indexer::{
+    block::{parser::BlockParser, store::BlockStore, BlockHash},
+    state::{ledger::store::LedgerStore, Canonicity},
+    store::IndexerStore,
+};
+
+use crate::helpers::temp_dir;
+
+#[tokio::test]
+async fn canonicity_column_family() {
+    let store_dir = temp_dir("canonicity-test");
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+
+    let db = IndexerStore::new(store_dir.path()).unwrap();
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // mainnet-105490-3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC.json
+    let block = block_parser
+        .get_precomputed_block("3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC")
+        .await
+        .unwrap();
+    let state_hash = BlockHash(block.state_hash.clone());
+    db.add_block(&block).unwrap();
+    assert_eq!(db.get_canonicity(&state_hash).unwrap(), None);
+
+    // canonicity is stored apart from the block
+    db.set_canonicity(&state_hash, Canonicity::Orphaned)
+        .unwrap();
+    assert_eq!(
+        db.get_canonicity(&state_hash).unwrap(),
+        Some(Canonicity::Orphaned)
+    );
+    assert_eq!(db.get_block(&state_hash).unwrap(), Some(block.clone()));
+
+    // indexing a canonical block marks it canonical and maps its height to it
+    db.add_canonical_block(
+        block.blockchain_length,
+        block.global_slot_since_genesis(),
+        &state_hash,
+    )
+    .unwrap();
+    assert_eq!(
+        db.get_canonicity(&state_hash).unwrap(),
+        Some(Canonicity::Canonical)
+    );
+    assert!(
+        db.get_canonical_hash_at_height(block.blockchain_length)
+            .unwrap()
+            == Some(state_hash)
+    );
+}
@@ -0,0 +1,29 @@
+use mina_indexer::store::{IndexerStore, DB_VERSION};
+
+use crate::helpers::temp_dir;
+
+#[test]
+fn db_version_marker() {
+    let store_dir = temp_dir("db-version-test");
+
+    // a fresh db is marked with the current version
+    let db = IndexerStore::new(store_dir.path()).unwrap();
+    assert_eq!(db.db_version().unwrap(), Some(DB_VERSION));
+    drop(db);
+
+    // and reopens
+    let db = IndexerStore::new(store_dir.path()).unwrap();
+    assert_eq!(db.db_version().unwrap(), Some(DB_VERSION));
+
+    // a db of another version doesn't
+    let metadata_cf = db.database.cf_handle("metadata").unwrap();
+    db.database
+        .put_cf(
+            &metadata_cf,
+            b"db-version",
+            bcs::to_bytes(&(DB_VERSION + 1)).unwrap(),
+        )
+        .unwrap();
+    drop(db);
+    assert!(IndexerStore::new(store_dir.path()).is_err());
+}
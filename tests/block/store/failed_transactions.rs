@@ -0,0 +1,69 @@
+use std::{path::PathBuf, sync::Arc};
+
+use mina_indexer::{
+    block::{parser::BlockParser, signed_command::SignedCommand, store::BlockStore, BlockHash},
+    gql::root::{create_schema, Context},
+    network::NetworkProfile,
+    state::ledger::command::UserCommandWithStatus,
+    store::{IndexerStore, TransactionKey},
+};
+
+use crate::helpers::temp_dir;
+
+#[tokio::test]
+async fn failure_reasons_are_stored() {
+    let store_dir = temp_dir("failed-transactions-test");
+    let log_dir = PathBuf::from("./tests/data/canonical_chain_discovery/contiguous");
+
+    let db = IndexerStore::new(store_dir.path()).unwrap();
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // mainnet-3-3NKd5So3VNqGZtRZiWsti4yaEe1fX79yz5TbfG6jBZqgMnCQQp3R.json
+    // contains 4 failed payments
+    let block = block_parser
+        .get_precomputed_block("3NKd5So3VNqGZtRZiWsti4yaEe1fX79yz5TbfG6jBZqgMnCQQp3R")
+        .await
+        .unwrap();
+    let state_hash = BlockHash(block.state_hash.clone());
+    db.add_block(&block).unwrap();
+
+    for cmd in block.commands() {
+        db.put_tx(
+            block.blockchain_length,
+            block.timestamp(),
+            block.global_slot_since_genesis(),
+            &state_hash,
+            cmd.clone(),
+        )
+        .unwrap();
+
+        let key = TransactionKey::new(
+            block.blockchain_length,
+            block.timestamp(),
+            SignedCommand::from_user_command(UserCommandWithStatus(cmd))
+                .hash_signed_command()
+                .unwrap(),
+            state_hash.0.clone(),
+        );
+        assert_eq!(
+            db.get_tx(&key).unwrap().unwrap().failure_reason,
+            Some("Amount_insufficient_to_create_account".to_string())
+        );
+    }
+
+    // the gql transaction resolver reads the stored reason
+    let schema = create_schema();
+    let ctx = Context::new(Arc::new(db), NetworkProfile::mainnet());
+    let query = "{ transactions(query: { from: \"B62qre3erTHfzQckNuibViWQGyyKwZseztqrjPZBv6SQF384Rg6ESAy\" }) { status failureReason } }";
+    let (res, errors) =
+        juniper::execute_sync(query, None, &schema, &juniper::Variables::new(), &ctx).unwrap();
+    assert!(errors.is_empty());
+
+    let res = serde_json::to_value(&res).unwrap();
+    let transactions = res["transactions"].as_array().unwrap();
+    assert_eq!(transactions.len(), 4);
+    for tx in transactions {
+        assert_eq!(tx["status"], "FAILED");
+        assert_eq!(tx["failureReason"], "Amount_insufficient_to_create_account");
+    }
+}
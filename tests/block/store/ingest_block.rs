@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+
+use mina_indexer::{
+    block::{parser::BlockParser, signed_command::SignedCommand, store::BlockStore, BlockHash},
+    network::NetworkProfile,
+    state::{
+        ledger::{command::UserCommandWithStatus, store::LedgerStore},
+        Canonicity,
+    },
+    store::{block_index_key, IndexerStore},
+};
+
+use crate::helpers::temp_dir;
+
+#[tokio::test]
+async fn atomic_ingestion_and_recovery() {
+    let store_dir = temp_dir("ingest-block-test");
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+
+    let db = IndexerStore::new(store_dir.path()).unwrap();
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // mainnet-105490-3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC.json
+    let block = block_parser
+        .get_precomputed_block("3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC")
+        .await
+        .unwrap();
+    let state_hash = BlockHash(block.state_hash.clone());
+
+    // everything derived from the block is written together
+    let network = NetworkProfile::mainnet();
+    db.ingest_block(&block, &network, true, &[]).unwrap();
+    assert_eq!(db.get_block(&state_hash).unwrap(), Some(block.clone()));
+    assert_eq!(
+        db.get_canonicity(&state_hash).unwrap(),
+        Some(Canonicity::Canonical)
+    );
+    assert!(
+        db.get_canonical_hash_at_height(block.blockchain_length)
+            .unwrap()
+            == Some(state_hash.clone())
+    );
+    for cmd in block.commands() {
+        let hash = SignedCommand::from_user_command(UserCommandWithStatus(cmd))
+            .hash_signed_command()
+            .unwrap();
+        assert!(db
+            .get_tx_blocks(&hash)
+            .unwrap()
+            .iter()
+            .any(|(tx_state_hash, _)| *tx_state_hash == state_hash));
+    }
+    assert!(db.find_incomplete_blocks().unwrap().is_empty());
+    assert!(db.is_dirty().unwrap());
+
+    // a block written without its post balances is completed
+    let mut next_block_parser = BlockParser::new_testing(&log_dir).unwrap();
+    let half_written = next_block_parser
+        .get_precomputed_block("3NKizDx3nnhXha2WqHDNUvJk9jW7GsonsEGYs26tCPW2Wow1ZoR3")
+        .await
+        .unwrap();
+    let half_written_hash = BlockHash(half_written.state_hash.clone());
+    db.add_block(&half_written).unwrap();
+    assert!(db.find_incomplete_blocks().unwrap() == vec![half_written_hash.clone()]);
+
+    db.recover_incomplete_blocks(&network).unwrap();
+    assert!(db.find_incomplete_blocks().unwrap().is_empty());
+    assert!(!db.is_dirty().unwrap());
+
+    // index entries without a block are removed
+    for (cf, n) in [("blocks-height", 105500), ("blocks-slot", 160000)] {
+        let cf_handle = db.database.cf_handle(cf).unwrap();
+        db.database
+            .put_cf(&cf_handle, block_index_key(n, "3NKmissing"), "3NKmissing")
+            .unwrap();
+    }
+    assert!(db.find_incomplete_blocks().unwrap() == vec![BlockHash("3NKmissing".to_string())]);
+
+    // but only if the db wasn't marked clean
+    db.recover_incomplete_blocks(&network).unwrap();
+    assert!(db.find_incomplete_blocks().unwrap() == vec![BlockHash("3NKmissing".to_string())]);
+
+    let metadata_cf = db.database.cf_handle("metadata").unwrap();
+    db.database.put_cf(&metadata_cf, b"dirty", b"").unwrap();
+    db.recover_incomplete_blocks(&network).unwrap();
+    assert!(db.find_incomplete_blocks().unwrap().is_empty());
+    assert!(db.iterator_cf("blocks-slot").all(|entry| {
+        let (_, state_hash) = entry.unwrap();
+        state_hash.as_ref() != b"3NKmissing"
+    }));
+}
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use mina_indexer::{
+    block::{parser::BlockParser, store::BlockStore, BlockHash},
+    store::IndexerStore,
+};
+
+use crate::helpers::temp_dir;
+
+#[tokio::test]
+async fn invalid_signatures_column_family() {
+    let store_dir = temp_dir("invalid-signatures-test");
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+
+    let db = IndexerStore::new(store_dir.path()).unwrap();
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // mainnet-105490-3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC.json
+    let block = block_parser
+        .get_precomputed_block("3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC")
+        .await
+        .unwrap();
+    let state_hash = BlockHash(block.state_hash.clone());
+    db.add_block(&block).unwrap();
+    assert_eq!(db.get_invalid_signatures(&state_hash).unwrap(), None);
+    assert!(db.get_blocks_with_invalid_signatures().unwrap().is_empty());
+    assert_eq!(db.get_num_blocks_with_invalid_signatures().unwrap(), 0);
+
+    // flagged blocks keep the hashes of their invalid commands
+    let tx_hashes = vec!["CkpZZsSm9hQpGkGzMi8rcsQEWPZwGJXktiqGYADNwLoBeeamhzqnX".to_string()];
+    db.set_invalid_signatures(&state_hash, &tx_hashes).unwrap();
+    assert_eq!(
+        db.get_invalid_signatures(&state_hash).unwrap(),
+        Some(tx_hashes.clone())
+    );
+    assert!(db.get_blocks_with_invalid_signatures().unwrap() == vec![state_hash.clone()]);
+    assert_eq!(db.get_num_blocks_with_invalid_signatures().unwrap(), 1);
+
+    // flagging the same block again doesn't recount it
+    db.set_invalid_signatures(&state_hash, &tx_hashes).unwrap();
+    assert_eq!(db.get_num_blocks_with_invalid_signatures().unwrap(), 1);
+}
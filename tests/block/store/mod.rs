@@ -1 +1,10 @@
+mod account_transactions;
 mod add_and_get_blocks;
+mod block_index;
+mod canonicity;
+mod db_version;
+mod failed_transactions;
+mod ingest_block;
+mod invalid_signatures;
+mod transaction_hash;
+mod tx_canonicity;
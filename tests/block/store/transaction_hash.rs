@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+
+use mina_indexer::{
+    block::{parser::BlockParser, signed_command::SignedCommand, BlockHash},
+    state::ledger::command::UserCommandWithStatus,
+    store::{IndexerStore, TransactionKey},
+};
+
+use crate::helpers::temp_dir;
+
+#[tokio::test]
+async fn index_by_hash() {
+    let store_dir = temp_dir("transaction-hash-test");
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+
+    let db = IndexerStore::new(store_dir.path()).unwrap();
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // mainnet-105490-3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC.json
+    let block = block_parser
+        .get_precomputed_block("3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC")
+        .await
+        .unwrap();
+    let state_hash = BlockHash(block.state_hash.clone());
+    let cmd = block.commands().first().cloned().unwrap();
+
+    db.put_tx(
+        block.blockchain_length,
+        block.timestamp(),
+        block.global_slot_since_genesis(),
+        &state_hash,
+        cmd.clone(),
+    )
+    .unwrap();
+
+    let hash = SignedCommand::from_user_command(UserCommandWithStatus(cmd.clone()))
+        .hash_signed_command()
+        .unwrap();
+    let key = TransactionKey::new(
+        block.blockchain_length,
+        block.timestamp(),
+        hash.clone(),
+        state_hash.0.clone(),
+    );
+    assert!(db.get_tx_blocks(&hash).unwrap() == vec![(state_hash.clone(), key.clone())]);
+    assert!(db.get_tx(&key).unwrap().unwrap().state_hash == state_hash);
+
+    // the same command included in a sibling block with the same timestamp
+    let fork_hash = BlockHash("3NKfork".to_string());
+    let fork_key = TransactionKey::new(
+        block.blockchain_length,
+        block.timestamp(),
+        hash.clone(),
+        fork_hash.0.clone(),
+    );
+    db.put_tx(
+        block.blockchain_length,
+        block.timestamp(),
+        block.global_slot_since_genesis(),
+        &fork_hash,
+        cmd,
+    )
+    .unwrap();
+
+    let tx_blocks = db.get_tx_blocks(&hash).unwrap();
+    assert_eq!(tx_blocks.len(), 2);
+    assert!(tx_blocks.contains(&(state_hash.clone(), key.clone())));
+    assert!(tx_blocks.contains(&(fork_hash.clone(), fork_key.clone())));
+
+    // neither overwrites the other
+    assert!(db.get_tx(&key).unwrap().unwrap().state_hash == state_hash);
+    assert!(db.get_tx(&fork_key).unwrap().unwrap().state_hash == fork_hash);
+
+    // unknown hashes have no blocks
+    assert!(db.get_tx_blocks("CkpUnknown").unwrap().is_empty());
+}
@@ -0,0 +1,162 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use mina_indexer::{
+    block::{parser::BlockParser, signed_command::SignedCommand, store::BlockStore, BlockHash},
+    gql::root::{create_schema, Context, Schema},
+    network::NetworkProfile,
+    state::{ledger::command::UserCommandWithStatus, Canonicity, IndexerState},
+    store::{IndexerStore, TransactionKey},
+};
+
+use crate::helpers::temp_dir;
+
+#[tokio::test]
+async fn resolved_through_containing_block() {
+    let store_dir = temp_dir("tx-canonicity-test");
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+
+    let db = IndexerStore::new(store_dir.path()).unwrap();
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // mainnet-105490-3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC.json
+    let block = block_parser
+        .get_precomputed_block("3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC")
+        .await
+        .unwrap();
+    let state_hash = BlockHash(block.state_hash.clone());
+    db.add_block(&block).unwrap();
+
+    let cmd = block.commands().first().cloned().unwrap();
+    db.put_tx(
+        block.blockchain_length,
+        block.timestamp(),
+        block.global_slot_since_genesis(),
+        &state_hash,
+        cmd.clone(),
+    )
+    .unwrap();
+
+    let hash = SignedCommand::from_user_command(UserCommandWithStatus(cmd))
+        .hash_signed_command()
+        .unwrap();
+    let key = TransactionKey::new(
+        block.blockchain_length,
+        block.timestamp(),
+        hash,
+        state_hash.0.clone(),
+    );
+    let tx = db.get_tx(&key).unwrap().unwrap();
+    let tx_state_hash = tx.state_hash;
+    assert!(tx_state_hash == state_hash);
+    assert_eq!(tx.global_slot, block.global_slot_since_genesis());
+
+    // the transaction follows its block's canonicity
+    assert_eq!(db.get_canonicity(&tx_state_hash).unwrap(), None);
+
+    db.set_canonicity(&state_hash, Canonicity::Orphaned)
+        .unwrap();
+    assert_eq!(
+        db.get_canonicity(&tx_state_hash).unwrap(),
+        Some(Canonicity::Orphaned)
+    );
+
+    db.set_canonicity(&state_hash, Canonicity::Canonical)
+        .unwrap();
+    assert_eq!(
+        db.get_canonicity(&tx_state_hash).unwrap(),
+        Some(Canonicity::Canonical)
+    );
+}
+
+/// Number of transactions returned by the gql `canonical` filter,
+/// by the state hash of their containing block
+fn tx_block_counts(schema: &Schema, ctx: &Context, canonical: bool) -> HashMap<String, usize> {
+    let query = format!(
+        "{{ transactions(query: {{ canonical: {canonical} }}, limit: 1000) {{ blockStateHash canonical }} }}"
+    );
+    let (res, errors) =
+        juniper::execute_sync(&query, None, schema, &juniper::Variables::new(), ctx).unwrap();
+    assert!(errors.is_empty());
+
+    let res = serde_json::to_value(&res).unwrap();
+    let mut counts = HashMap::new();
+    for tx in res["transactions"].as_array().unwrap() {
+        assert_eq!(tx["canonical"], canonical);
+        *counts
+            .entry(tx["blockStateHash"].as_str().unwrap().to_owned())
+            .or_default() += 1;
+    }
+    counts
+}
+
+#[tokio::test]
+async fn canonical_filter_follows_update_canonical() {
+    let store_dir = temp_dir("tx-canonical-filter-test");
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // root_block = mainnet-105489-3NK4huLvUDiL4XuCUcyrWCKynmvhqfKsx5h2MfBXVVUq2Qwzi5uT.json
+    let root_block = block_parser
+        .get_precomputed_block("3NK4huLvUDiL4XuCUcyrWCKynmvhqfKsx5h2MfBXVVUq2Qwzi5uT")
+        .await
+        .unwrap();
+    let mut state =
+        IndexerState::new_testing(&root_block, None, Some(store_dir.path()), None).unwrap();
+
+    // keep every block pending until the canonical tip is updated below
+    state.canonical_update_threshold = u32::MAX;
+    let mut num_txs = 0;
+    while let Some(block) = block_parser.next().await.unwrap() {
+        if block.blockchain_length > root_block.blockchain_length {
+            num_txs += block.commands().len();
+            state.add_block(&block).unwrap();
+        }
+    }
+
+    let schema = create_schema();
+    let ctx = Context::new(
+        state.indexer_store.clone().unwrap(),
+        NetworkProfile::mainnet(),
+    );
+
+    // mainnet-105494-3NKqd3XGqkLmZVmPC3iG6AnrwQoZdBKdmYTzEJT3vwwnn2H1Z4ww.json
+    // is on the best chain, its sibling
+    // mainnet-105494-3NKXsaznJ6WdyA4PHfXxn25RzVanzQsNMZrxjidbhoBug8R4LZDy.json
+    // isn't
+    let best_chain_hash = "3NKqd3XGqkLmZVmPC3iG6AnrwQoZdBKdmYTzEJT3vwwnn2H1Z4ww".to_string();
+    let orphaned_hash = "3NKXsaznJ6WdyA4PHfXxn25RzVanzQsNMZrxjidbhoBug8R4LZDy".to_string();
+
+    // every block keeps its own copy of the transactions it shares with
+    // its siblings
+    assert!(tx_block_counts(&schema, &ctx, true).is_empty());
+    let pending = tx_block_counts(&schema, &ctx, false);
+    assert_eq!(pending.values().sum::<usize>(), num_txs);
+    assert_eq!(pending.get(&best_chain_hash), Some(&2));
+    assert_eq!(pending.get(&orphaned_hash), Some(&38));
+
+    state.canonical_threshold = 2;
+    state.canonical_update_threshold = 0;
+    state.update_canonical().unwrap();
+    assert_eq!(
+        state
+            .indexer_store
+            .as_ref()
+            .unwrap()
+            .get_canonicity(&BlockHash(orphaned_hash.clone()))
+            .unwrap(),
+        Some(Canonicity::Orphaned)
+    );
+
+    // the best chain's transactions become canonical, the orphaned block's don't
+    let canonical = tx_block_counts(&schema, &ctx, true);
+    let non_canonical = tx_block_counts(&schema, &ctx, false);
+    assert_eq!(
+        canonical.values().sum::<usize>() + non_canonical.values().sum::<usize>(),
+        num_txs
+    );
+    assert_eq!(canonical.get(&best_chain_hash), Some(&2));
+    assert!(!canonical.contains_key(&orphaned_hash));
+    assert_eq!(non_canonical.get(&orphaned_hash), Some(&38));
+    assert!(!non_canonical.contains_key(&best_chain_hash));
+}
@@ -0,0 +1,10 @@
+use tempfile::TempDir;
+
+/// Creates a fresh temporary directory named after the test, removed when
+/// dropped, even if the test panics
+pub fn temp_dir(name: &str) -> TempDir {
+    tempfile::Builder::new()
+        .prefix(name)
+        .tempdir()
+        .expect("temporary directory created")
+}
@@ -1,3 +1,4 @@
 mod block;
+mod helpers;
 mod receiver;
 mod state;
@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+
+use mina_indexer::{
+    block::{parser::BlockParser, store::BlockStore, BlockHash},
+    network::NetworkProfile,
+    state::{ledger::store::LedgerStore, Canonicity, IndexerState},
+    MAINNET_CANONICAL_THRESHOLD, PRUNE_INTERVAL_DEFAULT,
+};
+
+use crate::helpers::temp_dir;
+
+/// Restores a state whose snapshot lags behind the db and catches it up with
+/// the startup blocks, including the block ingested after the snapshot
+#[tokio::test]
+async fn resume_from_store() {
+    let store_dir = temp_dir("catch-up-test");
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // root_block = mainnet-105489-3NK4huLvUDiL4XuCUcyrWCKynmvhqfKsx5h2MfBXVVUq2Qwzi5uT.json
+    let root_block = block_parser
+        .get_precomputed_block("3NK4huLvUDiL4XuCUcyrWCKynmvhqfKsx5h2MfBXVVUq2Qwzi5uT")
+        .await
+        .unwrap();
+    let mut state =
+        IndexerState::new_testing(&root_block, None, Some(store_dir.path()), None).unwrap();
+
+    for state_hash in [
+        "3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC",
+        "3NKizDx3nnhXha2WqHDNUvJk9jW7GsonsEGYs26tCPW2Wow1ZoR3",
+    ] {
+        let block = block_parser
+            .get_precomputed_block(state_hash)
+            .await
+            .unwrap();
+        state.add_block(&block).unwrap();
+    }
+    state.persist_state_snapshot().unwrap();
+
+    // mainnet-105492-3NKAqzELKDp2BbdKKwdRWEoMNehyMrxJGCoGCyH1t1PyyH7VQMgk.json
+    // is in the db, but not in the snapshot
+    let late_hash = BlockHash("3NKAqzELKDp2BbdKKwdRWEoMNehyMrxJGCoGCyH1t1PyyH7VQMgk".to_string());
+    let late_block = block_parser
+        .get_precomputed_block(&late_hash.0)
+        .await
+        .unwrap();
+    state.add_block(&late_block).unwrap();
+
+    let mut restored = IndexerState::from_state_snapshot(
+        state.indexer_store.clone().unwrap(),
+        NetworkProfile::mainnet(),
+        MAINNET_CANONICAL_THRESHOLD,
+        PRUNE_INTERVAL_DEFAULT,
+        2,
+    )
+    .unwrap();
+    assert!(!restored.root_branch.mem(&late_hash));
+
+    // only blocks longer than the root are parsed
+    let mut successive_parser =
+        BlockParser::new_successive(&log_dir, &NetworkProfile::mainnet(), 105489).unwrap();
+    assert_eq!(successive_parser.num_canonical, 0);
+    assert_eq!(successive_parser.total_num_blocks, 18);
+
+    // everything but the two blocks in the snapshot is added
+    assert_eq!(restored.catch_up(&mut successive_parser).await.unwrap(), 16);
+    assert!(restored.root_branch.mem(&late_hash));
+    assert!(restored.dangling_branches.is_empty());
+    assert_eq!(
+        restored.best_tip.state_hash,
+        BlockHash("3NKbLiBHzQrAimK7AkP8qAfQpHnezkdsSm8mkt2TzsbjsLN8Axmt".to_string())
+    );
+}
+
+/// Rebuilds the witness tree of a db without a state snapshot from its blocks
+#[tokio::test]
+async fn rebuild_from_store() {
+    let store_dir = temp_dir("rebuild-from-store-test");
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // root_block = mainnet-105489-3NK4huLvUDiL4XuCUcyrWCKynmvhqfKsx5h2MfBXVVUq2Qwzi5uT.json
+    let root_block = block_parser
+        .get_precomputed_block("3NK4huLvUDiL4XuCUcyrWCKynmvhqfKsx5h2MfBXVVUq2Qwzi5uT")
+        .await
+        .unwrap();
+    let mut state =
+        IndexerState::new_testing(&root_block, None, Some(store_dir.path()), None).unwrap();
+    while let Some(block) = block_parser.next().await.unwrap() {
+        if block.blockchain_length > root_block.blockchain_length {
+            state.add_block(&block).unwrap();
+        }
+    }
+    let best_tip = state.best_tip.state_hash.clone();
+    drop(state);
+
+    // all 18 blocks longer than the root are in the db, none in a snapshot
+    let mut rebuilt =
+        IndexerState::new_testing(&root_block, None, Some(store_dir.path()), None).unwrap();
+    assert_eq!(rebuilt.rebuild_from_store().unwrap(), 18);
+    assert!(rebuilt.dangling_branches.is_empty());
+    assert_eq!(rebuilt.best_tip.state_hash, best_tip);
+    drop(rebuilt);
+
+    // once 105494 is canonical and its siblings orphaned, the rebuild is rooted
+    // at it and the forks off its siblings are left out
+    let canonical_hash =
+        BlockHash("3NKXsaznJ6WdyA4PHfXxn25RzVanzQsNMZrxjidbhoBug8R4LZDy".to_string());
+    let mut rebuilt =
+        IndexerState::new_testing(&root_block, None, Some(store_dir.path()), None).unwrap();
+    let indexer_store = rebuilt.indexer_store.clone().unwrap();
+    let canonical_block = indexer_store.get_block(&canonical_hash).unwrap().unwrap();
+    indexer_store
+        .add_canonical_block(
+            canonical_block.blockchain_length,
+            canonical_block.global_slot_since_genesis(),
+            &canonical_hash,
+        )
+        .unwrap();
+    for orphaned_hash in [
+        "3NKqd3XGqkLmZVmPC3iG6AnrwQoZdBKdmYTzEJT3vwwnn2H1Z4ww",
+        "3NLVgiopzZW9toJV4wCkggsCZJsQ3irSL5G5KfNN5CSrPTa3evpv",
+    ] {
+        indexer_store
+            .set_canonicity(&BlockHash(orphaned_hash.to_string()), Canonicity::Orphaned)
+            .unwrap();
+    }
+
+    // only 105495 3NL4zE extends the canonical block
+    assert_eq!(rebuilt.rebuild_from_store().unwrap(), 1);
+    assert_eq!(rebuilt.root_branch.root_block().state_hash, canonical_hash);
+    assert_eq!(rebuilt.canonical_tip.state_hash, canonical_hash);
+    assert!(rebuilt.dangling_branches.is_empty());
+    assert_eq!(
+        rebuilt.best_tip.state_hash,
+        BlockHash("3NL4zEKGtSokPMy29pGv7tm8uJt8GitM9JqrRg6Lkf3tRdnwrjpF".to_string())
+    );
+}
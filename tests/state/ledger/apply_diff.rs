@@ -2,7 +2,16 @@ use std::path::PathBuf;
 
 use mina_indexer::{
     block::parser::BlockParser,
-    state::ledger::{diff::LedgerDiff, Ledger},
+    network::NetworkProfile,
+    state::ledger::{
+        account::Amount,
+        diff::{
+            account::{AccountCreationDiff, AccountDiff},
+            LedgerDiff,
+        },
+        public_key::PublicKey,
+        Ledger, LedgerError,
+    },
 };
 
 #[tokio::test]
@@ -15,7 +24,7 @@ async fn account_diffs() {
         .get_precomputed_block("3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC")
         .await
         .unwrap();
-    let diff = LedgerDiff::from_precomputed_block(&block);
+    let diff = LedgerDiff::from_precomputed_block(&block, &NetworkProfile::mainnet());
 
     let mut ledger = Ledger::from(vec![
         (
@@ -74,6 +83,7 @@ async fn account_diffs() {
 
     ledger.apply_diff(&diff).unwrap();
 
+    // only fee payers consume a nonce, once per user command
     let expected = Ledger::from(vec![
         (
             "B62qrusueb8gq1RbZWyZG9EN1eCKjbByTQ39fgiGigkvg7nJR3VdGwX",
@@ -84,13 +94,13 @@ async fn account_diffs() {
         (
             "B62qrRvo5wngd5WA1dgXkQpCdQMRDndusmjfWXWT1LgsSFFdBS9RCsV",
             843190000000,
-            Some(2),
+            Some(1),
             None,
         ),
         (
             "B62qrdhG66vK71Jbdz6Xs7cnDxQ8f6jZUFvefkp3pje4EejYUTvotGP",
             2439634213000,
-            Some(7),
+            Some(1),
             None,
         ),
         (
@@ -102,25 +112,25 @@ async fn account_diffs() {
         (
             "B62qqLa7eh6FNPH4hCw2oB7qhA5HuKtMyqnNRnD7KyGR3McaATPjahL",
             1000377787000,
-            Some(1),
+            None,
             None,
         ),
         (
             "B62qre3erTHfzQckNuibViWQGyyKwZseztqrjPZBv6SQF384Rg6ESAy",
             999997998000,
-            Some(4),
+            Some(2),
             None,
         ),
         (
             "B62qjYanmV7y9njVeH5UHkz3GYBm7xKir1rAnoY4KsEYUGLMiU45FSM",
             1000000002000,
-            Some(2),
+            None,
             None,
         ),
         (
             "B62qq66ZuaVGxVvNwR752jPoZfN4uyZWrKkLeBS8FxdG9S76dhscRLy",
-            1156800000000,
-            Some(1),
+            1155800000000,
+            None,
             None,
         ),
     ])
@@ -134,3 +144,96 @@ async fn account_diffs() {
 
     assert_eq!(ledger, expected);
 }
+
+#[tokio::test]
+async fn account_creation_fee() {
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // mainnet-105494-3NKXsaznJ6WdyA4PHfXxn25RzVanzQsNMZrxjidbhoBug8R4LZDy.json
+    let block = block_parser
+        .get_precomputed_block("3NKXsaznJ6WdyA4PHfXxn25RzVanzQsNMZrxjidbhoBug8R4LZDy")
+        .await
+        .unwrap();
+    let diff = LedgerDiff::from_precomputed_block(&block, &NetworkProfile::mainnet());
+
+    let new_accounts = [
+        "B62qjBTyJDrx7ZcpUgv79BD9fnrsNmML1kaa2MYTVbK86wYa3pfD1JF",
+        "B62qqYqrC8XFWEaQDGomK3LtCsmaF8SiCX2ADHpFwnRJB5eDLKwAGM4",
+    ]
+    .map(|pk| PublicKey::from_address(pk).unwrap());
+    let creation_diffs: Vec<AccountCreationDiff> = diff
+        .account_diffs
+        .iter()
+        .filter_map(|account_diff| match account_diff {
+            AccountDiff::AccountCreation(creation_diff) => Some(creation_diff.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        creation_diffs,
+        new_accounts
+            .iter()
+            .map(|public_key| AccountCreationDiff {
+                public_key: public_key.clone(),
+                fee: Amount(1000000000),
+            })
+            .collect::<Vec<_>>()
+    );
+
+    // the receivers get the payment amounts net of the creation fee,
+    // matching their post balances
+    let mut ledger = Ledger::new();
+    ledger.apply_diff(&diff).unwrap();
+    assert_eq!(
+        ledger.accounts.get(&new_accounts[0]).unwrap().balance,
+        Amount(224179000000)
+    );
+    assert_eq!(
+        ledger.accounts.get(&new_accounts[1]).unwrap().balance,
+        Amount(288000000)
+    );
+}
+
+#[test]
+fn internal_commands_to_missing_accounts() {
+    let receiver =
+        PublicKey::from_address("B62qrusueb8gq1RbZWyZG9EN1eCKjbByTQ39fgiGigkvg7nJR3VdGwX").unwrap();
+
+    // a coinbase receiver seen by the diff is created and pays the creation fee
+    let coinbase_diff = LedgerDiff {
+        public_keys_seen: vec![receiver.clone()],
+        account_diffs: vec![
+            AccountDiff::from_coinbase(receiver.clone(), false),
+            AccountDiff::InternalAccountCreation(AccountCreationDiff {
+                public_key: receiver.clone(),
+                fee: Amount(1000000000),
+            }),
+        ],
+    };
+    let mut ledger = Ledger::new();
+    ledger.apply_diff(&coinbase_diff).unwrap();
+    assert_eq!(
+        ledger.accounts.get(&receiver).unwrap().balance,
+        Amount(719000000000)
+    );
+
+    // a fee transfer or its creation fee to an account the diff never saw is an error
+    for account_diff in [
+        AccountDiff::from_fee_transfer(receiver.clone(), Amount(1000000)),
+        AccountDiff::InternalAccountCreation(AccountCreationDiff {
+            public_key: receiver.clone(),
+            fee: Amount(1000000000),
+        }),
+    ] {
+        let fee_transfer_diff = LedgerDiff {
+            public_keys_seen: vec![],
+            account_diffs: vec![account_diff],
+        };
+        let error = Ledger::new().apply_diff(&fee_transfer_diff).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<LedgerError>(),
+            Some(LedgerError::AccountNotFound)
+        ));
+    }
+}
@@ -1,6 +1,19 @@
 use std::path::PathBuf;
 
-use mina_indexer::{block::parser::BlockParser, state::ledger::Ledger};
+use mina_indexer::{
+    block::{parser::BlockParser, signed_command::SignedCommand},
+    network::NetworkProfile,
+    state::ledger::{
+        account::{Account, Amount},
+        command::UserCommandWithStatus,
+        diff::LedgerDiff,
+        post_balances::{PostBalance, PostBalanceUpdate},
+        public_key::PublicKey,
+        Ledger,
+    },
+    MAINNET_CANONICAL_THRESHOLD,
+};
+use mina_serialization_types::staged_ledger_diff::TransactionStatus;
 
 #[tokio::test]
 async fn post_balances() {
@@ -68,7 +81,7 @@ async fn post_balances() {
     println!("=== Initial ===");
     println!("{:?}", ledger);
 
-    ledger.apply_post_balances(&block);
+    ledger.apply_post_balances(&block).unwrap();
 
     let expected = Ledger::from(vec![
         (
@@ -85,7 +98,7 @@ async fn post_balances() {
         ),
         (
             "B62qrdhG66vK71Jbdz6Xs7cnDxQ8f6jZUFvefkp3pje4EejYUTvotGP",
-            66859024736773,
+            68299046736773,
             Some(7297),
             None,
         ),
@@ -127,3 +140,179 @@ async fn post_balances() {
 
     assert_eq!(ledger, expected);
 }
+
+#[tokio::test]
+async fn failed_commands() {
+    let log_dir = PathBuf::from("./tests/data/canonical_chain_discovery/contiguous");
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // mainnet-3-3NKd5So3VNqGZtRZiWsti4yaEe1fX79yz5TbfG6jBZqgMnCQQp3R.json
+    // contains 4 failed payments
+    let block = block_parser
+        .get_precomputed_block("3NKd5So3VNqGZtRZiWsti4yaEe1fX79yz5TbfG6jBZqgMnCQQp3R")
+        .await
+        .unwrap();
+
+    for command in block.commands() {
+        let command = UserCommandWithStatus(command);
+        assert!(!command.is_applied());
+        assert_eq!(
+            command.failure_reason(),
+            Some("Amount_insufficient_to_create_account".to_string())
+        );
+    }
+
+    let sender =
+        PublicKey::from_address("B62qre3erTHfzQckNuibViWQGyyKwZseztqrjPZBv6SQF384Rg6ESAy").unwrap();
+    let receiver =
+        PublicKey::from_address("B62qjYanmV7y9njVeH5UHkz3GYBm7xKir1rAnoY4KsEYUGLMiU45FSM").unwrap();
+    let coinbase_receiver =
+        PublicKey::from_address("B62qqSUUCnoC8Vehw5xwhrnaNxhk6Xe3FcBhngoxyXCbJBfvVhiqia1").unwrap();
+    let initial_ledger = Ledger::from(vec![(
+        "B62qre3erTHfzQckNuibViWQGyyKwZseztqrjPZBv6SQF384Rg6ESAy",
+        1440000000000,
+        None,
+        None,
+    )])
+    .unwrap();
+
+    // the fees are paid and the nonces consumed, the receiver isn't created
+    let mut ledger = initial_ledger.clone();
+    ledger.apply_post_balances(&block).unwrap();
+
+    let sender_account = ledger.accounts.get(&sender).unwrap();
+    assert_eq!(sender_account.balance, Amount(1439880000000));
+    assert_eq!(sender_account.nonce.0, 4);
+    assert!(ledger.accounts.get(&receiver).is_none());
+
+    // the new coinbase receiver pays the account creation fee
+    assert_eq!(
+        ledger.accounts.get(&coinbase_receiver).unwrap().balance,
+        Amount(719120000000)
+    );
+
+    // ledger diffs agree
+    let mut ledger = initial_ledger;
+    ledger
+        .apply_diff(&LedgerDiff::from_precomputed_block(
+            &block,
+            &NetworkProfile::mainnet(),
+        ))
+        .unwrap();
+
+    let sender_account = ledger.accounts.get(&sender).unwrap();
+    assert_eq!(sender_account.balance, Amount(1439880000000));
+    assert_eq!(sender_account.nonce.0, 4);
+    assert!(ledger.accounts.get(&receiver).is_none());
+
+    assert_eq!(
+        ledger.accounts.get(&coinbase_receiver).unwrap().balance,
+        Amount(719120000000)
+    );
+}
+
+#[tokio::test]
+async fn failed_command_without_source_balance() {
+    let log_dir = PathBuf::from("./tests/data/canonical_chain_discovery/contiguous");
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // mainnet-3-3NKd5So3VNqGZtRZiWsti4yaEe1fX79yz5TbfG6jBZqgMnCQQp3R.json
+    let block = block_parser
+        .get_precomputed_block("3NKd5So3VNqGZtRZiWsti4yaEe1fX79yz5TbfG6jBZqgMnCQQp3R")
+        .await
+        .unwrap();
+    let mut command = block.commands().first().cloned().unwrap();
+    if let TransactionStatus::Failed(_, balance_data) = &mut command.t.status.t {
+        balance_data.t.source_balance = None;
+    } else {
+        panic!("expected a failed command");
+    }
+
+    // the source is the fee payer, it gets the fee payer's balance
+    let post_balance_update =
+        PostBalanceUpdate::from_command(&UserCommandWithStatus(command)).unwrap();
+    assert_eq!(post_balance_update.fee_payer.balance, 1439990000000);
+    assert_eq!(post_balance_update.source.balance, 1439990000000);
+    assert_eq!(post_balance_update.source_nonce, 0);
+    assert_eq!(post_balance_update.receiver, None);
+}
+
+#[tokio::test]
+async fn internal_command_receivers() {
+    let log_dir = PathBuf::from("./tests/data/non_sequential_blocks");
+    let mut block_parser = BlockParser::new(
+        &log_dir,
+        &NetworkProfile::mainnet(),
+        MAINNET_CANONICAL_THRESHOLD,
+    )
+    .unwrap();
+
+    // mainnet-220897-3NL4HLb7MQrxmAqVw8D4vEXCj2tdT8zgP9DFWGRoDxP72b4wxyUw.json
+    // none of the coinbase or fee transfer receivers are party to a user command
+    let block = block_parser
+        .get_precomputed_block("3NL4HLb7MQrxmAqVw8D4vEXCj2tdT8zgP9DFWGRoDxP72b4wxyUw")
+        .await
+        .unwrap();
+
+    // fee transfers are paired in increasing compressed public key order
+    let coinbase_receiver = "B62qospDjUj43x2yMKiNehojWWRUsE1wpdUDVpfxH8V3n5Y1QgJKFfw";
+    let internal_post_balances: Vec<(String, u64)> = PostBalance::from_internal_commands(&block)
+        .into_iter()
+        .map(|post_balance| (post_balance.public_key.to_address(), post_balance.balance))
+        .collect();
+    assert_eq!(
+        internal_post_balances,
+        vec![
+            (coinbase_receiver.to_string(), 31842501008915),
+            (
+                "B62qn2Ne2JGRdbHXdfD8wkA6PTWuBjaxUDQ6QuPAmggrcYjTP3HwWkF".to_string(),
+                33061725390
+            ),
+            (coinbase_receiver.to_string(), 31843099388915),
+            (
+                "B62qpcENWiR5VKkrHscV9cWfPwNs56ExFeb94FDiVz9GeV2mBNpMCkY".to_string(),
+                66000718438840
+            ),
+            (
+                "B62qqWzWHjUmJSSB9db6BDpGjFJkRNjjtZorwJdpeASzSPHpRe4CoJS".to_string(),
+                2897396577482
+            ),
+        ]
+    );
+
+    let mut initial_ledger = Ledger::new();
+    for command in block.commands() {
+        let signed_command = SignedCommand::from_user_command(UserCommandWithStatus(command));
+        for public_key in [
+            signed_command.fee_payer(),
+            signed_command.source_pk(),
+            signed_command.receiver_pk(),
+        ] {
+            initial_ledger
+                .accounts
+                .insert(public_key.clone(), Account::empty(public_key));
+        }
+    }
+
+    // receivers missing from the ledger are created with their post balances
+    let coinbase_receiver = PublicKey::from_address(coinbase_receiver).unwrap();
+    let mut ledger = initial_ledger.clone();
+    ledger.apply_post_balances(&block).unwrap();
+    assert_eq!(
+        ledger.accounts.get(&coinbase_receiver).unwrap().balance,
+        Amount(31843099388915)
+    );
+
+    // ledger diffs burn the account creation fee of new receivers
+    let mut ledger = initial_ledger;
+    ledger
+        .apply_diff(&LedgerDiff::from_precomputed_block(
+            &block,
+            &NetworkProfile::mainnet(),
+        ))
+        .unwrap();
+    assert_eq!(
+        ledger.accounts.get(&coinbase_receiver).unwrap().balance,
+        Amount(1440000000000 + 598380000 - 1000000000)
+    );
+}
@@ -2,9 +2,13 @@ use std::{collections::HashMap, path::PathBuf};
 
 use mina_indexer::{
     block::parser::BlockParser,
+    network::NetworkProfile,
     state::ledger::{
         diff::{
-            account::{AccountDiff, DelegationDiff, PaymentDiff, UpdateType},
+            account::{
+                AccountCreationDiff, AccountDiff, DelegationDiff, FeePaymentDiff, PaymentDiff,
+                UpdateType,
+            },
             LedgerDiff,
         },
         public_key::PublicKey,
@@ -21,7 +25,7 @@ async fn account_diffs() {
         .get_precomputed_block("3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC")
         .await
         .unwrap();
-    let diff = LedgerDiff::from_precomputed_block(&block);
+    let diff = LedgerDiff::from_precomputed_block(&block, &NetworkProfile::mainnet());
 
     let mut ledger: HashMap<PublicKey, i64> = HashMap::from([
         (
@@ -98,6 +102,33 @@ async fn account_diffs() {
                 println!("delegate:  {delegate:?}");
                 println!("delegator: {delegator:?}");
             }
+            AccountDiff::InternalAccountCreation(AccountCreationDiff { public_key, fee }) => {
+                println!("\n* Internal account creation");
+                println!("public_key: {public_key:?}");
+                println!("fee:        {}", fee.0);
+            }
+            AccountDiff::AccountCreation(AccountCreationDiff { public_key, fee }) => {
+                println!("\n* Account creation");
+                println!("public_key: {public_key:?}");
+                println!("fee:        {}", fee.0);
+
+                if let Some(balance) = ledger.get_mut(&public_key) {
+                    *balance -= fee.0 as i64;
+                }
+            }
+            AccountDiff::FeePayment(FeePaymentDiff { public_key, fee }) => {
+                println!("\n* Fee payment");
+                println!("public_key: {public_key:?}");
+                println!("fee:        {}", fee.0);
+
+                if let Some(balance) = ledger.get_mut(&public_key) {
+                    if fee.0 as i64 > *balance {
+                        println!("fee exceeded balance");
+                        panic!();
+                    }
+                    *balance -= fee.0 as i64;
+                }
+            }
         }
     }
 
@@ -130,7 +161,7 @@ async fn account_diffs() {
         (
             PublicKey::from_address("B62qq66ZuaVGxVvNwR752jPoZfN4uyZWrKkLeBS8FxdG9S76dhscRLy")
                 .unwrap(),
-            156800000000,
+            155800000000,
         ),
     ]);
 
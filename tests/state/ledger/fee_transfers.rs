@@ -0,0 +1,164 @@
+use std::path::PathBuf;
+
+use mina_indexer::{
+    block::{parse_file, parser::BlockParser},
+    network::NetworkProfile,
+    state::ledger::{
+        coinbase::Coinbase,
+        command::InternalCommand,
+        diff::account::{AccountDiff, PaymentDiff, UpdateType},
+        fee_transfer::FeeTransfer,
+        public_key::PublicKey,
+    },
+    MAINNET_CANONICAL_THRESHOLD,
+};
+
+use crate::helpers::temp_dir;
+
+#[tokio::test]
+async fn fees_to_coinbase_receiver() {
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // mainnet-105490-3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC.json
+    let block = block_parser
+        .get_precomputed_block("3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC")
+        .await
+        .unwrap();
+
+    // no completed works, all fees go to the coinbase receiver
+    assert!(block.completed_works().is_empty());
+    assert_eq!(
+        FeeTransfer::from_precomputed_block(&block),
+        vec![FeeTransfer {
+            receiver: PublicKey::from_address(
+                "B62qrdhG66vK71Jbdz6Xs7cnDxQ8f6jZUFvefkp3pje4EejYUTvotGP"
+            )
+            .unwrap(),
+            fee: 22000000.into(),
+        }]
+    );
+}
+
+#[tokio::test]
+async fn snark_work_fees() {
+    let log_dir = PathBuf::from("./tests/data/non_sequential_blocks");
+    let mut block_parser = BlockParser::new(
+        &log_dir,
+        &NetworkProfile::mainnet(),
+        MAINNET_CANONICAL_THRESHOLD,
+    )
+    .unwrap();
+
+    // mainnet-220897-3NL4HLb7MQrxmAqVw8D4vEXCj2tdT8zgP9DFWGRoDxP72b4wxyUw.json
+    let block = block_parser
+        .get_precomputed_block("3NL4HLb7MQrxmAqVw8D4vEXCj2tdT8zgP9DFWGRoDxP72b4wxyUw")
+        .await
+        .unwrap();
+    assert_eq!(block.completed_works().len(), 5);
+
+    // zero fee works are omitted
+    let fee_transfers: Vec<(String, u64)> = FeeTransfer::from_precomputed_block(&block)
+        .into_iter()
+        .map(|fee_transfer| (fee_transfer.receiver.to_address(), fee_transfer.fee.0))
+        .collect();
+    assert_eq!(
+        fee_transfers,
+        vec![
+            (
+                "B62qospDjUj43x2yMKiNehojWWRUsE1wpdUDVpfxH8V3n5Y1QgJKFfw".to_string(),
+                598380000
+            ),
+            (
+                "B62qpcENWiR5VKkrHscV9cWfPwNs56ExFeb94FDiVz9GeV2mBNpMCkY".to_string(),
+                10000
+            ),
+            (
+                "B62qpcENWiR5VKkrHscV9cWfPwNs56ExFeb94FDiVz9GeV2mBNpMCkY".to_string(),
+                10000
+            ),
+            (
+                "B62qn2Ne2JGRdbHXdfD8wkA6PTWuBjaxUDQ6QuPAmggrcYjTP3HwWkF".to_string(),
+                1000000
+            ),
+            (
+                "B62qqWzWHjUmJSSB9db6BDpGjFJkRNjjtZorwJdpeASzSPHpRe4CoJS".to_string(),
+                100000
+            ),
+        ]
+    );
+
+    // supercharged coinbase without coinbase fee transfers
+    let coinbase = Coinbase::from_precomputed_block(&block);
+    assert!(coinbase.fee_transfers.is_empty());
+    assert_eq!(
+        coinbase
+            .clone()
+            .as_account_diffs(&NetworkProfile::mainnet()),
+        vec![AccountDiff::Payment(PaymentDiff {
+            public_key: PublicKey::from_address(
+                "B62qospDjUj43x2yMKiNehojWWRUsE1wpdUDVpfxH8V3n5Y1QgJKFfw"
+            )
+            .unwrap(),
+            amount: 1440000000000.into(),
+            update_type: UpdateType::Deposit,
+        })]
+    );
+
+    // the coinbase internal command carries the same net amount
+    let network = NetworkProfile::mainnet();
+    assert!(
+        InternalCommand::from_precomputed_block(&block, &network).contains(
+            &InternalCommand::Coinbase {
+                receiver: coinbase.receiver.clone(),
+                amount: coinbase.net_amount(&network),
+            }
+        )
+    );
+}
+
+#[tokio::test]
+async fn fees_across_pre_diffs() {
+    let blocks_dir = temp_dir("pre-diff-two-test");
+
+    let block_file = PathBuf::from(
+        "./tests/data/non_sequential_blocks/mainnet-220897-3NL4HLb7MQrxmAqVw8D4vEXCj2tdT8zgP9DFWGRoDxP72b4wxyUw.json",
+    );
+    let block = parse_file(&block_file).await.unwrap();
+
+    // move some completed works, commands, and the coinbase into pre_diff_two
+    // (the staged ledger diff isn't part of the protocol state, the state hash is unchanged)
+    let mut block_json: serde_json::Value =
+        serde_json::from_slice(&tokio::fs::read(&block_file).await.unwrap()).unwrap();
+    let diff = &mut block_json["staged_ledger_diff"]["diff"];
+    let pre_diff_one = diff[0].as_object_mut().unwrap();
+    let completed_works = pre_diff_one["completed_works"].as_array_mut().unwrap();
+    let completed_works_two = completed_works.split_off(3);
+    let commands = pre_diff_one["commands"].as_array_mut().unwrap();
+    let commands_two = commands.split_off(10);
+    let coinbase = std::mem::replace(&mut pre_diff_one["coinbase"], serde_json::json!(["Zero"]));
+    diff[1] = serde_json::json!({
+        "completed_works": completed_works_two,
+        "commands": commands_two,
+        "coinbase": coinbase,
+        "internal_command_balances": [],
+    });
+
+    let split_file = blocks_dir.path().join(block_file.file_name().unwrap());
+    tokio::fs::write(&split_file, serde_json::to_vec(&block_json).unwrap())
+        .await
+        .unwrap();
+    let split_block = parse_file(&split_file).await.unwrap();
+
+    assert_eq!(split_block.completed_works().len(), 5);
+    assert_eq!(split_block.completed_works(), block.completed_works());
+    assert_eq!(split_block.commands(), block.commands());
+    assert_eq!(
+        FeeTransfer::from_precomputed_block(&split_block),
+        FeeTransfer::from_precomputed_block(&block)
+    );
+    assert_eq!(
+        Coinbase::from_precomputed_block(&split_block),
+        Coinbase::from_precomputed_block(&block)
+    );
+}
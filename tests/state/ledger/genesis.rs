@@ -1,6 +1,8 @@
 use std::path::Path;
 
-use mina_indexer::state::ledger::{genesis::GenesisRoot, Ledger};
+use mina_indexer::state::ledger::{
+    account::Amount, genesis::GenesisRoot, public_key::PublicKey, Ledger,
+};
 use tokio::{fs::File, io::AsyncReadExt};
 
 const GENESIS_LEDGERS_PATH: &str = "./tests/data/genesis_ledgers";
@@ -68,6 +70,83 @@ pub async fn test_ignore_known_invalid_pks_on_mainnet() {
     let root: GenesisRoot =
         serde_json::from_str(ledger_json).expect("Genesis ledger parses into GenesisRoot");
     assert_eq!(3, root.ledger.accounts.len(), "Should contain 3 accounts");
-    let ledger: Ledger = root.ledger.into();
+    let ledger: Ledger = root.ledger.try_into().unwrap();
     assert_eq!(1, ledger.accounts.len(), "Should only be 1 account")
 }
+
+#[tokio::test]
+pub async fn genesis_accounts_keep_their_timing() {
+    let ledger_json = r#"{
+        "genesis": {
+            "genesis_state_timestamp": "2021-03-17T00:00:00Z"
+        },
+        "ledger": {
+            "name": "mainnet",
+            "accounts": [
+                {
+                    "pk": "B62qmqMrgPshhHKLJ7DqWn1KeizEgga5MuGmWb2bXajUnyivfeMW6JE",
+                    "balance": "372093",
+                    "timing": {
+                        "initial_minimum_balance": "372093",
+                        "cliff_time": "86400",
+                        "cliff_amount": "172093",
+                        "vesting_period": "10",
+                        "vesting_increment": "0.5"
+                    }
+                },
+                {"pk": "B62qmVHmj3mNhouDf1hyQFCSt3ATuttrxozMunxYMLctMvnk5y7nas1","balance":"1000"}
+            ]
+        }
+    }"#;
+    let root: GenesisRoot =
+        serde_json::from_str(ledger_json).expect("Genesis ledger parses into GenesisRoot");
+    let ledger: Ledger = root.ledger.try_into().unwrap();
+
+    let timed_pk =
+        PublicKey::from_address("B62qmqMrgPshhHKLJ7DqWn1KeizEgga5MuGmWb2bXajUnyivfeMW6JE").unwrap();
+    let timed_account = ledger.accounts.get(&timed_pk).unwrap();
+
+    // fully locked before the cliff
+    assert_eq!(timed_account.locked_balance(86399), Amount(372093000000000));
+    assert_eq!(timed_account.liquid_balance(86399), Amount(0));
+
+    // the cliff amount unlocks at the cliff, then 0.5 mina every 10 slots
+    assert_eq!(timed_account.locked_balance(86400), Amount(200000000000000));
+    assert_eq!(timed_account.locked_balance(86420), Amount(199999000000000));
+    assert_eq!(timed_account.liquid_balance(86420), Amount(172094000000000));
+
+    let untimed_pk =
+        PublicKey::from_address("B62qmVHmj3mNhouDf1hyQFCSt3ATuttrxozMunxYMLctMvnk5y7nas1").unwrap();
+    let untimed_account = ledger.accounts.get(&untimed_pk).unwrap();
+    assert_eq!(untimed_account.locked_balance(0), Amount(0));
+    assert_eq!(untimed_account.liquid_balance(0), Amount(1000000000000));
+}
+
+#[tokio::test]
+pub async fn unparsable_timing_is_an_error() {
+    let ledger_json = r#"{
+        "genesis": {
+            "genesis_state_timestamp": "2021-03-17T00:00:00Z"
+        },
+        "ledger": {
+            "name": "mainnet",
+            "accounts": [
+                {
+                    "pk": "B62qmqMrgPshhHKLJ7DqWn1KeizEgga5MuGmWb2bXajUnyivfeMW6JE",
+                    "balance": "372093",
+                    "timing": {
+                        "initial_minimum_balance": "372093",
+                        "cliff_time": "not a slot",
+                        "cliff_amount": "172093",
+                        "vesting_period": "10",
+                        "vesting_increment": "0.5"
+                    }
+                }
+            ]
+        }
+    }"#;
+    let root: GenesisRoot =
+        serde_json::from_str(ledger_json).expect("Genesis ledger parses into GenesisRoot");
+    assert!(Ledger::try_from(root.ledger.clone()).is_err());
+    assert!(root.ledger.merkle_root(1).is_err());
+}
@@ -0,0 +1,177 @@
+use std::{path::PathBuf, sync::Arc};
+
+use mina_indexer::{
+    block::{
+        parser::BlockParser, precomputed::PrecomputedBlock, signed_command::SignedCommand,
+        store::BlockStore, BlockHash,
+    },
+    gql::root::{create_schema, Context},
+    network::NetworkProfile,
+    state::ledger::{
+        account::Account, command::UserCommandWithStatus, post_balances::BlockPostBalances,
+        store::LedgerStore, Ledger,
+    },
+    state::IndexerState,
+    store::IndexerStore,
+};
+
+use crate::helpers::temp_dir;
+
+/// Ledger with empty accounts for all parties to the blocks' user commands
+fn user_command_accounts(blocks: &[PrecomputedBlock]) -> Ledger {
+    let mut ledger = Ledger::new();
+    for command in blocks.iter().flat_map(PrecomputedBlock::commands) {
+        let signed_command = SignedCommand::from_user_command(UserCommandWithStatus(command));
+        for public_key in [
+            signed_command.fee_payer(),
+            signed_command.source_pk(),
+            signed_command.receiver_pk(),
+        ] {
+            ledger
+                .accounts
+                .insert(public_key.clone(), Account::empty(public_key));
+        }
+    }
+    ledger
+}
+
+#[tokio::test]
+async fn ledger_at_height_and_slot() {
+    let store_dir = temp_dir("historical-ledger-test");
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+
+    let db = IndexerStore::new(store_dir.path()).unwrap();
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // canonical chain 105490 -> 105493
+    let mut blocks = vec![];
+    for state_hash in [
+        "3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC",
+        "3NKizDx3nnhXha2WqHDNUvJk9jW7GsonsEGYs26tCPW2Wow1ZoR3",
+        "3NKAqzELKDp2BbdKKwdRWEoMNehyMrxJGCoGCyH1t1PyyH7VQMgk",
+        "3NKakum3B2Tigw9TSsxwvXvV3x8L2LvrJ3yXFLEAJDMZu2vkn7db",
+    ] {
+        let block = block_parser
+            .get_precomputed_block(state_hash)
+            .await
+            .unwrap();
+        db.add_block(&block).unwrap();
+        db.add_post_balances(
+            &BlockHash(block.state_hash.clone()),
+            &BlockHash::previous_state_hash(&block),
+            &BlockPostBalances::from_precomputed(&block),
+        )
+        .unwrap();
+        db.add_canonical_block(
+            block.blockchain_length,
+            block.global_slot_since_genesis(),
+            &BlockHash(block.state_hash.clone()),
+        )
+        .unwrap();
+        blocks.push(block);
+    }
+
+    // only the root ledger is checkpointed, ledgers are rebuilt from post balances
+    let root_ledger = user_command_accounts(&blocks);
+    let root_hash = BlockHash(blocks[0].state_hash.clone());
+    db.add_ledger(&root_hash, root_ledger.clone()).unwrap();
+
+    let mut expected = root_ledger.clone();
+    for block in blocks.iter().skip(1).take(2) {
+        expected.apply_post_balances(block).unwrap();
+    }
+
+    let ledger = db.get_ledger_at_height(105492).unwrap().unwrap();
+    assert_eq!(ledger.accounts, expected.accounts);
+
+    let slot = blocks[2].global_slot_since_genesis();
+    let ledger = db.get_ledger_at_slot(slot).unwrap().unwrap();
+    assert_eq!(ledger.accounts, expected.accounts);
+
+    // slots past the canonical tip resolve to the tip
+    expected.apply_post_balances(&blocks[3]).unwrap();
+    let ledger = db.get_ledger_at_slot(slot + 1000).unwrap().unwrap();
+    assert_eq!(ledger.accounts, expected.accounts);
+
+    // nothing is known above the canonical tip or below the root
+    assert!(db.get_ledger_at_height(105494).unwrap().is_none());
+    assert!(db.get_ledger_at_height(105489).unwrap().is_none());
+
+    // the nearest checkpoint is used as-is
+    let checkpoint_hash = BlockHash(blocks[2].state_hash.clone());
+    db.add_ledger(&checkpoint_hash, root_ledger.clone())
+        .unwrap();
+
+    let mut expected = root_ledger;
+    expected.apply_post_balances(&blocks[3]).unwrap();
+
+    let ledger = db.get_ledger_at_height(105493).unwrap().unwrap();
+    assert_eq!(ledger.accounts, expected.accounts);
+
+    // the gql ledger query rejects negative heights and slots
+    let schema = create_schema();
+    let ctx = Context::new(Arc::new(db), NetworkProfile::mainnet());
+    for (query, num_errors) in [
+        "{ ledger(query: { height: 105493 }) { publicKey } }",
+        "{ ledger(query: { height: -1 }) { publicKey } }",
+        "{ ledger(query: { globalSlot: -1 }) { publicKey } }",
+        "{ ledger(query: { height: 105493 }, limit: -1) { publicKey } }",
+    ]
+    .into_iter()
+    .zip([0, 1, 1, 1])
+    {
+        let (_, errors) =
+            juniper::execute_sync(query, None, &schema, &juniper::Variables::new(), &ctx).unwrap();
+        assert_eq!(errors.len(), num_errors, "{query}");
+    }
+}
+
+#[tokio::test]
+async fn best_ledger_follows_best_tip() {
+    let store_dir = temp_dir("best-ledger-test");
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // root 105489 -> 105493
+    let mut blocks = vec![];
+    for state_hash in [
+        "3NK4huLvUDiL4XuCUcyrWCKynmvhqfKsx5h2MfBXVVUq2Qwzi5uT",
+        "3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC",
+        "3NKizDx3nnhXha2WqHDNUvJk9jW7GsonsEGYs26tCPW2Wow1ZoR3",
+        "3NKAqzELKDp2BbdKKwdRWEoMNehyMrxJGCoGCyH1t1PyyH7VQMgk",
+        "3NKakum3B2Tigw9TSsxwvXvV3x8L2LvrJ3yXFLEAJDMZu2vkn7db",
+    ] {
+        blocks.push(
+            block_parser
+                .get_precomputed_block(state_hash)
+                .await
+                .unwrap(),
+        );
+    }
+
+    let root_ledger = user_command_accounts(&blocks);
+    let mut state = IndexerState::new_testing(
+        &blocks[0],
+        Some(root_ledger.clone()),
+        Some(store_dir.path()),
+        None,
+    )
+    .unwrap();
+
+    // rebuilt from the root checkpoint
+    let mut expected = root_ledger;
+    for block in &blocks[1..4] {
+        state.add_block(block).unwrap();
+        expected.apply_post_balances(block).unwrap();
+    }
+    let ledger = state.best_ledger().unwrap().unwrap();
+    assert_eq!(ledger.accounts, expected.accounts);
+
+    // extended by the new best tip's post balances
+    state.add_block(&blocks[4]).unwrap();
+    expected.apply_post_balances(&blocks[4]).unwrap();
+    let ledger = state.best_ledger().unwrap().unwrap();
+    assert_eq!(ledger.accounts, expected.accounts);
+    assert!(state.best_ledger_cache.as_ref().unwrap().0 == BlockHash(blocks[4].state_hash.clone()));
+}
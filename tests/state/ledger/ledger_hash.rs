@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+
+use mina_indexer::{
+    block::parser::BlockParser,
+    state::ledger::{genesis, Ledger, LedgerError},
+    MAINNET_LEDGER_DEPTH,
+};
+
+#[tokio::test]
+async fn genesis_ledger_root() {
+    let genesis_root = genesis::parse_file(Path::new("./tests/data/genesis_ledgers/mainnet.json"))
+        .await
+        .unwrap();
+
+    // accounts at their genesis file indices hash to the genesis ledger hash
+    assert_eq!(
+        genesis_root
+            .ledger
+            .merkle_root(MAINNET_LEDGER_DEPTH)
+            .unwrap()
+            .0,
+        "jx7buQVWFLsXTtzRgSxbYcT8EYLS8KCZbLrfDcJxMtyy4thw2Ee"
+    );
+}
+
+#[tokio::test]
+async fn block_ledger_hashes() {
+    let log_dir = PathBuf::from("./tests/data/canonical_chain_discovery/contiguous");
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // mainnet-2-3NLyWnjZqUECniE1q719CoLmes6WDQAod4vrTeLfN7XXJbHv6EHH.json
+    let block = block_parser
+        .get_precomputed_block("3NLyWnjZqUECniE1q719CoLmes6WDQAod4vrTeLfN7XXJbHv6EHH")
+        .await
+        .unwrap();
+
+    // nothing has been snarked yet, the snarked ledger is the genesis ledger
+    assert_eq!(
+        block.snarked_ledger_hash().0,
+        "jx7buQVWFLsXTtzRgSxbYcT8EYLS8KCZbLrfDcJxMtyy4thw2Ee"
+    );
+    assert_eq!(
+        block.staged_ledger_hash().0,
+        "jwCtBkoHByTR65wDXSWXFJBQQ59trDsH3NfCh4czw7ur8ZtjkiV"
+    );
+
+    // an empty ledger hashes to neither
+    let computed = Ledger::new().merkle_root(MAINNET_LEDGER_DEPTH);
+    match Ledger::new().verify_hash(&block, MAINNET_LEDGER_DEPTH) {
+        Err(LedgerError::HashMismatch {
+            computed: mismatched,
+            staged,
+            snarked,
+        }) => {
+            assert_eq!(mismatched, computed);
+            assert_eq!(staged, block.staged_ledger_hash());
+            assert_eq!(snarked, block.snarked_ledger_hash());
+        }
+        result => panic!("expected a hash mismatch, got {result:?}"),
+    }
+}
@@ -1,4 +1,9 @@
 mod apply_diff;
 mod apply_post_balances;
 mod diff_from_precomputed;
+mod fee_transfers;
 mod genesis;
+mod historical;
+mod ledger_hash;
+mod reconciliation;
+mod staking_ledger;
@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+
+use mina_indexer::{
+    block::parser::BlockParser,
+    network::NetworkProfile,
+    state::ledger::{diff::LedgerDiff, genesis, reconciliation::BlockReconciliation, Ledger},
+};
+
+#[tokio::test]
+async fn failed_commands_reconcile() {
+    let log_dir = PathBuf::from("./tests/data/canonical_chain_discovery/contiguous");
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // mainnet-3-3NKd5So3VNqGZtRZiWsti4yaEe1fX79yz5TbfG6jBZqgMnCQQp3R.json
+    // contains 4 failed payments from the same fee payer
+    let block = block_parser
+        .get_precomputed_block("3NKd5So3VNqGZtRZiWsti4yaEe1fX79yz5TbfG6jBZqgMnCQQp3R")
+        .await
+        .unwrap();
+
+    let ledger = Ledger::from(vec![(
+        "B62qre3erTHfzQckNuibViWQGyyKwZseztqrjPZBv6SQF384Rg6ESAy",
+        1440000000000,
+        None,
+        None,
+    )])
+    .unwrap();
+    let reconciliation = BlockReconciliation::new(&ledger, &block, &NetworkProfile::mainnet());
+    assert!(reconciliation.is_consistent(), "{reconciliation:?}");
+    assert_eq!(reconciliation.blockchain_length, 3);
+
+    // the post balances don't depend on the starting ledger
+    let ledger = Ledger::from(vec![(
+        "B62qre3erTHfzQckNuibViWQGyyKwZseztqrjPZBv6SQF384Rg6ESAy",
+        1440000000001,
+        None,
+        None,
+    )])
+    .unwrap();
+    let reconciliation = BlockReconciliation::new(&ledger, &block, &NetworkProfile::mainnet());
+    assert_eq!(reconciliation.error, None);
+    assert_eq!(reconciliation.discrepancies.len(), 1);
+
+    let discrepancy = &reconciliation.discrepancies[0];
+    assert_eq!(
+        discrepancy.public_key,
+        "B62qre3erTHfzQckNuibViWQGyyKwZseztqrjPZBv6SQF384Rg6ESAy"
+    );
+    assert_eq!(discrepancy.field, "balance");
+    assert_eq!(discrepancy.diff_ledger, Some("1439.880000001".to_string()));
+    assert_eq!(discrepancy.post_balance_ledger, Some("1439.88".to_string()));
+}
+
+#[tokio::test]
+async fn coinbase_and_fee_transfer_reconcile() {
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // mainnet-105490-3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC.json
+    // the supercharged coinbase and the fee transfer go to one of the senders,
+    // one of the receivers is created
+    let block = block_parser
+        .get_precomputed_block("3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC")
+        .await
+        .unwrap();
+
+    // balances and nonces before the block, worked back from its post balances
+    let ledger = Ledger::from(vec![
+        (
+            "B62qrRvo5wngd5WA1dgXkQpCdQMRDndusmjfWXWT1LgsSFFdBS9RCsV",
+            69533996528740403,
+            Some(42427),
+            None,
+        ),
+        (
+            "B62qrdhG66vK71Jbdz6Xs7cnDxQ8f6jZUFvefkp3pje4EejYUTvotGP",
+            66859412523773,
+            Some(7296),
+            None,
+        ),
+        (
+            "B62qqLa7eh6FNPH4hCw2oB7qhA5HuKtMyqnNRnD7KyGR3McaATPjahL",
+            110935738000,
+            None,
+            None,
+        ),
+        (
+            "B62qre3erTHfzQckNuibViWQGyyKwZseztqrjPZBv6SQF384Rg6ESAy",
+            947719527111,
+            Some(146492),
+            None,
+        ),
+        (
+            "B62qjYanmV7y9njVeH5UHkz3GYBm7xKir1rAnoY4KsEYUGLMiU45FSM",
+            860260858,
+            None,
+            None,
+        ),
+    ])
+    .unwrap();
+    let reconciliation = BlockReconciliation::new(&ledger, &block, &NetworkProfile::mainnet());
+    assert!(reconciliation.is_consistent(), "{reconciliation:?}");
+
+    // without the coinbase the block producer's balance is off
+    let network = NetworkProfile {
+        supercharged_coinbase_amount: NetworkProfile::mainnet().coinbase_amount,
+        ..NetworkProfile::mainnet()
+    };
+    let reconciliation = BlockReconciliation::new(&ledger, &block, &network);
+    assert_eq!(reconciliation.error, None);
+    assert_eq!(reconciliation.discrepancies.len(), 1);
+
+    let discrepancy = &reconciliation.discrepancies[0];
+    assert_eq!(
+        discrepancy.public_key,
+        "B62qrdhG66vK71Jbdz6Xs7cnDxQ8f6jZUFvefkp3pje4EejYUTvotGP"
+    );
+    assert_eq!(discrepancy.field, "balance");
+    assert_eq!(discrepancy.diff_ledger, Some("67579.046736773".to_string()));
+    assert_eq!(
+        discrepancy.post_balance_ledger,
+        Some("68299.046736773".to_string())
+    );
+}
+
+#[tokio::test]
+async fn canonical_chain_reconciles() {
+    let genesis_root = genesis::parse_file(Path::new("./tests/data/genesis_ledgers/mainnet.json"))
+        .await
+        .unwrap();
+    let log_dir = PathBuf::from("./tests/data/canonical_chain_discovery/contiguous");
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // one block per length from 2 to 21, each with a coinbase and all but
+    // the empty ones with a fee transfer
+    let mut blocks = vec![];
+    while let Some(block) = block_parser.next().await.unwrap() {
+        blocks.push(block);
+    }
+    blocks.sort_by_key(|block| block.blockchain_length);
+    assert_eq!(blocks.len(), 20);
+
+    let network = NetworkProfile::mainnet();
+    let mut ledger: Ledger = genesis_root.ledger.try_into().unwrap();
+    for block in blocks {
+        let reconciliation = BlockReconciliation::new(&ledger, &block, &network);
+        assert!(reconciliation.is_consistent(), "{reconciliation:?}");
+
+        ledger
+            .apply_diff(&LedgerDiff::from_precomputed_block(&block, &network))
+            .unwrap();
+    }
+}
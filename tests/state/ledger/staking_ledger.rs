@@ -0,0 +1,97 @@
+use mina_indexer::{
+    staking_ledger::{DelegationTotals, StakingLedger, StakingLedgerAccount},
+    state::ledger::Ledger,
+    MAINNET_LEDGER_DEPTH,
+};
+use serde::Serialize;
+
+#[test]
+fn derived_delegation_totals() {
+    let delegate = "B62qrRvo5wngd5WA1dgXkQpCdQMRDndusmjfWXWT1LgsSFFdBS9RCsV";
+    let ledger = Ledger::from(vec![
+        (delegate, 1000000000000, Some(3), None),
+        (
+            "B62qrdhG66vK71Jbdz6Xs7cnDxQ8f6jZUFvefkp3pje4EejYUTvotGP",
+            2500000000,
+            None,
+            Some(delegate),
+        ),
+        (
+            "B62qqLa7eh6FNPH4hCw2oB7qhA5HuKtMyqnNRnD7KyGR3McaATPjahL",
+            1000000000,
+            None,
+            Some(delegate),
+        ),
+    ])
+    .unwrap();
+    let staking_ledger = StakingLedger::from_ledger(2, &ledger, MAINNET_LEDGER_DEPTH);
+
+    // derived from a staged ledger, it's labeled with its own Merkle root
+    let ledger_hash = ledger.merkle_root(MAINNET_LEDGER_DEPTH).0;
+    assert_eq!(staking_ledger.epoch_number, 2);
+    assert!(staking_ledger.derived);
+    assert_eq!(staking_ledger.ledger_hash, ledger_hash);
+    assert_eq!(staking_ledger.accounts.len(), 3);
+
+    for account in &staking_ledger.accounts {
+        assert_eq!(account.epoch_number, Some(2));
+        assert_eq!(account.ledger_hash, Some(ledger_hash.clone()));
+
+        if account.pk == delegate {
+            // accounts without a delegate delegate to themselves
+            assert_eq!(account.delegate, delegate);
+            assert_eq!(account.balance, "1000");
+            assert_eq!(account.nonce, Some("3".to_string()));
+            assert_eq!(
+                account.delegation_totals,
+                Some(DelegationTotals {
+                    count_delegates: 3,
+                    total_delegations: 1003500000000,
+                })
+            );
+        } else {
+            assert_eq!(account.delegate, delegate);
+            assert_eq!(
+                account.delegation_totals,
+                Some(DelegationTotals {
+                    count_delegates: 0,
+                    total_delegations: 0,
+                })
+            );
+        }
+    }
+}
+
+/// Staking ledger encoding from before derived ledgers were flagged
+#[derive(Serialize)]
+struct LegacyStakingLedger {
+    epoch_number: u32,
+    ledger_hash: String,
+    accounts: Vec<StakingLedgerAccount>,
+}
+
+#[test]
+fn legacy_staking_ledgers_decode_as_official() {
+    let ledger = Ledger::from(vec![(
+        "B62qrRvo5wngd5WA1dgXkQpCdQMRDndusmjfWXWT1LgsSFFdBS9RCsV",
+        1000000000000,
+        None,
+        None,
+    )])
+    .unwrap();
+    let staking_ledger = StakingLedger::from_ledger(2, &ledger, MAINNET_LEDGER_DEPTH);
+
+    let legacy = LegacyStakingLedger {
+        epoch_number: 2,
+        ledger_hash: "jxYFH645cwMMMDmDe7KnvTuKJ5Ev8zZbWtA73fDFn7Jyh8p6SwH".to_string(),
+        accounts: staking_ledger.accounts.clone(),
+    };
+    let decoded = StakingLedger::from_bytes(&bcs::to_bytes(&legacy).unwrap()).unwrap();
+    assert!(!decoded.derived);
+    assert_eq!(decoded.ledger_hash, legacy.ledger_hash);
+    assert_eq!(decoded.accounts, legacy.accounts);
+
+    // the current encoding round trips
+    let decoded = StakingLedger::from_bytes(&bcs::to_bytes(&staking_ledger).unwrap()).unwrap();
+    assert_eq!(decoded, staking_ledger);
+}
@@ -1,3 +1,5 @@
+mod catch_up;
 mod dangling_branches;
 mod ledger;
 mod root_branch;
+mod snapshot;
@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+
+use mina_indexer::{
+    block::{parse_file, parser::BlockParser, BlockHash},
+    network::NetworkProfile,
+    state::{
+        snapshot::{StateStore, STATE_SNAPSHOT_VERSION},
+        ExtensionType, IndexerState,
+    },
+    MAINNET_CANONICAL_THRESHOLD, PRUNE_INTERVAL_DEFAULT,
+};
+
+use crate::helpers::temp_dir;
+
+/// Saves a state with forked dangling branches waiting for their missing
+/// parents, restores it and checks the forks are still merged once 105490
+/// arrives
+#[tokio::test]
+async fn round_trip_with_dangling_branches() {
+    let store_dir = temp_dir("snapshot-round-trip-test");
+    // sequential_blocks without 105490
+    let log_dir = PathBuf::from("./tests/data/dangling_branches");
+
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+
+    // root_block = mainnet-105489-3NK4huLvUDiL4XuCUcyrWCKynmvhqfKsx5h2MfBXVVUq2Qwzi5uT.json
+    let root_block = block_parser
+        .get_precomputed_block("3NK4huLvUDiL4XuCUcyrWCKynmvhqfKsx5h2MfBXVVUq2Qwzi5uT")
+        .await
+        .unwrap();
+    let mut state =
+        IndexerState::new_testing(&root_block, None, Some(store_dir.path()), None).unwrap();
+
+    // the root's siblings never merge and 105491 onwards, with the forks at
+    // 105494, 105495 and 105496, dangle without 105490
+    while let Some(precomputed_block) = block_parser.next().await.unwrap() {
+        if precomputed_block.state_hash != root_block.state_hash {
+            state.add_block(&precomputed_block).unwrap();
+        }
+    }
+    assert_eq!(state.dangling_branches.len(), 3);
+
+    let forked_branch = &state.dangling_branches[2];
+    assert_eq!(
+        forked_branch.root_block().state_hash,
+        BlockHash("3NKizDx3nnhXha2WqHDNUvJk9jW7GsonsEGYs26tCPW2Wow1ZoR3".to_string())
+    );
+    assert_eq!(forked_branch.len(), 17);
+    assert_eq!(forked_branch.height(), 8);
+
+    let snapshot = state.to_state_snapshot();
+    assert_eq!(snapshot.version, STATE_SNAPSHOT_VERSION);
+
+    let indexer_store = state.indexer_store.clone().unwrap();
+    indexer_store.store_state_snapshot(&snapshot).unwrap();
+
+    // a snapshot of another network isn't restored
+    assert!(IndexerState::from_state_snapshot(
+        indexer_store.clone(),
+        NetworkProfile::devnet(),
+        MAINNET_CANONICAL_THRESHOLD,
+        PRUNE_INTERVAL_DEFAULT,
+        2,
+    )
+    .is_err());
+
+    let mut restored = IndexerState::from_state_snapshot(
+        indexer_store,
+        NetworkProfile::mainnet(),
+        MAINNET_CANONICAL_THRESHOLD,
+        PRUNE_INTERVAL_DEFAULT,
+        2,
+    )
+    .unwrap();
+
+    // everything round trips
+    assert_eq!(restored.best_tip.state_hash, state.best_tip.state_hash);
+    assert_eq!(
+        restored.canonical_tip.state_hash,
+        state.canonical_tip.state_hash
+    );
+    assert_eq!(restored.blocks_processed, state.blocks_processed);
+    assert_eq!(restored.diffs_map, state.diffs_map);
+    assert_eq!(restored.root_branch.leaves(), state.root_branch.leaves());
+    assert_eq!(
+        restored.dangling_branches.len(),
+        state.dangling_branches.len()
+    );
+    for (restored_branch, branch) in restored
+        .dangling_branches
+        .iter()
+        .zip(state.dangling_branches.iter())
+    {
+        assert_eq!(restored_branch.root_block(), branch.root_block());
+        assert_eq!(restored_branch.leaves(), branch.leaves());
+        assert_eq!(restored_branch.len(), branch.len());
+    }
+
+    // the missing parent merges the restored forked branch
+    let missing_block = parse_file(&PathBuf::from(
+        "./tests/data/sequential_blocks/mainnet-105490-3NKxEA9gztvEGxL4uk4eTncZAxuRmMsB8n81UkeAMevUjMbLHmkC.json",
+    ))
+    .await
+    .unwrap();
+    assert_eq!(
+        restored.add_block(&missing_block).unwrap(),
+        ExtensionType::RootComplex
+    );
+    assert_eq!(restored.dangling_branches.len(), 2);
+    assert_eq!(restored.root_branch.len(), 19);
+    for state_hash in [
+        "3NLVgiopzZW9toJV4wCkggsCZJsQ3irSL5G5KfNN5CSrPTa3evpv",
+        "3NL4zEKGtSokPMy29pGv7tm8uJt8GitM9JqrRg6Lkf3tRdnwrjpF",
+        "3NKwSR9wWhXUwobCzCLEjHc5xQmvN1qkfnxG4EGoMGYRgYY5f9EB",
+    ] {
+        assert!(restored.root_branch.mem(&BlockHash(state_hash.to_string())));
+    }
+    assert_eq!(
+        restored.root_branch.best_tip().unwrap().state_hash,
+        BlockHash("3NKbLiBHzQrAimK7AkP8qAfQpHnezkdsSm8mkt2TzsbjsLN8Axmt".to_string())
+    );
+}
+
+/// Snapshots of another version are reported, not decoded
+#[tokio::test]
+async fn other_version_is_rejected() {
+    let store_dir = temp_dir("snapshot-version-test");
+    let log_dir = PathBuf::from("./tests/data/sequential_blocks");
+
+    let mut block_parser = BlockParser::new_testing(&log_dir).unwrap();
+    let root_block = block_parser
+        .get_precomputed_block("3NK4huLvUDiL4XuCUcyrWCKynmvhqfKsx5h2MfBXVVUq2Qwzi5uT")
+        .await
+        .unwrap();
+    let state = IndexerState::new_testing(&root_block, None, Some(store_dir.path()), None).unwrap();
+    let indexer_store = state.indexer_store.clone().unwrap();
+
+    let mut snapshot = state.to_state_snapshot();
+    snapshot.version = STATE_SNAPSHOT_VERSION - 1;
+    indexer_store.store_state_snapshot(&snapshot).unwrap();
+
+    assert_eq!(
+        indexer_store.snapshot_version().unwrap(),
+        Some(STATE_SNAPSHOT_VERSION - 1)
+    );
+    assert!(indexer_store.read_snapshot().is_err());
+
+    // a current snapshot replaces it
+    indexer_store
+        .store_state_snapshot(&state.to_state_snapshot())
+        .unwrap();
+    assert_eq!(
+        indexer_store.snapshot_version().unwrap(),
+        Some(STATE_SNAPSHOT_VERSION)
+    );
+    assert!(indexer_store.read_snapshot().unwrap().is_some());
+}